@@ -1,9 +1,53 @@
-use proc_macro2::{TokenStream, Span};
+use proc_macro2::{TokenStream, TokenTree, Span, Group, Delimiter, Punct, Spacing};
 use quote::{ToTokens};
 use std::any::{Any};
 use syn::{Attribute, Ident, Lit, Meta, NestedMeta};
 
 
+/// Error in derive input, reported by `compile_error!` at tokens of offending code (attribute, field type etc.),
+/// same as `syn::Error` of newer `syn`
+pub struct DeriveError
+{
+    /// Span of first token of offending code
+    start: Span,
+
+    /// Span of last token of offending code
+    end: Span,
+
+    message: String
+}
+
+pub type DeriveResult<T> = Result<T, DeriveError>;
+
+impl DeriveError
+{
+    /// Error, which points to `tokens`
+    pub fn new_spanned<T: ToTokens>(tokens: &T, message: String) -> DeriveError
+    {
+        let spans: Vec<Span> = tokens.into_token_stream().into_iter().map(|token| token.span()).collect();
+        DeriveError
+            {
+                start: spans.first().cloned().unwrap_or(Span::call_site()),
+                end: spans.last().cloned().unwrap_or(Span::call_site()),
+                message
+            }
+    }
+
+    /// Generate `compile_error! { "<message>" }`, spanned from first to last token of offending code
+    pub fn to_compile_error(&self) -> TokenStream
+    {
+        let mut bang = Punct::new('!', Spacing::Alone);
+        bang.set_span(self.start);
+        let message = &self.message;
+        let mut group = Group::new(Delimiter::Brace, quote!( #message ));
+        group.set_span(self.end);
+
+        let tokens: Vec<TokenTree> = vec![Ident::new("compile_error", self.start).into(), bang.into(), group.into()];
+        tokens.into_iter().collect()
+    }
+}
+
+
 /// Get all attributes from `macro_name` macro declaration to list of `(name, value)` tuples
 ///
 /// Input:
@@ -30,15 +74,17 @@ use syn::{Attribute, Ident, Lit, Meta, NestedMeta};
 ///     ("required", true)
 /// ]
 ///```
-pub fn collect_attributes(macro_name: &str, attributes: &Vec<Attribute>) -> Vec<(Ident, Lit)>
+///
+/// Error (at `owner`, ex.: struct name) if there is no `macro_name` attribute.
+pub fn collect_attributes<T: ToTokens>(macro_name: &str, attributes: &Vec<Attribute>, owner: &T) -> DeriveResult<Vec<(Ident, Lit)>>
 {
     let attribute = find_attribute(macro_name, attributes);
     match attribute
         {
-            None => panic!(format!("Cannot found '{}'", macro_name)),
+            None => Err(DeriveError::new_spanned(owner, format!("Cannot found '#[{}]'", macro_name))),
             Some(attribute) =>
                 {
-                    collect_attribute(&attribute)
+                    Ok(collect_attribute(&attribute))
                 }
         }
 }
@@ -115,15 +161,15 @@ pub fn collect_attribute(attribute: &Attribute) -> Vec<(Ident, Lit)>
 /// #[multipart(name="file12")
 /// ```
 #[allow(dead_code)]
-pub fn get_string(ident: &Ident, lit: &Lit) -> String
+pub fn get_string(ident: &Ident, lit: &Lit) -> DeriveResult<String>
 {
     if let Lit::Str(ref lit_str) = lit
         {
-            return lit_str.value().clone();
+            return Ok(lit_str.value().clone());
         }
         else
         {
-            panic!("'{}' must be string, but '{}' found", ident, lit_to_string(lit));
+            Err(DeriveError::new_spanned(lit, format!("'{}' must be string, but '{}' found", ident, lit_to_string(lit))))
         }
 }
 
@@ -137,15 +183,15 @@ pub fn get_string(ident: &Ident, lit: &Lit) -> String
 /// #[multipart(debug=true)
 /// ```
 #[allow(dead_code)]
-pub fn get_bool(ident: &Ident, lit: &Lit) -> bool
+pub fn get_bool(ident: &Ident, lit: &Lit) -> DeriveResult<bool>
 {
     if let Lit::Bool(lit_bool) = lit
         {
-            return lit_bool.value.clone();
+            return Ok(lit_bool.value.clone());
         }
         else
         {
-            panic!("'{}' must be bool, but '{}' found", ident, lit_to_string(lit))
+            Err(DeriveError::new_spanned(lit, format!("'{}' must be bool, but '{}' found", ident, lit_to_string(lit))))
         }
 }
 
@@ -158,15 +204,15 @@ pub fn get_bool(ident: &Ident, lit: &Lit) -> bool
 /// #[multipart(count=12)
 /// ```
 #[allow(dead_code)]
-pub fn get_int(ident: &Ident, lit: &Lit) -> u64
+pub fn get_int(ident: &Ident, lit: &Lit) -> DeriveResult<u64>
 {
     if let Lit::Int(lit_int) = lit
         {
-            return lit_int.value().clone();
+            return Ok(lit_int.value().clone());
         }
         else
         {
-            panic!("'{}' must be int, but '{}' found", ident, lit_to_string(lit))
+            Err(DeriveError::new_spanned(lit, format!("'{}' must be int, but '{}' found", ident, lit_to_string(lit))))
         }
 }
 
@@ -178,9 +224,9 @@ pub fn get_int(ident: &Ident, lit: &Lit) -> u64
 ///               \          /
 /// #[multipart(max_size=1048576)
 #[allow(dead_code)]
-pub fn get_usize(ident: &Ident, lit: &Lit) -> usize
+pub fn get_usize(ident: &Ident, lit: &Lit) -> DeriveResult<usize>
 {
-    get_int(&ident, &lit) as usize
+    get_int(&ident, &lit).map(|value| value as usize)
 }
 
 
@@ -192,11 +238,11 @@ pub fn get_usize(ident: &Ident, lit: &Lit) -> usize
 /// #[multipart(variable_name="i")
 /// ```
 #[allow(dead_code)]
-pub fn get_ident(ident: &Ident, lit: &Lit) -> Ident
+pub fn get_ident(ident: &Ident, lit: &Lit) -> DeriveResult<Ident>
 {
-    let s = get_string(ident, lit);
+    let s = get_string(ident, lit)?;
     let result: Ident = Ident::new(s.as_str(), Span::call_site());
-    result
+    Ok(result)
 }
 
 
@@ -207,9 +253,9 @@ pub fn get_ident(ident: &Ident, lit: &Lit) -> Ident
 ///  #[multipart(default="Option::None")
 /// ```
 #[allow(dead_code)]
-pub fn get_any(_ident: &Ident, lit: &Lit) -> Box<Any>
+pub fn get_any(ident: &Ident, lit: &Lit) -> DeriveResult<Box<Any>>
 {
-    match lit
+    let value: Box<Any> = match lit
         {
             Lit::Str(lit_str) =>
                 {
@@ -217,7 +263,7 @@ pub fn get_any(_ident: &Ident, lit: &Lit) -> Box<Any>
                 }
             Lit::ByteStr(_lit_byte_str) =>
                 {
-                    panic!("Unimplemented")
+                    return Err(DeriveError::new_spanned(lit, format!("'{}' has unsupported literal '{}'", ident, lit_to_string(lit))))
                 }
             Lit::Byte(lit_byte) =>
                 {
@@ -241,9 +287,10 @@ pub fn get_any(_ident: &Ident, lit: &Lit) -> Box<Any>
                 }
             Lit::Verbatim(_lit_verbatim) =>
                 {
-                    panic!("Unimplemented")
+                    return Err(DeriveError::new_spanned(lit, format!("'{}' has unsupported literal '{}'", ident, lit_to_string(lit))))
                 }
-        }
+        };
+    Ok(value)
 }
 
 pub fn ident_to_string(ident: &Ident) -> String
@@ -40,7 +40,7 @@
 //!
 //! # Struct attributes
 //!
-//! `#[multipart(debug=false)]`
+//! `#[multipart(debug=false, proxy_prefix="Multipart", case_insensitive=false, ordered=false, require_name=false, derive_default=false, collect_results=false)]`
 //!
 //! ## `debug`
 //!
@@ -73,12 +73,16 @@
 //! ```rust,ignore
 //! impl gnitive_multipart::gnitive_multipart::MultipartParserTargetGenerated for Test
 //! {
-//!     fn content_parser_generated(&self, self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Box<ProcessContent>>
+//!     fn content_parser_generated(&mut self, self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
 //!     {
-//!         let name = headers.get_name().unwrap().as_ref();
+//!         let name: &str = match headers.get_name()
+//!             {
+//!                 Some(name) if !name.is_empty() => name.as_ref(),
+//!                 _ => return self.content_parser(self_, headers)
+//!             };
 //!         match name
 //!             {
-//!                 "file" => Some(Box::new(MultipartTestFile::new(self_.clone()))),
+//!                 "file" => Some(Rc::new(RefCell::new(MultipartTestFile::new(self_.clone())))),
 //!                 _ => self.content_parser(self_, headers)
 //!             }
 //!     }
@@ -108,14 +112,14 @@
 //!         self.processor.open(headers);
 //!     }
 //!
-//!     fn write(&mut self, headers: &Headers, data: &Vec<u8>) -> ()
+//!     fn write(&mut self, headers: &Headers, data: &[u8]) -> Result<(), ::std::io::Error>
 //!     {
-//!         self.processor.write(headers, data);
+//!         self.processor.write(headers, data)
 //!     }
 //!
-//!     fn flush(&mut self, headers: &Headers) -> ()
+//!     fn flush(&mut self, headers: &Headers) -> Result<(), ::std::io::Error>
 //!     {
-//!         self.processor.flush(headers);
+//!         self.processor.flush(headers)?;
 //!         let processor = &self.processor;
 //!         let result = Vec::<u8>::try_from(processor);
 //!         match result
@@ -123,6 +127,7 @@
 //!                 Ok(value) => self.target.borrow_mut().file = value,
 //!                 Err(error) => { let _unused = self.target.borrow_mut().error(&error.to_multipart_parse_error("#name".to_string(), processor.raw_data())); }
 //!             }
+//!         Ok(())
 //!     }
 //!
 //!     fn get_process_params(&self) -> &gnitive_multipart::gnitive_multipart::ProcessParams
@@ -134,6 +139,168 @@
 //!
 //! </details>
 //!
+//! ## `proxy_prefix`
+//!
+//! Prefix of names of generated proxy structs `<ProxyPrefix><StructName><FieldName>`.
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `Multipart`.
+//!
+//! Use it when generated names collide with another derived struct (ex.: struct `Ab` with field `c_d`
+//! and struct `AbC` with field `d` both generate `MultipartAbCD`) or with user types in the same module.
+//!
+//! ```rust,ignore
+//! #[derive(MultipartDerive)]
+//! #[multipart(proxy_prefix="__Mp")]
+//! struct AbC
+//! {
+//!     #[multipart]
+//!     pub d: String,
+//! }
+//! ```
+//!
+//! ## `case_insensitive`
+//!
+//! Match part names with `name` of fields ignoring case (ex.: part `Email` to field `email`).
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! Names of fields must differ not only by case. Parts of `Vec<NestedStruct>` fields are matched case-sensitively.
+//!
+//! ```rust,ignore
+//! #[derive(MultipartDerive)]
+//! #[multipart(case_insensitive=true)]
+//! struct Login
+//! {
+//!     #[multipart]
+//!     pub email: String,
+//! }
+//! ```
+//!
+//! ## `ordered`
+//!
+//! Parts must be received in order of fields in struct (for legacy protocols).
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! Part of field, received after part of field declared later, fires `MultipartParseError::OutOfOrder { expected, found }`.
+//! Absent (optional) fields, repeated parts of the same field and parts of not declared fields are allowed.
+//!
+//! ```rust,ignore
+//! #[derive(MultipartDerive)]
+//! #[multipart(ordered=true)]
+//! struct Legacy
+//! {
+//!     #[multipart]
+//!     pub header: String,
+//!
+//!     #[multipart]
+//!     pub payload: Vec<u8>,
+//! }
+//! ```
+//!
+//! ## Feature `serde`
+//!
+//! Generate `to_json_value(&self) -> serde_json::Value` (crate `serde_json` must be dependency of user crate), ex.: for debugging endpoints.
+//! Object contains all `#[multipart]` fields by `name`: `Vec<u8>` as base64 string, `Vec<NestedStruct>` as array of objects,
+//! `Vec<PathBuf>` as array of paths, `None` as `null`, other values as strings (by `ToString`).
+//!
+//! ```rust,ignore
+//! println!("{}", form.to_json_value());
+//! ```
+//!
+//! ## `require_name`
+//!
+//! Report part without `name` (or with empty `name`) by `MultipartParseError::MissingName`, fired by `MultipartParser`
+//! (as other field errors, see `ProcessContentHooks::take_open_error`).
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false` - such part is passed to `MultipartParserTarget::content_parser` (by default dropped).
+//!
+//! With `require_name=true` part is dropped without `content_parser` call.
+//!
+//! ```rust,ignore
+//! #[derive(MultipartDerive)]
+//! #[multipart(require_name=true)]
+//! struct Upload
+//! {
+//!     #[multipart]
+//!     pub file: Vec<u8>,
+//! }
+//! ```
+//!
+//! ## `derive_default`
+//!
+//! Generate `Default` impl, each field is `Default::default()` (ex.: to create target by `T::default()` instead of `new()`).
+//! All fields of struct must be marked with `#[multipart]` and have type with `Default`: primitive type, `String`, `Option<_>` or `Vec<_>`.
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! ```rust,ignore
+//! #[derive(MultipartDerive)]
+//! #[multipart(derive_default=true)]
+//! struct Upload
+//! {
+//!     #[multipart]
+//!     pub title: String,
+//!
+//!     #[multipart]
+//!     pub count: Option<u32>,
+//! }
+//!
+//! let target = Rc::new(RefCell::new(Upload::default()));
+//! ```
+//!
+//! ## `collect_results`
+//!
+//! Store result of each field in field `field_results: HashMap<String, Result<(), FieldError>>` of struct (by `name`),
+//! instead of `MultipartParserTarget::error` call for conversion errors (`MultipartParseError::ParseIntError`, `MultipartParseError::EmptyField`, etc.).
+//! Generated `field_result(&self, name: &str) -> Option<&Result<(), FieldError>>` returns result of field:
+//! `Ok(())` - value assigned, `Err` - value is not changed, `None` - field is not received.
+//! Fields `Vec<NestedStruct>` and `Vec<PathBuf>` are not stored. Deferred errors (`MultipartParser::defer_field_errors`) are not stored too.
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! ```rust,ignore
+//! #[derive(MultipartDerive)]
+//! #[multipart(collect_results=true)]
+//! struct Form
+//! {
+//!     field_results: HashMap<String, Result<(), FieldError>>,
+//!
+//!     #[multipart]
+//!     pub count: u32,
+//! }
+//!
+//! match form.field_result("count")
+//!     {
+//!         Some(&Err(ref error)) => println!("invalid count: {}", error.message),
+//!         _ => ()
+//!     }
+//! ```
+//!
 //! # Field attributes
 //!
 //! `#[multipart(name="file", max_size=1073741824, required=true)]`
@@ -149,6 +316,8 @@
 //! *Default*: same as field name.
 //!
 //! If `name` is not present in macro attributes, field name will be used.
+//! Empty `name` is not allowed: parts without name or with empty name (`name=""`) are always passed to
+//! `MultipartParserTarget::content_parser`.
 //!
 //! ### Example 1 (without `name`)
 //!
@@ -200,7 +369,8 @@
 //!
 //! *Default*: unlimited.
 //!
-//! If content of field exceed `max_size`, `MultipartParserTarget::error` will be called.
+//! If content of field exceed `max_size`, `MultipartParserTarget::error` will be called. Field value is not changed,
+//! even if `error` returns `OnError::ContinueWithoutError` (data after `max_size` is not kept).
 //!
 //! ## `required`
 //!
@@ -277,6 +447,278 @@
 //!
 //! </details>
 //!
+//! ## `lowercase`
+//!
+//! Convert value to lowercase, useful for case-insensitive values like emails or usernames.
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! Allowed only for `String`, `Option<String>` and `Vec<String>` fields.
+//!
+//! ```rust,ignore
+//! #[multipart(name="email", lowercase=true)]
+//! pub email: String,
+//! ```
+//!
+//! ## `lossy`
+//!
+//! Decode value by `String::from_utf8_lossy`: invalid UTF-8 sequences are replaced with `U+FFFD` instead of `ParseStrError`.
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! Allowed only for `String`, `Option<String>` and `Vec<String>` fields.
+//!
+//! ```rust,ignore
+//! #[multipart(name="note", lossy=true)]
+//! pub note: String,
+//! ```
+//!
+//! ## `form_decode`
+//!
+//! Decode value as `application/x-www-form-urlencoded`, like browser forms: `+` to space, then `%XX` sequences
+//! (ex.: `a+b%20c` to `a b c`). Invalid sequences are left as is. Only for `String`, `Option<String>` and `Vec<String>`.
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! ```rust,ignore
+//! #[multipart(name="q", form_decode=true)]
+//! pub q: String,
+//! ```
+//!
+//! ## `normalize_newlines`
+//!
+//! Rewrite line endings (ex.: mixed `\r\n` and `\n` of textarea) to `"lf"` (`\n`) or `"crlf"` (`\r\n`).
+//! Only for `String`, `Option<String>` and `Vec<String>`.
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: none - value stored as is.
+//!
+//! ```rust,ignore
+//! #[multipart(name="body", normalize_newlines="lf")]
+//! pub body: String,
+//! ```
+//!
+//! ## `non_empty`
+//!
+//! Require non-empty value (after `lowercase`), useful for fields like `username`, where present but empty value is invalid.
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! Allowed only for `String`, `Option<String>` and `Vec<String>` fields. For empty value `MultipartParseError::EmptyField(name)` is fired
+//! and field value is not changed.
+//!
+//! ```rust,ignore
+//! #[multipart(name="username", non_empty=true)]
+//! pub username: String,
+//! ```
+//!
+//! ## `finite`
+//!
+//! Reject `NaN` and infinity (`"NaN"`, `"inf"`, `"-inf"` are parsed by `str::parse` successfully), which are rarely valid form input.
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false`.
+//!
+//! Allowed only for `f32`, `f64` and `Option`, `Vec` of them. For not finite value `MultipartParseError::NonFiniteFloat(name)`
+//! is fired and field value is not changed.
+//!
+//! ```rust,ignore
+//! #[multipart(name="price", finite=true)]
+//! pub price: f64,
+//! ```
+//!
+//! ## `bool_format`
+//!
+//! Parsing of `bool` value: `"strict"` - `true` and `false` only (`bool::from_str`), `"lenient"` - also common HTML values
+//! `1`/`0`, `yes`/`no`, `y`/`n`, `on`/`off`, ignoring case (see `process_content::parse_bool_lenient`).
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `"strict"`.
+//!
+//! Allowed only for `bool`, `Option<bool>` and `Vec<bool>` fields. Other values fire `MultipartParseError::ParseBoolError`.
+//!
+//! ```rust,ignore
+//! #[multipart(name="agree", bool_format="lenient")]
+//! pub agree: bool,
+//! ```
+//!
+//! ## `empty_as_none`
+//!
+//! Empty value is `None`, for forms, which send empty string for "not provided" value.
+//!
+//! *Type*: `bool`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `false` - empty value is `Some("")` for `Option<String>` and conversion error for `Option<i32>` etc.
+//!
+//! Allowed only for `Option<_>` fields. Value is checked before conversion (and `form_decode`, `lowercase`).
+//!
+//! ```rust,ignore
+//! #[multipart(name="mid", empty_as_none=true)]
+//! pub mid: Option<String>,
+//! ```
+//!
+//! ## `from_header`
+//!
+//! Name of part header (case-insensitive), which value is converted and assigned to field instead of part body (body is ignored).
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: none (value is part body).
+//!
+//! Field is not changed if part has no such header.
+//!
+//! ```rust,ignore
+//! #[multipart(name="token", from_header="X-Token")]
+//! pub token: String,
+//! ```
+//!
+//! ## `content_type_into`
+//!
+//! Name of sibling field, where value of part `Content-Type` header (ex.: `image/png`) will be stored.
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: none.
+//!
+//! Sibling field must be `String` or `Option<String>` and is not changed if `Content-Type` not present in part.
+//!
+//! ```rust,ignore
+//! #[multipart(name="file", content_type_into="file_mime")]
+//! pub file: Vec<u8>,
+//!
+//! pub file_mime: Option<String>,
+//! ```
+//!
+//! ## `filename_into`
+//!
+//! Name of sibling field, where `filename` of part `Content-Disposition` header (ex.: `report.pdf`) will be stored.
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: none.
+//!
+//! Sibling field must be `String` or `Option<String>` and is not changed if part has no `filename`
+//! (ex.: `Option<String>` is left `None`). Decoded `filename*` is preferred (see `Headers::get_filename`).
+//!
+//! ```rust,ignore
+//! #[multipart(name="file", filename_into="file_name")]
+//! pub file: Vec<u8>,
+//!
+//! pub file_name: Option<String>,
+//! ```
+//!
+//! ## `handler`
+//!
+//! Method of struct, called with converted value instead of assignment to field (ex.: to forward value to database immediately).
+//! Method must have signature `fn <handler>(&mut self, value: <field type>)`, field is not changed.
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: none (value assigned to field).
+//!
+//! ```rust,ignore
+//! #[multipart(name="event", handler="on_event")]
+//! pub event: String,
+//!
+//! impl Form
+//! {
+//!     fn on_event(&mut self, value: String) -> () { ... }
+//! }
+//! ```
+//!
+//! ## `extensions`
+//!
+//! Comma separated list of allowed extensions of uploaded file (part after last `.` in `filename`), compared case-insensitively.
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: none (any extension allowed).
+//!
+//! If extension not in list (or `filename` has no extension), `MultipartParseError::DisallowedExtension(name, extension)` is fired
+//! by `MultipartParser` (as other field errors, see `ProcessContentHooks::take_open_error`) and field is skipped - value is not changed.
+//!
+//! ```rust,ignore
+//! #[multipart(name="doc", extensions="pdf,docx")]
+//! pub doc: Vec<u8>,
+//! ```
+//!
+//! ## `require_content_type`
+//!
+//! Required `Content-Type` of part (ex.: JSON config as text field), compared with `Content-Type` without parameters
+//! (ex.: `; charset=utf-8`) case-insensitively, before data is buffered.
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: none (any `Content-Type` allowed).
+//!
+//! If `Content-Type` differs (or absent), `MultipartParseError::DisallowedContentType(name, content_type)` is fired
+//! by `MultipartParser` (as other field errors, see `ProcessContentHooks::take_open_error`) and field is skipped - value is not changed.
+//!
+//! ```rust,ignore
+//! #[multipart(name="config", require_content_type="application/json")]
+//! pub config: String,
+//! ```
+//!
+//! ## `max_items`
+//!
+//! Maximum count of elements in `Vec<NestedStruct>` field (see below), parts with greater index are ignored.
+//! `Vec` is grown up to index of part, so limit protects from huge index, chosen by client (ex.: `address[99999999][city]`).
+//!
+//! *Type*: `usize`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `1000`.
+//!
+//! ## `dir`
+//!
+//! Directory for uploaded files of `Vec<PathBuf>` field (see below).
+//!
+//! *Type*: `String`.
+//!
+//! *Required*: `false`.
+//!
+//! *Default*: `std::env::temp_dir()`.
+//!
 //!
 //! # Field type
 //!
@@ -288,8 +730,72 @@
 //! * Bool and optional bool: `bool`, `Option<bool>`
 //! * String and optional string: `String`, `Option<String>`
 //! * Vectors: `Vec<u8>`, `Option<Vec<u8>>`
+//! * Vector of repeated values: `Vec<T>`, where `T` is integer (except `u8`), float, bool or `String`
+//! * Vector of nested struct: `Vec<NestedStruct>`
+//! * Vector of uploaded files: `Vec<PathBuf>`
+//!
+//! ## Vector of repeated values
+//!
+//! Several parts with same name (ex.: `<select multiple>`, checkboxes) are converted to `T` and pushed to `Vec<T>`
+//! in order of receiving. Attributes of `T` are allowed (ex.: `lowercase` for `Vec<String>`), part with conversion error
+//! is not pushed.
+//!
+//! ```rust,ignore
+//! #[multipart(name="tag")]
+//! pub tags: Vec<String>,
+//! ```
+//!
+//! ## Vector of nested struct
+//!
+//! Repeated groups of parts (ex.: several addresses in one form) are collected to `Vec<NestedStruct>`.
+//! Parts must be named `<name>[<index>][<field>]`, where `<name>` is `name` of `Vec` field,
+//! `<index>` is element index (from `0`) and `<field>` is `name` of field in `NestedStruct`:
+//!
+//! ```text
+//! address[0][city]=Paris
+//! address[0][zip]=75001
+//! address[1][city]=Berlin
+//! ```
+//!
+//! `NestedStruct` must derive `MultipartDerive` and implement `MultipartParserTarget`, `Default` and `Clone`.
+//! `Vec` grows as needed (up to `max_items`), missing elements filled by `Default::default()`. Conversion errors of nested fields
+//! passed to `MultipartParserTarget::error` of `NestedStruct`, `required` of nested fields is ignored.
+//!
+//! ```rust,ignore
+//! #[derive(MultipartDerive, Default, Clone)]
+//! #[multipart]
+//! struct Address
+//! {
+//!     #[multipart]
+//!     pub city: String,
+//!
+//!     #[multipart]
+//!     pub zip: Option<u32>,
+//! }
+//!
+//! impl MultipartParserTarget for Address {}
+//!
+//! #[derive(MultipartDerive)]
+//! #[multipart]
+//! struct Order
+//! {
+//!     #[multipart(name="address", max_items=10)]
+//!     pub addresses: Vec<Address>,
+//! }
+//! ```
+//!
+//! ## Vector of uploaded files
+//!
+//! Each part of `Vec<PathBuf>` field is streamed to new file with unique name in `dir` (not buffered in memory),
+//! path of file is pushed to `Vec` after part received. Several parts with same name are appended in order of receiving.
+//! Removing files is up to user.
+//!
+//! ```rust,ignore
+//! #[multipart(name="files", dir="/var/tmp/uploads")]
+//! pub files: Vec<PathBuf>,
+//! ```
 #![feature(proc_macro)]
-#![recursion_limit = "128"]
+#![recursion_limit = "256"]
 #![feature(extern_prelude)]
 
 extern crate proc_macro;
@@ -313,8 +819,27 @@ pub fn multipart(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 {
     let ast: DeriveInput = syn::parse(input).unwrap();
 
-    let multipart_struct = MultipartStruct::new(&ast);
+    let multipart_struct = match MultipartStruct::new(&ast)
+        {
+            Ok(multipart_struct) => multipart_struct,
+            Err(error) => return error.to_compile_error().into()
+        };
     let multipart_parser_target_generated = multipart_struct.impl_multipart_parser_target_generated();
+    let default = match multipart_struct.derive_default
+        {
+            true => multipart_struct.impl_default(),
+            false => TokenStream::new()
+        };
+    let field_result = match multipart_struct.collect_results
+        {
+            true => multipart_struct.impl_field_result(),
+            false => TokenStream::new()
+        };
+    let to_json_value = match cfg!(feature = "serde")
+        {
+            true => multipart_struct.impl_to_json_value(),
+            false => TokenStream::new()
+        };
 
     let mut process_contents = TokenStream::new();
     for mut filed_attribute in multipart_struct.fields
@@ -325,6 +850,9 @@ pub fn multipart(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 
     let result: TokenStream = quote!(
         #multipart_parser_target_generated
+        #default
+        #field_result
+        #to_json_value
         #process_contents
     );
 
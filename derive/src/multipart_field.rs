@@ -1,7 +1,14 @@
 use proc_macro2::{Ident, Span, TokenStream};
-use quote::{ToTokens};
-use syn::{Attribute, Expr, Field, Path, PathArguments, Type};
-use attributes_utils::{get_string, get_bool, get_usize, ident_to_string, collect_attribute};
+use quote::{ToTokens, TokenStreamExt};
+use syn::{Attribute, Field, GenericArgument, Path, PathArguments, Type};
+use attributes_utils::{get_string, get_bool, get_usize, get_ident, ident_to_string, collect_attribute, DeriveError, DeriveResult};
+
+
+/// Default `max_items` of `Vec<NestedStruct>`: index of part is chosen by client, `Vec` is grown up to it
+const DEFAULT_MAX_ITEMS: usize = 1000;
+
+/// Element types of `Vec<_>` field, which collects values of repeated parts with same name (ex.: `<select multiple>`)
+const REPEATED_TYPES: [&str; 11] = ["bool", "i8", "i16", "i32", "i64", "u16", "u32", "u64", "f32", "f64", "String"];
 
 
 /// Wrapper for user field with `#[multipart(...)]`
@@ -20,7 +27,7 @@ pub struct MultipartField
     /// Field name in multipart struct
     pub field_name: Ident,
 
-    /// Name for generated proxy struct in format `Multipart<StructName><FieldName>`
+    /// Name for generated proxy struct in format `<ProxyPrefix><StructName><FieldName>`, default prefix `Multipart`
     pub proxy_struct_name: Ident,
 
     /// Field type in multipart struct
@@ -34,6 +41,66 @@ pub struct MultipartField
 
     /// maximum size of data, default `None` (unlimited)
     pub max_size: Option<usize>,
+
+    /// Convert value to lowercase (only for `String` and `Option<String>`), default `false`
+    pub lowercase: bool,
+
+    /// Decode value by `String::from_utf8_lossy` instead of error (only for `String` and `Option<String>`), default `false`
+    pub lossy: bool,
+
+    /// Decode value as `application/x-www-form-urlencoded` (`+` to space, then `%XX`), only for `String` and `Option<String>`, default `false`
+    pub form_decode: bool,
+
+    /// Parsing of `bool` and `Option<bool>`: `"strict"` (`true`/`false` only) or `"lenient"` (`1`, `yes`, `on` etc.), default `None` (strict)
+    pub bool_format: Option<String>,
+
+    /// Rewrite line endings to `"lf"` (`\n`) or `"crlf"` (`\r\n`), only for `String` and `Option<String>`, default `None` (as is)
+    pub normalize_newlines: Option<String>,
+
+    /// Fire `MultipartParseError::EmptyField` for empty value (only for `String` and `Option<String>`), default `false`
+    pub non_empty: bool,
+
+    /// Fire `MultipartParseError::NonFiniteFloat` for `NaN` and infinity (only for `f32`, `f64` and `Option` of them), default `false`
+    pub finite: bool,
+
+    /// Empty value is `None` instead of `Some("")` or conversion error (only for `Option<_>`), default `false`
+    pub empty_as_none: bool,
+
+    /// Sibling field (`String` or `Option<String>`) to store value of `Content-Type` header, default `None`
+    pub content_type_into: Option<Ident>,
+
+    /// Sibling field (`String` or `Option<String>`) to store `filename` of `Content-Disposition` header, default `None`
+    pub filename_into: Option<Ident>,
+
+    /// Name of part header (case-insensitive), which value is converted instead of part body, default `None`
+    pub from_header: Option<String>,
+
+    /// Method of target `fn <handler>(&mut self, value: <field type>)`, called with converted value instead of assignment to field, default `None`
+    pub handler: Option<Ident>,
+
+    /// Allowed extensions of uploaded file in lowercase, ex.: `extensions="pdf,docx"`, default `None` (any)
+    pub extensions: Option<Vec<String>>,
+
+    /// Required `Content-Type` of part in lowercase, ex.: `require_content_type="application/json"`, default `None` (any)
+    pub require_content_type: Option<String>,
+
+    /// Element type for `Vec<NestedStruct>` field, parts named `<name>[<index>][<field>]`, default `None`
+    pub nested: Option<Path>,
+
+    /// Element type for `Vec<T>` field of primitive type (except `u8`) or `String`: value of each part with same name is pushed, default `None`
+    pub repeated: Option<Path>,
+
+    /// Maximum count of elements in `Vec<NestedStruct>`, default `None` (unlimited)
+    pub max_items: Option<usize>,
+
+    /// Field is `Vec<PathBuf>`: each uploaded file streamed to new file in `dir`, path pushed to `Vec`
+    pub files: bool,
+
+    /// Directory for uploaded files of `Vec<PathBuf>` field, default `None` (`std::env::temp_dir()`)
+    pub dir: Option<String>,
+
+    /// Store result of field in `field_results` of struct instead of `MultipartParserTarget::error` call (struct attribute `collect_results`)
+    pub collect_results: bool,
 }
 
 impl MultipartField
@@ -71,18 +138,19 @@ impl MultipartField
     }
 
 
-    pub fn new(field: &Field, attribute: &Attribute, struct_name: &Ident) -> MultipartField
+    pub fn new(field: &Field, attribute: &Attribute, struct_name: &Ident, proxy_prefix: &str) -> DeriveResult<MultipartField>
     {
         let field_name = match &field.ident
             {
-                &None => panic!("Cannot get field name"),
+                &None => return Err(DeriveError::new_spanned(field, "Cannot get field name".to_string())),
                 &Some(ref ident) => ident_to_string(&ident)
             };
 
 
         let proxy_struct_name: Ident =
             {
-                let tmp = format!("Multipart{}{}"
+                let tmp = format!("{}{}{}"
+                                                    , proxy_prefix
                                                     , MultipartField::to_camel_case(&ident_to_string(struct_name) )
                                                     , MultipartField::to_camel_case(&field_name));
                 Ident::new(tmp.as_str(), Span::call_site())
@@ -91,27 +159,67 @@ impl MultipartField
         let field_type: Path = match field.ty
             {
                 Type::Path(ref type_path) => type_path.path.clone(),
-                _ => panic!("Only primitive types allowed: (bool, i8, i16, i32, i64, u8, u16, u32, u64, f32, f64), Option<primitive type>, String, Option<String>, Vec<u8> and Option<Vec<u8>>, cannot process {}", &field_name)
+                _ => return Err(DeriveError::new_spanned(&field.ty, format!("Only primitive types allowed: (bool, i8, i16, i32, i64, u8, u16, u32, u64, f32, f64), Option<primitive type>, String, Option<String>, Vec<u8> and Option<Vec<u8>>, cannot process {}", &field_name)))
             };
 
         let mut name = field_name.clone();
         let mut required = false;
         let mut max_size: Option<usize> = None;
+        let mut lowercase = false;
+        let mut non_empty = false;
+        let mut finite = false;
+        let mut empty_as_none = false;
+        let mut lossy = false;
+        let mut form_decode = false;
+        let mut bool_format: Option<String> = None;
+        let mut normalize_newlines: Option<String> = None;
+        let mut content_type_into: Option<Ident> = None;
+        let mut filename_into: Option<Ident> = None;
+        let mut handler: Option<Ident> = None;
+        let mut from_header: Option<String> = None;
+        let mut extensions: Option<Vec<String>> = None;
+        let mut require_content_type: Option<String> = None;
+        let mut max_items: Option<usize> = None;
+        let mut dir: Option<String> = None;
         for (ident, lit) in &collect_attribute(&attribute)
             {
                 let string_ident = ident_to_string(&ident);
                 match string_ident.as_ref()
                     {
-                        "name"     => name = get_string(&ident, &lit),
-                        "required" => required = get_bool(&ident, &lit),
-                        "max_size" => max_size = Some(get_usize(&ident, &lit)),
-                        other      => panic!("Unknown multipart attribute '{}' in field '{}'", other, field_name)
+                        "name"     => name = get_string(&ident, &lit)?,
+                        "required" => required = get_bool(&ident, &lit)?,
+                        "max_size" => max_size = Some(get_usize(&ident, &lit)?),
+                        "lowercase" => lowercase = get_bool(&ident, &lit)?,
+                        "non_empty" => non_empty = get_bool(&ident, &lit)?,
+                        "finite" => finite = get_bool(&ident, &lit)?,
+                        "empty_as_none" => empty_as_none = get_bool(&ident, &lit)?,
+                        "lossy" => lossy = get_bool(&ident, &lit)?,
+                        "form_decode" => form_decode = get_bool(&ident, &lit)?,
+                        "bool_format" => bool_format = Some(get_string(&ident, &lit)?),
+                        "normalize_newlines" => normalize_newlines = Some(get_string(&ident, &lit)?),
+                        "content_type_into" => content_type_into = Some(get_ident(&ident, &lit)?),
+                        "filename_into" => filename_into = Some(get_ident(&ident, &lit)?),
+                        "handler" => handler = Some(get_ident(&ident, &lit)?),
+                        "from_header" => from_header = Some(get_string(&ident, &lit)?),
+                        "extensions" => extensions = Some(MultipartField::parse_list(&get_string(&ident, &lit)?)),
+                        "require_content_type" => require_content_type = Some(get_string(&ident, &lit)?.trim().to_lowercase()),
+                        "max_items" => max_items = Some(get_usize(&ident, &lit)?),
+                        "dir" => dir = Some(get_string(&ident, &lit)?),
+                        other      => return Err(DeriveError::new_spanned(ident, format!("Unknown multipart attribute '{}' in field '{}'", other, field_name)))
                     }
             }
 
+        if name.is_empty()
+            {
+                return Err(DeriveError::new_spanned(attribute, format!("Empty 'name' not allowed, field '{}'", field_name)));
+            }
+
         let field_name = Ident::new(field_name.as_str(), Span::call_site());
+        let nested = MultipartField::nested_type(&field_type);
+        let repeated = MultipartField::repeated_type(&field_type);
+        let files = MultipartField::is_path_vec(&field_type);
 
-        MultipartField
+        let multipart_field = MultipartField
             {
                 field_name,
                 field_type,
@@ -119,23 +227,515 @@ impl MultipartField
                 proxy_struct_name,
                 required,
                 max_size,
+                lowercase,
+                lossy,
+                form_decode,
+                bool_format,
+                normalize_newlines,
+                non_empty,
+                finite,
+                empty_as_none,
+                content_type_into,
+                filename_into,
+                from_header,
+                handler,
+                extensions,
+                require_content_type,
+                nested,
+                repeated,
+                max_items,
+                files,
+                dir,
+                collect_results: false,
+            };
+
+        if multipart_field.lowercase && !multipart_field.is_string()
+            {
+                return Err(DeriveError::new_spanned(&field.ty, format!("'lowercase' allowed only for String, Option<String> and Vec<String>, field '{}'", &multipart_field.field_name)));
+            }
+
+        if multipart_field.lossy && !multipart_field.is_string()
+            {
+                return Err(DeriveError::new_spanned(&field.ty, format!("'lossy' allowed only for String, Option<String> and Vec<String>, field '{}'", &multipart_field.field_name)));
+            }
+
+        if multipart_field.form_decode && !multipart_field.is_string()
+            {
+                return Err(DeriveError::new_spanned(&field.ty, format!("'form_decode' allowed only for String, Option<String> and Vec<String>, field '{}'", &multipart_field.field_name)));
+            }
+
+        if let Some(ref normalize_newlines) = multipart_field.normalize_newlines
+            {
+                if !multipart_field.is_string()
+                    {
+                        return Err(DeriveError::new_spanned(&field.ty, format!("'normalize_newlines' allowed only for String, Option<String> and Vec<String>, field '{}'", &multipart_field.field_name)));
+                    }
+                if normalize_newlines != "lf" && normalize_newlines != "crlf"
+                    {
+                        return Err(DeriveError::new_spanned(attribute, format!("'normalize_newlines' must be \"lf\" or \"crlf\", field '{}'", &multipart_field.field_name)));
+                    }
+            }
+
+        if let Some(ref bool_format) = multipart_field.bool_format
+            {
+                if !multipart_field.is_bool()
+                    {
+                        return Err(DeriveError::new_spanned(&field.ty, format!("'bool_format' allowed only for bool, Option<bool> and Vec<bool>, field '{}'", &multipart_field.field_name)));
+                    }
+                if bool_format != "strict" && bool_format != "lenient"
+                    {
+                        return Err(DeriveError::new_spanned(attribute, format!("'bool_format' must be \"strict\" or \"lenient\", field '{}'", &multipart_field.field_name)));
+                    }
+            }
+
+        if multipart_field.non_empty && !multipart_field.is_string()
+            {
+                return Err(DeriveError::new_spanned(&field.ty, format!("'non_empty' allowed only for String, Option<String> and Vec<String>, field '{}'", &multipart_field.field_name)));
+            }
+
+        if multipart_field.finite && !multipart_field.is_float()
+            {
+                return Err(DeriveError::new_spanned(&field.ty, format!("'finite' allowed only for f32, f64 and Option, Vec of them, field '{}'", &multipart_field.field_name)));
+            }
+
+        if multipart_field.empty_as_none && !multipart_field.type_name().starts_with("Option<")
+            {
+                return Err(DeriveError::new_spanned(&field.ty, format!("'empty_as_none' allowed only for Option<primitive type>, Option<String> and Option<Vec<u8>>, field '{}'", &multipart_field.field_name)));
+            }
+
+        if multipart_field.nested.is_some()
+            {
+                if multipart_field.required || multipart_field.max_size.is_some() || multipart_field.lowercase || multipart_field.non_empty || multipart_field.lossy || multipart_field.form_decode || multipart_field.bool_format.is_some()
+                    || multipart_field.content_type_into.is_some() || multipart_field.filename_into.is_some() || multipart_field.extensions.is_some() || multipart_field.handler.is_some()
+                    || multipart_field.from_header.is_some() || multipart_field.normalize_newlines.is_some() || multipart_field.require_content_type.is_some()
+                    {
+                        return Err(DeriveError::new_spanned(attribute, format!("Only 'name' and 'max_items' allowed for Vec<struct>, field '{}'", &multipart_field.field_name)));
+                    }
+            }
+        else if multipart_field.max_items.is_some()
+            {
+                return Err(DeriveError::new_spanned(&field.ty, format!("'max_items' allowed only for Vec<struct>, field '{}'", &multipart_field.field_name)));
+            }
+
+        if multipart_field.files
+            {
+                if multipart_field.lowercase || multipart_field.non_empty || multipart_field.lossy || multipart_field.form_decode || multipart_field.bool_format.is_some()
+                    || multipart_field.content_type_into.is_some() || multipart_field.filename_into.is_some() || multipart_field.extensions.is_some() || multipart_field.handler.is_some()
+                    || multipart_field.from_header.is_some() || multipart_field.normalize_newlines.is_some() || multipart_field.require_content_type.is_some()
+                    {
+                        return Err(DeriveError::new_spanned(attribute, format!("Only 'name', 'required', 'max_size' and 'dir' allowed for Vec<PathBuf>, field '{}'", &multipart_field.field_name)));
+                    }
+            }
+        else if multipart_field.dir.is_some()
+            {
+                return Err(DeriveError::new_spanned(&field.ty, format!("'dir' allowed only for Vec<PathBuf>, field '{}'", &multipart_field.field_name)));
             }
+
+        Ok(multipart_field)
     }
 
+    /// Return `true` for `Vec<PathBuf>` (`PathBuf` may be full path, ex.: `std::path::PathBuf`)
+    fn is_path_vec(field_type: &Path) -> bool
+    {
+        let mut tokens = TokenStream::new();
+        field_type.to_tokens(&mut tokens);
+        let type_name = tokens.to_string().replace(" ", "");
+        type_name.starts_with("Vec<") && (type_name == "Vec<PathBuf>" || type_name.ends_with("::PathBuf>"))
+    }
 
+    /// Return `T` for `Vec<T>`, `None` for all other types
+    fn vec_element_type(field_type: &Path) -> Option<Path>
+    {
+        let segment = field_type.segments.iter().last()?;
+        if ident_to_string(&segment.ident) != "Vec"
+            {
+                return None;
+            }
+        if let PathArguments::AngleBracketed(ref arguments) = segment.arguments
+            {
+                if let Some(&GenericArgument::Type(Type::Path(ref type_path))) = arguments.args.iter().next()
+                    {
+                        return Some(type_path.path.clone());
+                    }
+            }
+        None
+    }
 
-    /// Generate code line like
-    /// `"<name>" => Some(Box::new(<proxy>::new(self_.clone()))),`
-    pub fn parser_target_generated_item(&self) -> TokenStream
+    /// Path as string without spaces, ex.: `u8`
+    fn path_to_string(path: &Path) -> String
     {
-        let name = self.name.as_str();
-        let proxy = &self.proxy_struct_name;
+        let mut tokens = TokenStream::new();
+        path.to_tokens(&mut tokens);
+        tokens.to_string().replace(" ", "")
+    }
+
+    /// Return `NestedStruct` for `Vec<NestedStruct>`, `None` for all other types (including `Vec<u8>`, `Vec<PathBuf>`
+    /// and `Vec` of repeated values, see `repeated_type`)
+    fn nested_type(field_type: &Path) -> Option<Path>
+    {
+        if MultipartField::is_path_vec(field_type) || MultipartField::repeated_type(field_type).is_some()
+            {
+                return None;
+            }
+
+        match MultipartField::vec_element_type(field_type)
+            {
+                Some(ref element) if MultipartField::path_to_string(element) == "u8" => None,
+                element => element
+            }
+    }
+
+    /// Return `T` for `Vec<T>`, where `T` is primitive type (except `u8`, `Vec<u8>` is data of part) or `String`,
+    /// `None` for all other types
+    fn repeated_type(field_type: &Path) -> Option<Path>
+    {
+        let element = MultipartField::vec_element_type(field_type)?;
+        match REPEATED_TYPES.contains(&MultipartField::path_to_string(&element).as_str())
+            {
+                true => Some(element),
+                false => None
+            }
+    }
+
+
+    /// Split comma separated list `"pdf, .DOCX"` to `["pdf", "docx"]`
+    fn parse_list(s: &String) -> Vec<String>
+    {
+        s.split(',')
+            .map(|item| item.trim().trim_start_matches('.').to_lowercase())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+
+    /// Field type as string without spaces, ex.: `Option<String>`
+    fn type_name(&self) -> String
+    {
+        let mut token_stream_field_type = TokenStream::new();
+        self.field_type.to_tokens(&mut token_stream_field_type);
+        token_stream_field_type.to_string().replace(" ", "")
+    }
+
+    /// `true` for types with `Default`: primitive types, `String`, `Option<_>` and `Vec<_>`
+    pub fn has_default(&self) -> bool
+    {
+        match self.type_name().as_str()
+            {
+                "bool" | "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "f32" | "f64" | "String" => true,
+                type_name => type_name.starts_with("Option<") || type_name.starts_with("Vec<")
+            }
+    }
 
+    /// `true` for `String`, `Option<String>` and `Vec<String>`
+    fn is_string(&self) -> bool
+    {
+        match self.type_name().as_str()
+            {
+                "String" | "Option<String>" | "Vec<String>" => true,
+                _ => false
+            }
+    }
+
+    /// `true` for `f32`, `f64` and `Option`, `Vec` of them
+    fn is_float(&self) -> bool
+    {
+        match self.type_name().as_str()
+            {
+                "f32" | "f64" | "Option<f32>" | "Option<f64>" | "Vec<f32>" | "Vec<f64>" => true,
+                _ => false
+            }
+    }
+
+    /// Field is `bool`, `Option<bool>` or `Vec<bool>`
+    fn is_bool(&self) -> bool
+    {
+        match self.type_name().as_str()
+            {
+                "bool" | "Option<bool>" | "Vec<bool>" => true,
+                _ => false
+            }
+    }
+
+    /// Generate code, which apply `transform` (expression of `value: String`, returning `String`)
+    /// to converted `value` of `String` or `Option<String>` field
+    fn transform_string(&self, transform: TokenStream) -> TokenStream
+    {
+        match self.type_name().as_str()
+            {
+                "Option<String>" => quote!( let value = value.map(|value| #transform); ),
+                _ => quote!( let value = #transform; )
+            }
+    }
+
+    /// Generate expression, which convert `processor` to `Result<<field type>, _>`
+    fn convert(&self, field_type: &TokenStream) -> TokenStream
+    {
+        let convert = self.convert_data(field_type);
+        match self.empty_as_none
+            {
+                true => quote!( if processor.raw_data().is_empty() { Ok(None) } else { #convert } ),
+                false => convert
+            }
+    }
+
+    /// Generate expression, which convert data of `processor` (empty too) to `Result<<field type>, _>`
+    fn convert_data(&self, field_type: &TokenStream) -> TokenStream
+    {
+        if self.bool_format.as_ref().map(|bool_format| bool_format == "lenient").unwrap_or(false)
+            {
+                let value = quote!( ::gnitive_multipart::process_content::parse_bool_lenient(&String::from_utf8_lossy(processor.raw_data())) );
+                return match self.type_name().as_str()
+                    {
+                        "Option<bool>" => quote!( #value.map(Some) ),
+                        _ => value
+                    };
+            }
+        if !self.lossy
+            {
+                return quote!( #field_type(processor) );
+            }
+
+        let from_utf8_error = quote!(::std::string::FromUtf8Error);
+        let value = quote!( String::from_utf8_lossy(processor.raw_data()).into_owned() );
+        match self.type_name().as_str()
+            {
+                "Option<String>" => quote!( Ok::<Option<String>, #from_utf8_error>(Some(#value)) ),
+                _ => quote!( Ok::<String, #from_utf8_error>(#value) )
+            }
+    }
+
+    /// Generate code, which copy values from `headers` to sibling fields in `open`
+    fn open_captures(&self) -> TokenStream
+    {
+        let mut result = TokenStream::new();
+        if let Some(ref content_type_into) = self.content_type_into
+            {
+                result.append_all(quote!(
+                    if let Some(header) = headers.get_header("Content-Type")
+                        {
+                            self.target.borrow_mut().#content_type_into = header.value.clone().into();
+                        }
+                ));
+            }
+        if let Some(ref filename_into) = self.filename_into
+            {
+                result.append_all(quote!(
+                    if let Some(filename) = headers.get_filename()
+                        {
+                            self.target.borrow_mut().#filename_into = filename.clone().into();
+                        }
+                ));
+            }
+        result
+    }
+
+    /// Generate code, which open processor in `open`. For `from_header` processor is opened without headers of part:
+    /// header value is not transfer-encoded (`Content-Transfer-Encoding` is for body), field is skipped if part has no such header
+    fn open_processor(&self, process_content: &TokenStream, headers_type: &TokenStream) -> TokenStream
+    {
+        match self.from_header
+            {
+                Some(ref from_header) => quote!(
+                    #process_content::open(&mut self.processor, &#headers_type::new(&vec![]));
+                    if headers.get_header(#from_header).is_none()
+                        {
+                            self.skip = true;
+                        }
+                ),
+                None => quote!( #process_content::open(&mut self.processor, headers); )
+            }
+    }
+
+    /// Generate code, which write value of `from_header` header to processor in `flush`, before conversion
+    fn flush_from_header(&self, process_content: &TokenStream) -> TokenStream
+    {
+        match self.from_header
+            {
+                Some(ref from_header) => quote!(
+                    if let Some(header) = headers.get_header(#from_header)
+                        {
+                            #process_content::write(&mut self.processor, headers, header.value.as_bytes())?;
+                        }
+                ),
+                None => quote!()
+            }
+    }
+
+    /// Generate code, which check headers in `open` and set `self.skip` if field must be skipped,
+    /// error is stored in `self.open_error` for `MultipartParser` (see `ProcessContentHooks::take_open_error`)
+    fn open_checks(&self) -> TokenStream
+    {
+        let name = &self.name;
+        let multipart_parse_error = quote!(::gnitive_multipart::gnitive_multipart::MultipartParseError);
+
+        let mut result = TokenStream::new();
+        // part body ignored for `from_header` - encoding is not used
+        if self.from_header.is_none()
+            {
+                result.append_all(quote!(
+                    if let Some(encoding) = self.processor.unsupported_encoding().cloned()
+                        {
+                            self.skip = true;
+                            self.open_error = Some(#multipart_parse_error::UnsupportedEncoding(#name.to_string(), encoding));
+                        }
+                ));
+            }
+        if let Some(ref extensions) = self.extensions
+            {
+                result.append_all(quote!(
+                    let extension = headers.get_extension().unwrap_or("").to_lowercase();
+                    if ![#(#extensions),*].contains(&extension.as_str())
+                        {
+                            self.skip = true;
+                            self.open_error = Some(#multipart_parse_error::DisallowedExtension(#name.to_string(), extension));
+                        }
+                ));
+            }
+        if let Some(ref require_content_type) = self.require_content_type
+            {
+                result.append_all(quote!(
+                    let content_type = headers.get_mime_type().unwrap_or("").trim().to_lowercase();
+                    if !self.skip && content_type != #require_content_type
+                        {
+                            self.skip = true;
+                            self.open_error = Some(#multipart_parse_error::DisallowedContentType(#name.to_string(), content_type));
+                        }
+                ));
+            }
+        result
+    }
+
+    /// Generate code, which reports error of field value: deferred error (see `ProcessContentHooks::take_deferred_error`),
+    /// without deferring (see `ProcessContentHooks::set_defer_errors`) entry in `field_results` (if `collect_results`) or `MultipartParserTarget::error` call with `multipart_parse_error`,
+    /// result is stored for `MultipartParser` (see `ProcessContentHooks::take_error_result`). Without `error` call
+    /// if `MultipartParser` applies its default action (see `ProcessContentHooks::set_default_on_error`).
+    fn report_error(&self, message: TokenStream, multipart_parse_error: TokenStream) -> TokenStream
+    {
+        let name = &self.name;
+        let field_error = quote!(::gnitive_multipart::gnitive_multipart::FieldError);
+        let target_trait = quote!(::gnitive_multipart::gnitive_multipart::MultipartParserTarget);
+
+        let report = match self.collect_results
+            {
+                true => quote!( self.target.borrow_mut().field_results.insert(#name.to_string(), Err(#field_error::new(#name, processor.raw_data().clone(), #message))); ),
+                false => quote!(
+                    if self.report_errors
+                        {
+                            self.error_result = Some(#target_trait::error(&mut *self.target.borrow_mut(), &#multipart_parse_error));
+                        }
+                )
+            };
         quote!(
-            #name => Some(Rc::new(RefCell::new(#proxy::new(self_.clone())))),
+            // stored anyway - `MultipartParser` passes it to `on_errors`
+            self.deferred_error = Some(#field_error::new(#name, processor.raw_data().clone(), #message));
+            if !self.defer_errors
+                {
+                    #report
+                }
         )
     }
 
+    /// Generate code, which validate transformed `value` before assignment to field.
+    /// On fail error is fired (or deferred) and field is not changed.
+    fn value_checks(&self) -> TokenStream
+    {
+        let name = &self.name;
+        let multipart_parse_error = quote!(::gnitive_multipart::gnitive_multipart::MultipartParseError);
+        let report_empty = self.report_error(quote!( "empty value".to_string() ), quote!( #multipart_parse_error::EmptyField(#name.to_string()) ));
+        let report_non_finite = self.report_error(quote!( "not finite value".to_string() ), quote!( #multipart_parse_error::NonFiniteFloat(#name.to_string()) ));
+
+        let mut result = TokenStream::new();
+        if self.non_empty
+            {
+                let is_empty = match self.type_name().as_str()
+                    {
+                        "Option<String>" => quote!( value.as_ref().map(|value| value.is_empty()).unwrap_or(false) ),
+                        _ => quote!( value.is_empty() )
+                    };
+                result.append_all(quote!(
+                    if #is_empty
+                        {
+                            #report_empty
+                            return Ok(());
+                        }
+                ));
+            }
+        if self.finite
+            {
+                let is_finite = match self.type_name().as_str()
+                    {
+                        "Option<f32>" | "Option<f64>" => quote!( value.map(|value| value.is_finite()).unwrap_or(true) ),
+                        _ => quote!( value.is_finite() )
+                    };
+                result.append_all(quote!(
+                    if !#is_finite
+                        {
+                            #report_non_finite
+                            return Ok(());
+                        }
+                ));
+            }
+        result
+    }
+
+    /// Generate code, which transform converted `value` before assignment to field
+    fn value_transforms(&self) -> TokenStream
+    {
+        let mut result = TokenStream::new();
+        if self.form_decode
+            {
+                let form_decode = quote!(::gnitive_multipart::url_decode::form_decode);
+                result.append_all(self.transform_string(quote!( #form_decode(&value) )));
+            }
+        match self.normalize_newlines.as_ref().map(|normalize_newlines| normalize_newlines.as_str())
+            {
+                Some("lf") => result.append_all(self.transform_string(quote!( value.replace("\r\n", "\n") ))),
+                Some("crlf") => result.append_all(self.transform_string(quote!( value.replace("\r\n", "\n").replace("\n", "\r\n") ))),
+                _ => ()
+            }
+        if self.lowercase
+            {
+                result.append_all(self.transform_string(quote!( value.to_lowercase() )));
+            }
+        result
+    }
+
+
+
+    /// Generate code line like
+    /// `"<name>" => Some(Rc::new(RefCell::new(<proxy>::new(self_.clone())))),`,
+    /// for `Vec<NestedStruct>` field - arm, matching parts `<name>[<index>][<field>]`
+    ///
+    /// * `case_insensitive` - `"<name>"` in lowercase (matched with lowercased name of part),
+    ///   `Vec<NestedStruct>` parts are matched with original `<name>` (case-sensitively)
+    pub fn parser_target_generated_item(&self, case_insensitive: bool) -> TokenStream
+    {
+        let proxy = &self.proxy_struct_name;
+        let field_name = &self.field_name;
+
+        match self.nested
+            {
+                Some(_) =>
+                    {
+                        let name = self.name.as_str();
+                        quote!(
+                            _ if headers.get_indexed_name(#name).is_some() => #proxy::new(self_.clone(), &mut self.#field_name, headers),
+                        )
+                    },
+                None =>
+                    {
+                        let name = match case_insensitive
+                            {
+                                true => self.name.to_lowercase(),
+                                false => self.name.clone()
+                            };
+                        let name = name.as_str();
+                        quote!(
+                            #name => Some(::std::rc::Rc::new(::std::cell::RefCell::new(#proxy::new(self_.clone())))),
+                        )
+                    }
+            }
+    }
+
     pub fn parser_required(&self) -> Option<String>
     {
         match self.required
@@ -150,10 +750,195 @@ impl MultipartField
     }
 
 
-    ///Generate proxy struct and `impl gnitive_multipart::ProcessContent`
-    pub fn impl_process_content(&mut self, target: &Ident) -> TokenStream
+    /// Generate expression `serde_json::Value` of field for generated `to_json_value` (feature `serde`):
+    /// `Vec<u8>` as base64 string, `Vec<NestedStruct>` as array of objects, `Vec<PathBuf>` as array of paths,
+    /// `None` as `null`, other values as `ToString` string. Closure `base64` must be in scope.
+    pub fn json_value(&self) -> TokenStream
+    {
+        let field_name = &self.field_name;
+        let value = quote!(::serde_json::Value);
+        if self.nested.is_some()
+            {
+                return quote!( #value::Array(self.#field_name.iter().map(|item| item.to_json_value()).collect()) );
+            }
+        if self.files
+            {
+                return quote!( #value::Array(self.#field_name.iter().map(|path| #value::String(path.display().to_string())).collect()) );
+            }
+        if self.repeated.is_some()
+            {
+                return quote!( #value::Array(self.#field_name.iter().map(|value| #value::String(value.to_string())).collect()) );
+            }
+        match self.type_name().as_str()
+            {
+                "Vec<u8>" => quote!( base64(&self.#field_name) ),
+                "Option<Vec<u8>>" => quote!( self.#field_name.as_ref().map(|data| base64(data)).unwrap_or(#value::Null) ),
+                type_name if type_name.starts_with("Option<") => quote!( self.#field_name.as_ref().map(|value| #value::String(value.to_string())).unwrap_or(#value::Null) ),
+                _ => quote!( #value::String(self.#field_name.to_string()) )
+            }
+    }
+
+
+    /// Generate proxy struct and `impl gnitive_multipart::ProcessContent` for `Vec<NestedStruct>` field.
+    /// Proxy redirect part `<name>[<index>][<field>]` to processor of `NestedStruct` for `<field>`
+    /// and store element back to `Vec` in `flush`.
+    fn impl_nested_process_content(&self, target: &Ident, nested: &Path) -> TokenStream
+    {
+        let name = &self.name;
+        let proxy_name = &self.proxy_struct_name;
+        let field_name = &self.field_name;
+
+        let process_params = quote!(::gnitive_multipart::gnitive_multipart::ProcessParams);
+        let process_content = quote!(::gnitive_multipart::gnitive_multipart::ProcessContent);
+        let process_content_hooks = quote!(::gnitive_multipart::gnitive_multipart::ProcessContentHooks);
+        let target_generated = quote!(::gnitive_multipart::gnitive_multipart::MultipartParserTargetGenerated);
+        let headers_type = quote!(::gnitive_multipart::gnitive_multipart::Headers);
+        let field_error = quote!(::gnitive_multipart::gnitive_multipart::FieldError);
+
+        let max_items = self.max_items.unwrap_or(DEFAULT_MAX_ITEMS);
+        let check_max_items = quote!(
+            if index >= #max_items
+                {
+                    return None;
+                }
+        );
+
+        let proxy_struct_decl: TokenStream = quote!(
+            struct #proxy_name
+            {
+                target: ::std::rc::Rc<::std::cell::RefCell<#target>>,
+                index: usize,
+                nested: ::std::rc::Rc<::std::cell::RefCell<#nested>>,
+                processor: ::std::rc::Rc<::std::cell::RefCell<#process_content>>,
+                headers: #headers_type,
+                process_params: #process_params
+            }
+        );
+
+        let proxy_struct_impl: TokenStream = quote!(
+            impl #proxy_name
+            {
+                pub fn new(target: ::std::rc::Rc<::std::cell::RefCell<#target>>, items: &mut Vec<#nested>, headers: &#headers_type) -> Option<::std::rc::Rc<::std::cell::RefCell<#process_content>>>
+                {
+                    let (index, field) = headers.get_indexed_name(#name)?;
+                    #check_max_items
+                    while items.len() <= index
+                        {
+                            items.push(<#nested as Default>::default());
+                        }
+
+                    let nested = ::std::rc::Rc::new(::std::cell::RefCell::new(items[index].clone()));
+                    let nested_headers = headers.with_name(&field);
+                    let processor = <#nested as #target_generated>::content_parser_generated(&mut *nested.borrow_mut(), &nested, &nested_headers)?;
+                    let max_size = processor.borrow().get_process_params().max_size;
+
+                    Some(::std::rc::Rc::new(::std::cell::RefCell::new(
+                        #proxy_name
+                            {
+                                target,
+                                index,
+                                nested,
+                                processor,
+                                headers: nested_headers,
+                                process_params: #process_params::new(headers.get_name().cloned().unwrap_or_default(), max_size)
+                            })))
+                }
+            }
+        );
+
+        let proxy_struct_impl_process_content: TokenStream = quote!(
+            impl #process_content for #proxy_name
+            {
+                fn open(&mut self, _headers: &#headers_type) -> ()
+                {
+                    self.processor.borrow_mut().open(&self.headers);
+                }
+
+                fn write(&mut self, _headers: &#headers_type, data: &[u8]) -> Result<(), ::std::io::Error>
+                {
+                    self.processor.borrow_mut().write(&self.headers, data)
+                }
+
+                fn flush(&mut self, _headers: &#headers_type) -> Result<(), ::std::io::Error>
+                {
+                    self.processor.borrow_mut().flush(&self.headers)?;
+                    let nested = ::std::mem::replace(&mut *self.nested.borrow_mut(), <#nested as Default>::default());
+                    self.target.borrow_mut().#field_name[self.index] = nested;
+                    Ok(())
+                }
+
+                fn get_process_params(&self) -> &#process_params
+                {
+                    &self.process_params
+                }
+
+                fn hooks(&mut self) -> Option<&mut #process_content_hooks>
+                {
+                    Some(self)
+                }
+
+                fn cleanup(&mut self, _headers: &#headers_type) -> ()
+                {
+                    self.processor.borrow_mut().cleanup(&self.headers);
+                }
+
+                fn abort(&mut self, _headers: &#headers_type) -> Result<(), ::std::io::Error>
+                {
+                    self.processor.borrow_mut().abort(&self.headers)
+                }
+            }
+
+            impl #process_content_hooks for #proxy_name
+            {
+                fn set_defer_errors(&mut self, defer: bool) -> ()
+                {
+                    if let Some(hooks) = self.processor.borrow_mut().hooks()
+                        {
+                            hooks.set_defer_errors(defer);
+                        }
+                }
+
+                fn take_deferred_error(&mut self) -> Option<#field_error>
+                {
+                    self.processor.borrow_mut().hooks().and_then(|hooks| hooks.take_deferred_error())
+                }
+
+                fn set_default_on_error(&mut self, on_error: ::gnitive_multipart::gnitive_multipart::OnError) -> ()
+                {
+                    if let Some(hooks) = self.processor.borrow_mut().hooks()
+                        {
+                            hooks.set_default_on_error(on_error);
+                        }
+                }
+
+                fn take_error_result(&mut self) -> Option<Result<::gnitive_multipart::gnitive_multipart::OnError, ::std::io::Error>>
+                {
+                    self.processor.borrow_mut().hooks().and_then(|hooks| hooks.take_error_result())
+                }
+
+                fn take_open_error(&mut self) -> Option<::gnitive_multipart::gnitive_multipart::MultipartParseError<'static>>
+                {
+                    self.processor.borrow_mut().hooks().and_then(|hooks| hooks.take_open_error())
+                }
+            }
+        );
+
+        quote!(
+            #proxy_struct_decl
+            #proxy_struct_impl
+            #proxy_struct_impl_process_content
+        )
+    }
+
+
+    /// Generate proxy struct and `impl gnitive_multipart::ProcessContent` for `Vec<PathBuf>` field.
+    /// Proxy stream each part to new file by `FileProcessor` and push its path to `Vec` in `flush`,
+    /// so repeated parts with same name are appended.
+    fn impl_files_process_content(&self, target: &Ident) -> TokenStream
     {
         let name = &self.name;
+        let proxy_name = &self.proxy_struct_name;
+        let field_name = &self.field_name;
 
         let max_size = match self.max_size
             {
@@ -161,127 +946,293 @@ impl MultipartField
                 Some(max_size) => quote!( Some(#max_size) )
             };
 
-        let proxy_name = &self.proxy_struct_name;
+        let dir = match self.dir
+            {
+                None => quote!( ::std::env::temp_dir() ),
+                Some(ref dir) => quote!( ::std::path::PathBuf::from(#dir) )
+            };
 
-        let process_params = quote!(gnitive_multipart::gnitive_multipart::ProcessParams);
-        let process_content = quote!(gnitive_multipart::gnitive_multipart::ProcessContent);
-        let default_processor = quote!(gnitive_multipart::process_content::DefaultProcessor);
+        let process_params = quote!(::gnitive_multipart::gnitive_multipart::ProcessParams);
+        let process_content = quote!(::gnitive_multipart::gnitive_multipart::ProcessContent);
+        let file_processor = quote!(::gnitive_multipart::process_content::FileProcessor);
+        let headers_type = quote!(::gnitive_multipart::gnitive_multipart::Headers);
 
-        let field_name = &self.field_name;
+        quote!(
+            struct #proxy_name
+            {
+                processor: #file_processor,
+                target: ::std::rc::Rc<::std::cell::RefCell<#target>>
+            }
 
+            impl #proxy_name
+            {
+                pub fn new(target: ::std::rc::Rc<::std::cell::RefCell<#target>>) -> Self
+                {
+                    Self
+                        {
+                            processor: #file_processor::new(#process_params::new(#name, #max_size), #dir),
+                            target
+                        }
+                }
+            }
 
-        let mut token_stream_field_type = TokenStream::new();
-        self.field_type.to_tokens(&mut token_stream_field_type);
-        let tokens = quote!(#token_stream_field_type);
-        let mut field_type = tokens.to_string();
-        // change Option<t> to Option::<t> - avoid "chained comparison operators require parentheses" error
-        {
-            for segment in &self.field_type.segments
+            impl #process_content for #proxy_name
+            {
+                fn open(&mut self, headers: &#headers_type) -> ()
+                {
+                    #process_content::open(&mut self.processor, headers);
+                }
+
+                fn write(&mut self, headers: &#headers_type, data: &[u8]) -> Result<(), ::std::io::Error>
+                {
+                    #process_content::write(&mut self.processor, headers, data)
+                }
+
+                fn flush(&mut self, headers: &#headers_type) -> Result<(), ::std::io::Error>
                 {
-                    if let PathArguments::AngleBracketed(ref _arguments) = segment.arguments
+                    #process_content::flush(&mut self.processor, headers)?;
+                    if let Some(path) = self.processor.path()
                         {
-                            let pos: Option<usize> =
-                                {
-                                    let mut result: Option<usize> = None;
-                                    if let Some(pos) = field_type.find('<')
-                                        {
-                                            if let Some (chr) = field_type.get(pos + 1..pos + 2)
-                                                {
-                                                    if chr != ":"
-                                                        {
-                                                            result = Some(pos);
-                                                        }
-                                                }
-                                        }
-                                    result
-                                };
-                            if pos.is_some()
-                                {
-                                    field_type.insert_str(pos.unwrap(), "::");
-                                }
-                            break;
+                            self.target.borrow_mut().#field_name.push(path.clone());
                         }
+                    Ok(())
                 }
-        }
 
-        field_type.push_str("::try_from");
+                fn get_process_params(&self) -> &#process_params
+                {
+                    #process_content::get_process_params(&self.processor)
+                }
+
+                fn cleanup(&mut self, headers: &#headers_type) -> ()
+                {
+                    #process_content::cleanup(&mut self.processor, headers);
+                }
+
+                fn abort(&mut self, headers: &#headers_type) -> Result<(), ::std::io::Error>
+                {
+                    #process_content::abort(&mut self.processor, headers)
+                }
+            }
+        )
+    }
+
+
+    ///Generate proxy struct and `impl gnitive_multipart::ProcessContent`
+    pub fn impl_process_content(&mut self, target: &Ident) -> TokenStream
+    {
+        if let Some(nested) = self.nested.clone()
+            {
+                return self.impl_nested_process_content(target, &nested);
+            }
+        if self.files
+            {
+                return self.impl_files_process_content(target);
+            }
+
+        let name = &self.name;
+
+        let max_size = match self.max_size
+            {
+                None => quote!( None ),
+                Some(max_size) => quote!( Some(#max_size) )
+            };
+
+        let proxy_name = &self.proxy_struct_name;
+
+        let process_params = quote!(::gnitive_multipart::gnitive_multipart::ProcessParams);
+        let process_content = quote!(::gnitive_multipart::gnitive_multipart::ProcessContent);
+        let process_content_hooks = quote!(::gnitive_multipart::gnitive_multipart::ProcessContentHooks);
+        let default_processor = quote!(::gnitive_multipart::process_content::DefaultProcessor);
+        let field_error = quote!(::gnitive_multipart::gnitive_multipart::FieldError);
+        let headers_type = quote!(::gnitive_multipart::gnitive_multipart::Headers);
+        let to_error = quote!(::gnitive_multipart::gnitive_multipart::ToMultipartParseError);
+
+        let field_name = &self.field_name;
+
+
+        // qualified call - `TryFrom` need not be in scope of derived struct; element of `Vec` for repeated field
+        let mut token_stream_field_type = TokenStream::new();
+        match self.repeated
+            {
+                Some(ref element) => element.to_tokens(&mut token_stream_field_type),
+                None => self.field_type.to_tokens(&mut token_stream_field_type)
+            }
+        let field_type = quote!( <#token_stream_field_type as ::std::convert::TryFrom<&#default_processor>>::try_from );
 
         let (error_ident, error_exp) = {
-            if field_type.find("Vec").is_some()
+            if self.type_name().find("Vec").is_some() && self.repeated.is_none()
 
                 {
                     (quote!(_error), quote!())
                 }
                 else
                 {
-                    (quote!(error), quote!(let _unused = self.target.borrow_mut().error(&error.to_multipart_parse_error(#name.to_string(), processor.raw_data()));))
+                    (quote!(error), self.report_error(quote!( error.to_string() ), quote!( #to_error::to_multipart_parse_error(&error, #name.to_string(), processor.raw_data()) )))
                 }
         };
 
 
-        let fixed_path = syn::parse_str::<Expr>(field_type.as_str()).unwrap();
-        let field_type = quote!(#fixed_path);
-
-
 
         let proxy_struct_decl : TokenStream = quote!(
             struct #proxy_name
             {
                 processor: #default_processor,
-                target: Rc<RefCell<#target>>
+                target: ::std::rc::Rc<::std::cell::RefCell<#target>>,
+                defer_errors: bool,
+                deferred_error: Option<#field_error>,
+                report_errors: bool,
+                error_result: Option<Result<::gnitive_multipart::gnitive_multipart::OnError, ::std::io::Error>>,
+                skip: bool,
+                open_error: Option<::gnitive_multipart::gnitive_multipart::MultipartParseError<'static>>
             }
         );
         let proxy_struct_impl : TokenStream = quote!(
 
             impl #proxy_name
             {
-                pub fn new(target: Rc<RefCell<#target>>) -> Self
+                pub fn new(target: ::std::rc::Rc<::std::cell::RefCell<#target>>) -> Self
                 {
                     Self
                         {
                             processor: #default_processor::new( #process_params::new(#name, #max_size) ),
-                            target: target.clone()
+                            target: target.clone(),
+                            defer_errors: false,
+                            deferred_error: None,
+                            report_errors: true,
+                            error_result: None,
+                            skip: false,
+                            open_error: None
                         }
                 }
             }
         );
 
+        let open_captures = self.open_captures();
+        let open_checks = self.open_checks();
+        let open_processor = self.open_processor(&process_content, &headers_type);
+        let flush_from_header = self.flush_from_header(&process_content);
+        // part body ignored - value is taken from header in `open`
+        let write_body = match self.from_header
+            {
+                Some(_) => quote!( { let _unused = (headers, data); Ok(()) } ),
+                None => quote!( #process_content::write(&mut self.processor, headers, data) )
+            };
+
         let fn_open: TokenStream = quote!(
-            fn open(&mut self, headers: &Headers) -> ()
+            fn open(&mut self, headers: &#headers_type) -> ()
             {
-                self.processor.open(headers);
+                self.skip = false;
+                self.open_error = None;
+                #open_processor
+                #open_captures
+                #open_checks
             }
         );
 
         let fn_write: TokenStream = quote!(
-            fn write(&mut self, headers: &Headers, data: &Vec<u8>) -> ()
+            fn write(&mut self, headers: &#headers_type, data: &[u8]) -> Result<(), ::std::io::Error>
             {
-                self.processor.write(headers, data);
+                if self.skip
+                    {
+                        return Ok(());
+                    }
+                #write_body
             }
         );
 
 
+        let convert = self.convert(&field_type);
+        let mut assign = match (&self.handler, &self.repeated)
+            {
+                (&Some(ref handler), _) => quote!( self.target.borrow_mut().#handler(value); ),
+                (&None, &Some(_)) => quote!( self.target.borrow_mut().#field_name.push(value); ),
+                (&None, &None) => quote!( self.target.borrow_mut().#field_name = value; )
+            };
+        if self.collect_results
+            {
+                assign.append_all(quote!( self.target.borrow_mut().field_results.insert(#name.to_string(), Ok(())); ));
+            }
+        let value_transforms = self.value_transforms();
+        let value_checks = self.value_checks();
+        let multipart_parse_error = quote!(::gnitive_multipart::gnitive_multipart::MultipartParseError);
+        let invalid_encoding = self.report_error(quote!( message.clone() ), quote!( #multipart_parse_error::InvalidEncoding(#name.to_string(), message.clone()) ));
+
         let fn_flush: TokenStream = quote!(
-            fn flush(&mut self, headers: &Headers) -> ()
+            fn flush(&mut self, headers: &#headers_type) -> Result<(), ::std::io::Error>
             {
-                self.processor.flush(headers);
+                if self.skip
+                    {
+                        return Ok(());
+                    }
+                #flush_from_header
+                #process_content::flush(&mut self.processor, headers)?;
                 let processor = &self.processor;
+                self.deferred_error = None;
+                self.error_result = None;
+                if processor.exceeded_limit()
+                    {
+                        // `SizeLimit` is fired by parser, truncated data is not converted
+                        return Ok(());
+                    }
+                if let Some(message) = processor.invalid_encoding().cloned()
+                    {
+                        // data is decoded up to invalid symbol only - not converted
+                        #invalid_encoding
+                        return Ok(());
+                    }
 
-                let result = #field_type(processor);
+                let result = #convert;
                 match result
                 {
-                    Ok(value) => self.target.borrow_mut().#field_name = value,
+                    Ok(value) =>
+                    {
+                        #value_transforms
+                        #value_checks
+                        #assign
+                    },
                     Err(#error_ident) =>
                     {
                         #error_exp
                     }
                 }
+                Ok(())
             });
 
         let fn_get_process_params: TokenStream = quote!(
             fn get_process_params(&self) -> &#process_params
             {
-                self.processor.get_process_params()
+                #process_content::get_process_params(&self.processor)
+            }
+
+            fn buffered_size(&self) -> usize
+            {
+                #process_content::buffered_size(&self.processor)
+            });
+
+        let fn_hooks: TokenStream = quote!(
+            fn set_defer_errors(&mut self, defer: bool) -> ()
+            {
+                self.defer_errors = defer;
+            }
+
+            fn take_deferred_error(&mut self) -> Option<#field_error>
+            {
+                self.deferred_error.take()
+            }
+
+            fn set_default_on_error(&mut self, on_error: ::gnitive_multipart::gnitive_multipart::OnError) -> ()
+            {
+                self.report_errors = on_error == ::gnitive_multipart::gnitive_multipart::OnError::ContinueWithError;
+            }
+
+            fn take_error_result(&mut self) -> Option<Result<::gnitive_multipart::gnitive_multipart::OnError, ::std::io::Error>>
+            {
+                self.error_result.take()
+            }
+
+            fn take_open_error(&mut self) -> Option<#multipart_parse_error<'static>>
+            {
+                self.open_error.take()
             });
 
         let proxy_struct_impl_process_content: TokenStream = quote!(
@@ -292,6 +1243,22 @@ impl MultipartField
                 #fn_write
                 #fn_flush
                 #fn_get_process_params
+
+                fn hooks(&mut self) -> Option<&mut #process_content_hooks>
+                {
+                    Some(self)
+                }
+
+                // field skipped - value is not assigned
+                fn abort(&mut self, headers: &#headers_type) -> Result<(), ::std::io::Error>
+                {
+                    #process_content::abort(&mut self.processor, headers)
+                }
+            }
+
+            impl #process_content_hooks for #proxy_name
+            {
+                #fn_hooks
             }
         );
 
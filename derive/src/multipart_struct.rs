@@ -1,8 +1,8 @@
-use syn::{Ident, DeriveInput, Data, Fields};
+use syn::{Ident, DeriveInput, Data, Field, Fields};
 use quote::{TokenStreamExt};
 use proc_macro2::{TokenStream};
 use multipart_field::{MultipartField};
-use attributes_utils::{collect_attributes, get_bool, ident_to_string, find_attribute};
+use attributes_utils::{collect_attributes, get_bool, get_string, ident_to_string, find_attribute, DeriveError, DeriveResult};
 
 
 /// Wrapper for user struct with `#[derive(MultipartDerive)]`
@@ -14,38 +14,100 @@ pub struct MultipartStruct
     /// Value of `debug` attribute in `#[multipart()]`, default `false`
     pub debug: bool,
 
+    /// Value of `proxy_prefix` attribute in `#[multipart()]` - prefix of generated proxy struct names, default `Multipart`
+    pub proxy_prefix: String,
+
+    /// Value of `case_insensitive` attribute in `#[multipart()]` - match part names ignoring case, default `false`
+    pub case_insensitive: bool,
+
+    /// Value of `ordered` attribute in `#[multipart()]` - parts must be received in order of fields, default `false`
+    pub ordered: bool,
+
+    /// Value of `require_name` attribute in `#[multipart()]` - fire `MissingName` for part without name, default `false`
+    pub require_name: bool,
+
+    /// Value of `derive_default` attribute in `#[multipart()]` - generate `Default` impl, default `false`
+    pub derive_default: bool,
+
+    /// Value of `collect_results` attribute in `#[multipart()]` - store result of each field in `field_results`, default `false`
+    pub collect_results: bool,
+
     /// All fields, marked with `#[multipart()]`
     pub fields: Vec<MultipartField>
 }
 
 impl MultipartStruct
 {
-    pub fn new (ast: &DeriveInput) -> Self
+    pub fn new (ast: &DeriveInput) -> DeriveResult<Self>
     {
         let name = ast.ident.clone();
         let mut debug = false;
-        for (ident, lit) in collect_attributes("multipart",&ast.attrs)
+        let mut proxy_prefix = "Multipart".to_string();
+        let mut case_insensitive = false;
+        let mut ordered = false;
+        let mut require_name = false;
+        let mut derive_default = false;
+        let mut collect_results = false;
+        for (ident, lit) in collect_attributes("multipart", &ast.attrs, &ast.ident)?
             {
                 let string_ident = ident_to_string(&ident);
                 match string_ident.as_str()
                     {
                         "debug" =>
                             {
-                                debug = get_bool(&ident, &lit);
+                                debug = get_bool(&ident, &lit)?;
+                            },
+                        "proxy_prefix" =>
+                            {
+                                proxy_prefix = get_string(&ident, &lit)?;
+                            },
+                        "case_insensitive" =>
+                            {
+                                case_insensitive = get_bool(&ident, &lit)?;
+                            },
+                        "ordered" =>
+                            {
+                                ordered = get_bool(&ident, &lit)?;
+                            },
+                        "require_name" =>
+                            {
+                                require_name = get_bool(&ident, &lit)?;
+                            },
+                        "derive_default" =>
+                            {
+                                derive_default = get_bool(&ident, &lit)?;
+                            },
+                        "collect_results" =>
+                            {
+                                collect_results = get_bool(&ident, &lit)?;
                             },
                         _ =>
                             {
-                                panic!("Unknown attribute '{}' in struct '{}'", &string_ident, &ast.ident);
+                                return Err(DeriveError::new_spanned(&ident, format!("Unknown attribute '{}' in struct '{}'", &string_ident, &ast.ident)));
                             }
                     }
             }
 
-        let fields: Vec<MultipartField> =
+        let mut fields: Vec<MultipartField> =
             {
                 if let &Data::Struct(ref data_struct) = &ast.data
                     {
                         if let Fields::Named(ref fields_named) = data_struct.fields
                             {
+                                // `field_results` is filled by generated code, not by part
+                                let is_field_results = |field: &&Field| collect_results && field.ident.as_ref().map(ident_to_string) == Some("field_results".to_string());
+                                if collect_results && !fields_named.named.iter().any(|field| is_field_results(&field))
+                                    {
+                                        return Err(DeriveError::new_spanned(&ast.ident, format!("'collect_results' requires field 'field_results: HashMap<String, Result<(), FieldError>>' in struct '{}'", &ast.ident)));
+                                    }
+                                if derive_default
+                                    {
+                                        if let Some(field) = fields_named.named.iter().find(|field| find_attribute("multipart", &field.attrs).is_none() && !is_field_results(field))
+                                            {
+                                                return Err(DeriveError::new_spanned(field, format!("'derive_default' allowed only if all fields are marked with #[multipart], field '{}' in struct '{}'"
+                                                       , field.ident.as_ref().map(ident_to_string).unwrap_or_default(), &ast.ident)));
+                                            }
+                                    }
                                 fields_named.named
                                     .iter()
                                     .filter_map(
@@ -53,31 +115,140 @@ impl MultipartStruct
                                             match find_attribute("multipart", &field.attrs)
                                                 {
                                                     None => None,
-                                                    Some(attr) => Some(MultipartField::new(field, attr, &name))
+                                                    Some(attr) => Some(MultipartField::new(field, attr, &name, &proxy_prefix))
                                                 })
-                                    .collect()
+                                    .collect::<DeriveResult<_>>()?
                             }
                             else
                             {
-                                panic!("Only struct allowed");
+                                return Err(DeriveError::new_spanned(&ast.ident, "Only struct allowed".to_string()));
                             }
                     }
                     else
                     {
-                        panic!("Only struct allowed");
+                        return Err(DeriveError::new_spanned(&ast.ident, "Only struct allowed".to_string()));
                     }
             };
 
 
-        MultipartStruct
+        if case_insensitive
+            {
+                let mut names: Vec<String> = fields.iter().map(|field| field.name.to_lowercase()).collect();
+                names.sort();
+                if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1])
+                    {
+                        return Err(DeriveError::new_spanned(&ast.ident, format!("Field names differ only by case with 'case_insensitive', name '{}' in struct '{}'", &pair[0], &ast.ident)));
+                    }
+            }
+
+        for field in fields.iter_mut()
+            {
+                field.collect_results = collect_results;
+            }
+
+        if derive_default
+            {
+                if let Some(field) = fields.iter().find(|field| !field.has_default())
+                    {
+                        return Err(DeriveError::new_spanned(&field.field_name, format!("'derive_default' requires type with Default (primitive type, String, Option<_> or Vec<_>), field '{}' in struct '{}'", &field.field_name, &ast.ident)));
+                    }
+            }
+
+        Ok(MultipartStruct
             {
                 name,
                 debug,
+                proxy_prefix,
+                case_insensitive,
+                ordered,
+                require_name,
+                derive_default,
+                collect_results,
                 fields
+            })
+    }
+
+
+
+    /// Generate `Default` impl with default value of each field (see `derive_default`)
+    pub fn impl_default(&self) -> TokenStream
+    {
+        let name = &self.name;
+        let field_names = self.fields.iter().map(|field| &field.field_name);
+        let field_results = match self.collect_results
+            {
+                true => quote!( field_results: ::std::default::Default::default(), ),
+                false => quote!()
+            };
+
+        quote!(
+            impl ::std::default::Default for #name
+            {
+                fn default() -> Self
+                {
+                    #name
+                        {
+                            #field_results
+                            #(#field_names: ::std::default::Default::default()),*
+                        }
+                }
             }
+        )
     }
 
+    /// Generate `field_result(&self, name: &str)` - result of field by `name` (see `collect_results`)
+    pub fn impl_field_result(&self) -> TokenStream
+    {
+        let name = &self.name;
+        let field_error = quote!(::gnitive_multipart::gnitive_multipart::FieldError);
 
+        quote!(
+            impl #name
+            {
+                /// Result of last received part of field by `name`: `Ok` if value assigned, `Err` with conversion error.
+                /// `None` if field is not received.
+                #[allow(dead_code)]
+                pub fn field_result(&self, name: &str) -> Option<&Result<(), #field_error>>
+                {
+                    self.field_results.get(name)
+                }
+            }
+        )
+    }
+
+    /// Generate `to_json_value(&self) -> serde_json::Value` with all `#[multipart]` fields by `name` (feature `serde`)
+    pub fn impl_to_json_value(&self) -> TokenStream
+    {
+        let name = &self.name;
+        let names = self.fields.iter().map(|field| field.name.as_str());
+        let values = self.fields.iter().map(|field| field.json_value());
+        let cte = quote!(::gnitive_multipart::transfer_encoding::Cte);
+        let encoder = quote!(::gnitive_multipart::transfer_encoding::Encoder);
+
+        quote!(
+            impl #name
+            {
+                /// All `#[multipart]` fields as JSON object by field names in form
+                #[allow(dead_code)]
+                pub fn to_json_value(&self) -> ::serde_json::Value
+                {
+                    #[allow(unused_variables)]
+                    let base64 = |data: &[u8]| -> ::serde_json::Value
+                        {
+                            let mut encoded: Vec<u8> = vec![];
+                            let mut encoder = #encoder::new(#cte::Base64);
+                            encoder.update(data, &mut encoded);
+                            encoder.finish(&mut encoded);
+                            ::serde_json::Value::String(String::from_utf8_lossy(&encoded).into_owned())
+                        };
+
+                    let mut map = ::serde_json::Map::new();
+                    #(map.insert(#names.to_string(), #values);)*
+                    ::serde_json::Value::Object(map)
+                }
+            }
+        )
+    }
 
     /// Generate impl trait `MultipartParserTargetGenerated`
     pub fn impl_multipart_parser_target_generated(&self) -> TokenStream
@@ -85,7 +256,7 @@ impl MultipartStruct
         let mut matches = TokenStream::new();
         for field in &self.fields
             {
-                let tokens = field.parser_target_generated_item();
+                let tokens = field.parser_target_generated_item(self.case_insensitive);
                 matches.append_all(tokens);
             }
 
@@ -98,9 +269,49 @@ impl MultipartStruct
                     }
             }
 
+        let declared = self.fields.iter().map(|field| field.name.as_str());
+
+        let get_ordered = match self.ordered
+            {
+                true =>
+                    {
+                        let ordered = self.fields.iter().map(|field| field.name.as_str());
+                        quote!(
+                            fn get_ordered(&self) -> Vec<String>
+                            {
+                                vec![#(#ordered.to_string()),*]
+                            }
+                        )
+                    },
+                false => quote!()
+            };
+
         let name = &self.name;
 
-        let trait_name: TokenStream = quote!(gnitive_multipart::gnitive_multipart::MultipartParserTargetGenerated);
+        let matched_name = match self.case_insensitive
+            {
+                true => quote!( name.to_lowercase().as_str() ),
+                false => quote!( name )
+            };
+
+        let trait_name: TokenStream = quote!(::gnitive_multipart::gnitive_multipart::MultipartParserTargetGenerated);
+        let target_trait = quote!(::gnitive_multipart::gnitive_multipart::MultipartParserTarget);
+        let multipart_parse_error = quote!(::gnitive_multipart::gnitive_multipart::MultipartParseError);
+
+        let nameless = match self.require_name
+            {
+                true => quote!(
+                    {
+                        // error is handled by `MultipartParser` as other field errors, see `ProcessContentHooks::take_open_error`
+                        let processor = ::gnitive_multipart::process_content::NullProcessor::rejecting(#multipart_parse_error::MissingName);
+                        return Some(::std::rc::Rc::new(::std::cell::RefCell::new(processor)));
+                    }
+                ),
+                false => quote!( return <Self as #target_trait>::content_parser(self, self_, headers) )
+            };
+
+        let headers_type = quote!(::gnitive_multipart::gnitive_multipart::Headers);
+        let process_content = quote!(::gnitive_multipart::gnitive_multipart::ProcessContent);
 
         quote!(
             impl #trait_name for #name
@@ -111,14 +322,26 @@ impl MultipartStruct
                     result
                 }
 
-                fn content_parser_generated(&mut self, self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+                fn get_all_declared(&self) -> Vec<String>
                 {
-                    let name = headers.get_name().unwrap().as_ref();
+                    vec![#(#declared.to_string()),*]
+                }
+
+                #get_ordered
+
+                fn content_parser_generated(&mut self, self_: &::std::rc::Rc<::std::cell::RefCell<Self>>, headers: &#headers_type) -> Option<::std::rc::Rc<::std::cell::RefCell<#process_content>>>
+                {
+                    // part without name or with empty name (`name=""`) never match declared field
+                    let name: &str = match headers.get_name()
+                        {
+                            Some(name) if !name.is_empty() => name.as_ref(),
+                            _ => #nameless
+                        };
 
-                    match name
+                    match #matched_name
                         {
                             #matches
-                            _ => self.content_parser(self_, headers)
+                            _ => <Self as #target_trait>::content_parser(self, self_, headers)
                         }
                 }
 
@@ -0,0 +1,10 @@
+extern crate trybuild;
+
+
+/// Derive errors point to offending code (see `tests/ui/*.stderr`)
+#[test]
+fn compile_fail() -> ()
+{
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
\ No newline at end of file
@@ -0,0 +1,127 @@
+#![feature(attr_literals, try_from)]
+#[macro_use]
+extern crate gnitive_multipart_derive;
+extern crate gnitive_multipart;
+extern crate bytes;
+extern crate http;
+extern crate http_body;
+// `to_json_value`, generated by derive with feature `serde`, refers to `::serde_json`
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
+use std::cell::{RefCell};
+use std::collections::{VecDeque};
+use std::convert::{TryFrom};
+use std::future::{Future};
+use std::io::{Write, Error as IOError, ErrorKind};
+use std::pin::{Pin};
+use std::rc::{Rc};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use bytes::{Bytes};
+use http::{HeaderValue};
+use http_body::{Body, Frame};
+
+use gnitive_multipart::gnitive_multipart::{Headers, MultipartParserTarget, MultipartParseError, OnError, ProcessContent, ToMultipartParseError};
+use gnitive_multipart::http_body_parser::{parse_http_body};
+
+
+/// Body, which return `Poll::Pending` before every frame
+struct MockBody
+{
+    frames: VecDeque<Bytes>,
+    pending: bool
+}
+
+impl Body for MockBody
+{
+    type Data = Bytes;
+    type Error = IOError;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<Frame<Bytes>, IOError>>>
+    {
+        self.pending = !self.pending;
+        if self.pending
+            {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        Poll::Ready(self.frames.pop_front().map(|data| Ok(Frame::data(data))))
+    }
+}
+
+/// Poll `future` until ready
+fn block_on<F: Future>(future: F) -> F::Output
+{
+    fn noop_raw_waker() -> RawWaker
+    {
+        fn clone(_: *const ()) -> RawWaker { noop_raw_waker() }
+        fn noop(_: *const ()) -> () {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(::std::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut context = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop
+        {
+            if let Poll::Ready(result) = future.as_mut().poll(&mut context)
+                {
+                    return result;
+                }
+        }
+}
+
+/// Split `body` to frames of `chunk` bytes
+fn mock_body(body: &[u8], chunk: usize) -> MockBody
+{
+    MockBody
+        {
+            frames: body.chunks(chunk).map(|data| Bytes::from(data.to_vec())).collect(),
+            pending: false
+        }
+}
+
+
+#[derive(MultipartDerive, Default)]
+#[multipart]
+struct Form
+{
+    #[multipart]
+    pub i: i32,
+
+    #[multipart]
+    pub s: String,
+
+    #[multipart(name="file1")]
+    pub file: Vec<u8>,
+}
+
+impl MultipartParserTarget for Form {}
+
+#[test]
+fn parse_mock_body() -> ()
+{
+    let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"i\"\r\n\r\n42\r\n--boundary\r\nContent-Disposition: form-data; name=\"s\"\r\n\r\ntext\r\n--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\n\r\nfile\r\n--boundary--\r\n";
+    let content_type = HeaderValue::from_static("multipart/form-data; boundary=boundary");
+    for &chunk in &[1, 7, body.len()]
+        {
+            let target = Rc::new(RefCell::new(Form::default()));
+            block_on(parse_http_body(&content_type, mock_body(body, chunk), &target).unwrap()).unwrap();
+
+            let target = target.borrow();
+            assert_eq!(42, target.i);
+            assert_eq!("text", target.s);
+            assert_eq!(b"file".to_vec(), target.file);
+            #[cfg(feature = "serde")]
+            assert_eq!(serde_json::json!({"i": "42", "s": "text", "file1": "ZmlsZQ=="}), target.to_json_value());
+        }
+
+    let target = Rc::new(RefCell::new(Form::default()));
+    let result = block_on(parse_http_body(&content_type, mock_body(&body[..body.len() - 20], 7), &target).unwrap());
+    assert_eq!(ErrorKind::UnexpectedEof, result.unwrap_err().kind());
+
+    let content_type = HeaderValue::from_static("application/json");
+    assert!(parse_http_body(&content_type, mock_body(body, 7), &target).is_err());
+}
\ No newline at end of file
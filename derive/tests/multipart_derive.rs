@@ -0,0 +1,1499 @@
+#![feature(attr_literals, try_from)]
+#[macro_use]
+extern crate gnitive_multipart_derive;
+extern crate gnitive_multipart;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
+use std::cell::{RefCell};
+use std::collections::{HashMap};
+use std::convert::{TryFrom};
+use std::fs;
+use std::io::{Write, Error as IOError};
+use std::path::{PathBuf};
+use std::rc::{Rc};
+
+use gnitive_multipart::multipart_parser::{MultipartParser};
+use gnitive_multipart::gnitive_multipart::{FieldError, Headers, MultipartParserTarget, MultipartParseError, OnError, ProcessContent, ToMultipartParseError};
+
+
+/// Build `multipart/form-data` body with boundary `boundary` from list of `(headers, value)`
+fn body_with_headers(parts: &[(&str, &[u8])]) -> Vec<u8>
+{
+    let mut result: Vec<u8> = vec![];
+    for &(headers, value) in parts
+        {
+            result.extend(format!("--boundary\r\n{}\r\n\r\n", headers).as_bytes());
+            result.extend(value);
+            result.extend(b"\r\n");
+        }
+    result.extend(b"--boundary--\r\n");
+    result
+}
+
+/// Build `multipart/form-data` body with boundary `boundary` from list of `(name, value)`
+fn body(fields: &[(&str, &[u8])]) -> Vec<u8>
+{
+    let headers: Vec<String> = fields
+        .iter()
+        .map(|&(name, _value)| format!("Content-Disposition: form-data; name=\"{}\"", name))
+        .collect();
+    let parts: Vec<(&str, &[u8])> = headers
+        .iter()
+        .zip(fields.iter())
+        .map(|(headers, &(_name, value))| (headers.as_str(), value))
+        .collect();
+    body_with_headers(&parts)
+}
+
+/// Parse `body` into `target`
+fn parse<T>(target: T, body: &[u8], setup: &Fn(&mut MultipartParser<T>) -> ()) -> Rc<RefCell<T>>
+    where T: MultipartParserTarget + gnitive_multipart::gnitive_multipart::MultipartParserTargetGenerated
+{
+    let target: Rc<RefCell<T>> = Rc::new(RefCell::new(target));
+    {
+        let mut multipart_parser: MultipartParser<T> = MultipartParser::new_from_str("boundary", &target);
+        setup(&mut multipart_parser);
+        multipart_parser.write_all(body).unwrap();
+        multipart_parser.flush().unwrap();
+    }
+    target
+}
+
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Deferred
+{
+    #[multipart]
+    pub i: i32,
+
+    #[multipart]
+    pub f: f64,
+
+    #[multipart]
+    pub s: String,
+
+    /// `(count of error calls, names of fields in `FieldErrors`)`
+    errors: (usize, Vec<String>)
+}
+
+impl MultipartParserTarget for Deferred
+{
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        self.errors.0 += 1;
+        if let &MultipartParseError::FieldErrors(field_errors) = error
+            {
+                self.errors.1 = field_errors.iter().map(|field_error| field_error.name.clone()).collect();
+            }
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn defer_field_errors() -> ()
+{
+    let target = Deferred { i: 0, f: 0.0, s: String::new(), errors: (0, vec![]) };
+    let body = body(&[("i", b"one"), ("s", b"text"), ("f", b"two")]);
+    let target = parse(target, &body, &|multipart_parser| { multipart_parser.defer_field_errors(true); });
+
+    let target = target.borrow();
+    assert_eq!("text", target.s);
+    assert_eq!(1, target.errors.0);
+    assert_eq!(vec!["i".to_string(), "f".to_string()], target.errors.1);
+}
+
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Lowercase
+{
+    #[multipart(name="email", lowercase=true)]
+    pub email: String,
+
+    #[multipart(lowercase=true)]
+    pub login: Option<String>,
+
+    #[multipart]
+    pub s: String,
+}
+
+impl MultipartParserTarget for Lowercase {}
+
+#[test]
+fn lowercase() -> ()
+{
+    let target = Lowercase { email: String::new(), login: None, s: String::new() };
+    let body = body(&[("email", b"John.Doe@Example.COM"), ("login", b"JohnDoe"), ("s", b"MiXeD")]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!("john.doe@example.com", target.email);
+    assert_eq!(Some("johndoe".to_string()), target.login);
+    assert_eq!("MiXeD", target.s);
+}
+
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct FailFast
+{
+    #[multipart]
+    pub i: i32,
+
+    #[multipart]
+    pub s: String,
+
+    errors: usize
+}
+
+impl MultipartParserTarget for FailFast
+{
+    fn error(&mut self, _error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        self.errors += 1;
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn fail_fast() -> ()
+{
+    let target: Rc<RefCell<FailFast>> = Rc::new(RefCell::new(FailFast { i: 0, s: String::new(), errors: 0 }));
+    let body = body(&[("i", b"one"), ("s", b"text")]);
+
+    let mut multipart_parser: MultipartParser<FailFast> = MultipartParser::new_from_str("boundary", &target);
+    multipart_parser.fail_fast(true);
+    assert!(multipart_parser.write(&body).is_err());
+
+    let target = target.borrow();
+    assert_eq!(0, target.errors);
+    assert_eq!("", target.s);
+}
+
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct ContentTypeInto
+{
+    #[multipart(name="file", content_type_into="file_mime")]
+    pub file: Vec<u8>,
+
+    #[multipart(content_type_into="text_mime")]
+    pub text: String,
+
+    pub file_mime: String,
+
+    pub text_mime: Option<String>,
+}
+
+impl MultipartParserTarget for ContentTypeInto {}
+
+#[test]
+fn content_type_into() -> ()
+{
+    let target = ContentTypeInto { file: vec![], text: String::new(), file_mime: String::new(), text_mime: None };
+    let body = body_with_headers(&[
+        ("Content-Disposition: form-data; name=\"file\"; filename=\"a.png\"\r\nContent-Type: image/png", b"\x89PNG"),
+        ("Content-Disposition: form-data; name=\"text\"", b"text"),
+    ]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!(b"\x89PNG".to_vec(), target.file);
+    assert_eq!("image/png", target.file_mime);
+    assert_eq!(None, target.text_mime);
+}
+
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct FilenameInto
+{
+    #[multipart(name="file", filename_into="file_name")]
+    pub file: Vec<u8>,
+
+    #[multipart(filename_into="text_name")]
+    pub text: String,
+
+    pub file_name: String,
+
+    pub text_name: Option<String>,
+}
+
+impl MultipartParserTarget for FilenameInto {}
+
+#[test]
+fn filename_into() -> ()
+{
+    let target = FilenameInto { file: vec![], text: String::new(), file_name: String::new(), text_name: None };
+    let body = body_with_headers(&[
+        ("Content-Disposition: form-data; name=\"file\"; filename=\"report.pdf\"", b"%PDF"),
+        ("Content-Disposition: form-data; name=\"text\"", b"text"),
+    ]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!(b"%PDF".to_vec(), target.file);
+    assert_eq!("report.pdf", target.file_name);
+    // no `filename` - not changed
+    assert_eq!("text", target.text);
+    assert_eq!(None, target.text_name);
+}
+
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Extensions
+{
+    #[multipart(name="doc", extensions="pdf,docx")]
+    pub doc: Option<Vec<u8>>,
+
+    #[multipart(name="exe", extensions="pdf,docx")]
+    pub exe: Option<Vec<u8>>,
+
+    #[multipart(name="readme", extensions="pdf,docx")]
+    pub readme: Option<Vec<u8>>,
+
+    disallowed: Vec<(String, String)>,
+
+    aggregated: usize
+}
+
+impl MultipartParserTarget for Extensions
+{
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        if let &MultipartParseError::DisallowedExtension(ref name, ref extension) = error
+            {
+                self.disallowed.push((name.clone(), extension.clone()));
+            }
+        Ok(OnError::ContinueWithoutError)
+    }
+
+    fn on_errors(&mut self, errors: &[MultipartParseError]) -> ()
+    {
+        self.aggregated = errors.iter().filter(|error| match error { &&MultipartParseError::DisallowedExtension(_, _) => true, _ => false }).count();
+    }
+}
+
+#[test]
+fn extensions() -> ()
+{
+    let new = || Extensions { doc: None, exe: None, readme: None, disallowed: vec![], aggregated: 0 };
+    let body = body_with_headers(&[
+        ("Content-Disposition: form-data; name=\"doc\"; filename=\"report.PDF\"", b"%PDF"),
+        ("Content-Disposition: form-data; name=\"exe\"; filename=\"setup.exe\"", b"MZ"),
+        ("Content-Disposition: form-data; name=\"readme\"; filename=\"README\"", b"text"),
+    ]);
+    let target = parse(new(), &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!(Some(b"%PDF".to_vec()), target.doc);
+    assert_eq!(None, target.exe);
+    assert_eq!(None, target.readme);
+    assert_eq!(vec![("exe".to_string(), "exe".to_string()), ("readme".to_string(), String::new())], target.disallowed);
+    assert_eq!(2, target.aggregated);
+
+    // error is handled by parser: `default_on_error` instead of `error`, passed to `on_errors`
+    let target = parse(new(), &body, &|multipart_parser| { multipart_parser.set_default_on_error(OnError::SkipAndClose); });
+    let target = target.borrow();
+    assert_eq!(Some(b"%PDF".to_vec()), target.doc);
+    assert_eq!(None, target.exe);
+    assert!(target.disallowed.is_empty());
+    assert_eq!(2, target.aggregated);
+
+    let target = Rc::new(RefCell::new(new()));
+    let mut multipart_parser: MultipartParser<Extensions> = MultipartParser::new_from_str("boundary", &target);
+    multipart_parser.fail_fast(true);
+    assert!(multipart_parser.write(&body).is_err());
+    assert!(target.borrow().disallowed.is_empty());
+}
+
+#[derive(MultipartDerive, Default)]
+#[multipart]
+struct SizeLimited
+{
+    #[multipart(max_size=4)]
+    pub i: i32,
+
+    #[multipart(max_size=4)]
+    pub s: String,
+}
+
+impl MultipartParserTarget for SizeLimited {}
+
+#[test]
+fn max_size_continue() -> ()
+{
+    // default `error` continues after `SizeLimit`: truncated value is not assigned
+    let body = body(&[("i", b"12345"), ("s", b"abcd")]);
+    let target = parse(SizeLimited::default(), &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!(0, target.i);
+    assert_eq!("abcd", target.s);
+}
+
+#[derive(MultipartDerive, Default, Clone, Debug, PartialEq)]
+#[multipart]
+struct Address
+{
+    #[multipart]
+    pub city: String,
+
+    #[multipart]
+    pub zip: Option<u32>,
+}
+
+impl MultipartParserTarget for Address {}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Addresses
+{
+    #[multipart(name="address", max_items=2)]
+    pub addresses: Vec<Address>,
+
+    #[multipart]
+    pub s: String,
+}
+
+impl MultipartParserTarget for Addresses {}
+
+#[test]
+fn vec_of_nested_struct() -> ()
+{
+    let target = Addresses { addresses: vec![], s: String::new() };
+    let body = body(&[
+        ("address[1][city]", b"Berlin"),
+        ("address[0][city]", b"Paris"),
+        ("s", b"text"),
+        ("address[0][zip]", b"75001"),
+        ("address[1][zip]", b"10115"),
+        ("address[2][city]", b"Rome"),
+        ("address[0][unknown]", b"ignored"),
+    ]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!("text", target.s);
+    assert_eq!(vec![
+            Address { city: "Paris".to_string(), zip: Some(75001) },
+            Address { city: "Berlin".to_string(), zip: Some(10115) },
+        ], target.addresses);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct UnlimitedAddresses
+{
+    #[multipart(name="address")]
+    pub addresses: Vec<Address>,
+}
+
+impl MultipartParserTarget for UnlimitedAddresses {}
+
+#[test]
+fn vec_of_nested_struct_huge_index() -> ()
+{
+    // default `max_items` - `Vec` is not grown up to index, chosen by client
+    let target = UnlimitedAddresses { addresses: vec![] };
+    let body = body(&[
+        ("address[99999999][city]", b"Rome"),
+        ("address[1000][city]", b"Oslo"),
+        ("address[999][city]", b"Paris"),
+    ]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!(1000, target.addresses.len());
+    assert_eq!("Paris", target.addresses[999].city);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Ab
+{
+    #[multipart]
+    pub c_d: String,
+}
+
+impl MultipartParserTarget for Ab {}
+
+/// Without `proxy_prefix` proxy for `d` is `MultipartAbCD` - same as for `Ab::c_d`
+#[derive(MultipartDerive)]
+#[multipart(proxy_prefix="__Mp")]
+struct AbC
+{
+    #[multipart]
+    pub d: String,
+}
+
+impl MultipartParserTarget for AbC {}
+
+#[test]
+fn proxy_prefix() -> ()
+{
+    let target = parse(Ab { c_d: String::new() }, &body(&[("c_d", b"ab")]), &|_| ());
+    assert_eq!("ab", target.borrow().c_d);
+
+    let target = parse(AbC { d: String::new() }, &body(&[("d", b"abc")]), &|_| ());
+    assert_eq!("abc", target.borrow().d);
+}
+
+#[test]
+fn new_owned() -> ()
+{
+    let body = body(&[("email", b"A@B.C"), ("s", b"text")]);
+    let mut multipart_parser = MultipartParser::new_owned("boundary", Lowercase { email: String::new(), login: None, s: String::new() });
+    multipart_parser.write_all(&body).unwrap();
+    multipart_parser.flush().unwrap();
+
+    let target: Lowercase = multipart_parser.into_target();
+    assert_eq!("a@b.c", target.email);
+    assert_eq!("text", target.s);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct DecodeParamNames
+{
+    #[multipart(name="user[id]")]
+    pub user_id: i32,
+}
+
+impl MultipartParserTarget for DecodeParamNames {}
+
+#[test]
+fn decode_param_names() -> ()
+{
+    let body = body(&[("user%5Bid%5D", b"42")]);
+
+    let target = parse(DecodeParamNames { user_id: 0 }, &body, &|_| ());
+    assert_eq!(0, target.borrow().user_id);
+
+    let target = parse(DecodeParamNames { user_id: 0 }, &body, &|multipart_parser| { multipart_parser.decode_param_names(true); });
+    assert_eq!(42, target.borrow().user_id);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Unreceived
+{
+    #[multipart(name="first")]
+    pub first: Option<String>,
+
+    #[multipart(name="second")]
+    pub second: Option<String>,
+
+    #[multipart(name="third")]
+    pub third: Option<i32>,
+
+    #[multipart(name="address")]
+    pub addresses: Vec<Address>,
+
+    #[multipart(name="phone")]
+    pub phones: Vec<Address>,
+}
+
+impl MultipartParserTarget for Unreceived {}
+
+#[test]
+fn unreceived_optional() -> ()
+{
+    let body = body(&[("second", b"2"), ("address[0][city]", b"Paris"), ("unknown", b"?")]);
+    let target = Rc::new(RefCell::new(Unreceived { first: None, second: None, third: None, addresses: vec![], phones: vec![] }));
+    let mut multipart_parser = MultipartParser::new_from_str("boundary", &target);
+    assert_eq!(vec!["first", "second", "third", "address", "phone"], multipart_parser.unreceived_optional());
+
+    multipart_parser.write_all(&body).unwrap();
+    multipart_parser.flush().unwrap();
+    assert_eq!(vec!["first", "third", "phone"], multipart_parser.unreceived_optional());
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct NonEmpty
+{
+    #[multipart(name="username", non_empty=true)]
+    pub username: String,
+
+    #[multipart(non_empty=true)]
+    pub nickname: Option<String>,
+
+    empty_fields: Vec<String>
+}
+
+impl MultipartParserTarget for NonEmpty
+{
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        if let &MultipartParseError::EmptyField(ref name) = error
+            {
+                self.empty_fields.push(name.clone());
+            }
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn non_empty() -> ()
+{
+    let new = || NonEmpty { username: "default".to_string(), nickname: None, empty_fields: vec![] };
+
+    let target = parse(new(), &body(&[("username", b""), ("nickname", b"")]), &|_| ());
+    let target = target.borrow();
+    assert_eq!("default", target.username);
+    assert_eq!(None, target.nickname);
+    assert_eq!(vec!["username".to_string(), "nickname".to_string()], target.empty_fields);
+
+    let target = parse(new(), &body(&[("username", b"john"), ("nickname", b"J")]), &|_| ());
+    let target = target.borrow();
+    assert_eq!("john", target.username);
+    assert_eq!(Some("J".to_string()), target.nickname);
+    assert!(target.empty_fields.is_empty());
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Lossy
+{
+    #[multipart(name="note", lossy=true)]
+    pub note: String,
+
+    #[multipart(lossy=true)]
+    pub comment: Option<String>,
+
+    #[multipart]
+    pub strict: String,
+
+    errors: usize
+}
+
+impl MultipartParserTarget for Lossy
+{
+    fn error(&mut self, _error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        self.errors += 1;
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn lossy() -> ()
+{
+    let target = Lossy { note: String::new(), comment: None, strict: String::new(), errors: 0 };
+    let body = body(&[("note", b"a\xffb"), ("comment", b"\xc3\xa9\xc3"), ("strict", b"c\xffd")]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!("a\u{FFFD}b", target.note);
+    assert_eq!(Some("\u{e9}\u{FFFD}".to_string()), target.comment);
+    assert_eq!("", target.strict);
+    assert_eq!(1, target.errors);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct EmptyName
+{
+    #[multipart]
+    pub s: String,
+
+    /// names of parts, passed to `content_parser`
+    unmatched: Vec<Option<String>>
+}
+
+impl MultipartParserTarget for EmptyName
+{
+    fn content_parser(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        self.unmatched.push(headers.get_name().cloned());
+        None
+    }
+}
+
+#[test]
+fn empty_name() -> ()
+{
+    let body = body_with_headers(&[
+        ("Content-Disposition: form-data; name=\"\"", b"empty"),
+        ("Content-Disposition: form-data", b"no name"),
+        ("Content-Disposition: form-data; name=\"s\"", b"text"),
+    ]);
+    let target = parse(EmptyName { s: String::new(), unmatched: vec![] }, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!("text", target.s);
+    assert_eq!(vec![Some(String::new()), None], target.unmatched);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Files
+{
+    #[multipart(name="files")]
+    pub files: Vec<PathBuf>,
+}
+
+impl MultipartParserTarget for Files {}
+
+#[test]
+fn vec_of_files() -> ()
+{
+    let body = body_with_headers(&[
+        ("Content-Disposition: form-data; name=\"files\"; filename=\"a.txt\"", b"first file"),
+        ("Content-Disposition: form-data; name=\"files\"; filename=\"b.txt\"", b""),
+        ("Content-Disposition: form-data; name=\"files\"; filename=\"c.bin\"", b"third\r\n--file"),
+    ]);
+    let target = parse(Files { files: vec![] }, &body, &|_| ());
+
+    let files = target.borrow().files.clone();
+    let contents: Vec<Vec<u8>> = files.iter().map(|path| fs::read(path).unwrap()).collect();
+    for path in &files
+        {
+            fs::remove_file(path).unwrap();
+        }
+    assert_eq!(vec![b"first file".to_vec(), vec![], b"third\r\n--file".to_vec()], contents);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct FormDecode
+{
+    #[multipart(form_decode=true)]
+    pub q: String,
+
+    #[multipart(form_decode=true)]
+    pub tag: Option<String>,
+
+    #[multipart]
+    pub raw: String,
+}
+
+impl MultipartParserTarget for FormDecode {}
+
+#[test]
+fn form_decode() -> ()
+{
+    let target = FormDecode { q: String::new(), tag: None, raw: String::new() };
+    let body = body(&[("q", b"a+b%20c"), ("tag", b"x%2By"), ("raw", b"a+b%20c")]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!("a b c", target.q);
+    assert_eq!(Some("x+y".to_string()), target.tag);
+    assert_eq!("a+b%20c", target.raw);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct NormalizeNewlines
+{
+    #[multipart(normalize_newlines="lf")]
+    pub lf: String,
+
+    #[multipart(normalize_newlines="crlf")]
+    pub crlf: Option<String>,
+}
+
+impl MultipartParserTarget for NormalizeNewlines {}
+
+#[test]
+fn normalize_newlines() -> ()
+{
+    let target = NormalizeNewlines { lf: String::new(), crlf: None };
+    let body = body(&[("lf", b"a\r\nb\nc\r\n"), ("crlf", b"a\r\nb\nc\n")]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!("a\nb\nc\n", target.lf);
+    assert_eq!(Some("a\r\nb\r\nc\r\n".to_string()), target.crlf);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct RequireContentType
+{
+    #[multipart(require_content_type="application/json")]
+    pub config: Option<String>,
+
+    disallowed: Vec<(String, String)>
+}
+
+impl MultipartParserTarget for RequireContentType
+{
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        if let &MultipartParseError::DisallowedContentType(ref name, ref content_type) = error
+            {
+                self.disallowed.push((name.clone(), content_type.clone()));
+            }
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn require_content_type() -> ()
+{
+    let new = || RequireContentType { config: None, disallowed: vec![] };
+
+    let with_headers = body_with_headers(&[("Content-Disposition: form-data; name=\"config\"\r\nContent-Type: text/plain", b"{}")]);
+    let target = parse(new(), &with_headers, &|_| ());
+    let target = target.borrow();
+    assert_eq!(None, target.config);
+    assert_eq!(vec![("config".to_string(), "text/plain".to_string())], target.disallowed);
+
+    let with_headers = body_with_headers(&[("Content-Disposition: form-data; name=\"config\"\r\nContent-Type: Application/JSON; charset=utf-8", b"{}")]);
+    let target = parse(new(), &with_headers, &|_| ());
+    let target = target.borrow();
+    assert_eq!(Some("{}".to_string()), target.config);
+    assert!(target.disallowed.is_empty());
+
+    // no `Content-Type`
+    let target = parse(new(), &body(&[("config", b"{}")]), &|_| ());
+    assert_eq!(vec![("config".to_string(), "".to_string())], target.borrow().disallowed);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct TransferEncoding
+{
+    #[multipart(required=false)]
+    pub text: Option<String>,
+
+    #[multipart(required=false)]
+    pub data: Option<Vec<u8>>,
+
+    unsupported: Vec<(String, String)>,
+
+    invalid: Vec<(String, String)>
+}
+
+impl MultipartParserTarget for TransferEncoding
+{
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        match error
+            {
+                &MultipartParseError::UnsupportedEncoding(ref name, ref encoding) => self.unsupported.push((name.clone(), encoding.clone())),
+                &MultipartParseError::InvalidEncoding(ref name, ref message) => self.invalid.push((name.clone(), message.clone())),
+                _ => ()
+            }
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn transfer_encoding() -> ()
+{
+    let new = || TransferEncoding { text: None, data: None, unsupported: vec![], invalid: vec![] };
+
+    for cte in &["7bit", "8bit", "binary"]
+        {
+            let with_headers = body_with_headers(&[
+                (&format!("Content-Disposition: form-data; name=\"text\"\r\nContent-Transfer-Encoding: {}", cte), b"a=3D"),
+                (&format!("Content-Disposition: form-data; name=\"data\"\r\nContent-Transfer-Encoding: {}", cte), b"\x00\xff")]);
+            let target = parse(new(), &with_headers, &|_| ());
+            let target = target.borrow();
+            assert_eq!(Some("a=3D".to_string()), target.text, "{}", cte);
+            assert_eq!(Some(b"\x00\xff".to_vec()), target.data, "{}", cte);
+            assert!(target.unsupported.is_empty());
+        }
+
+    let with_headers = body_with_headers(&[
+        ("Content-Disposition: form-data; name=\"text\"\r\nContent-Transfer-Encoding: quoted-printable", b"a=3D"),
+        ("Content-Disposition: form-data; name=\"data\"\r\nContent-Transfer-Encoding: x-uuencode", b"#86)C")]);
+    let target = parse(new(), &with_headers, &|_| ());
+    let target = target.borrow();
+    assert_eq!(Some("a=".to_string()), target.text);
+    assert_eq!(None, target.data);
+    assert_eq!(vec![("data".to_string(), "x-uuencode".to_string())], target.unsupported);
+
+    // invalid base64 - field error, parsing continues
+    let with_headers = body_with_headers(&[
+        ("Content-Disposition: form-data; name=\"data\"\r\nContent-Transfer-Encoding: base64", b"YW!j"),
+        ("Content-Disposition: form-data; name=\"text\"\r\nContent-Transfer-Encoding: base64", b"YWJj")]);
+    let target = parse(new(), &with_headers, &|_| ());
+    let target = target.borrow();
+    assert_eq!(None, target.data);
+    assert_eq!(Some("abc".to_string()), target.text);
+    assert_eq!(vec![("data".to_string(), "Invalid base64 symbol 0x21".to_string())], target.invalid);
+}
+
+#[derive(MultipartDerive)]
+#[multipart(derive_default=true)]
+struct BoolFormat
+{
+    #[multipart(name="agree", bool_format="lenient")]
+    pub agree: bool,
+
+    #[multipart(bool_format="lenient")]
+    pub subscribe: Option<bool>,
+
+    #[multipart(bool_format="strict")]
+    pub strict: Option<bool>,
+}
+
+impl MultipartParserTarget for BoolFormat
+{
+    fn error(&mut self, _error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn bool_format() -> ()
+{
+    for &(value, expected) in &[("true", true), ("1", true), ("yes", true), ("y", true), ("on", true), ("On", true), ("YES", true), (" TRUE ", true),
+                                ("false", false), ("0", false), ("no", false), ("n", false), ("off", false), ("OFF", false), ("N", false)]
+        {
+            let target: BoolFormat = parse_into(&body(&[("agree", (!expected).to_string().as_bytes()), ("agree", value.as_bytes()), ("subscribe", value.as_bytes())]));
+            assert_eq!((expected, Some(expected)), (target.agree, target.subscribe), "{}", value);
+        }
+
+    // invalid value - field is not changed
+    let target: BoolFormat = parse_into(&body(&[("agree", b"true"), ("agree", b"maybe"), ("subscribe", b"2"), ("strict", b"yes")]));
+    assert_eq!((true, None, None), (target.agree, target.subscribe, target.strict));
+    let target: BoolFormat = parse_into(&body(&[("agree", b"on"), ("strict", b"true")]));
+    assert_eq!((true, Some(true)), (target.agree, target.strict));
+}
+
+#[derive(MultipartDerive, Default)]
+#[multipart]
+struct Repeated
+{
+    #[multipart(name="tag")]
+    pub tags: Vec<String>,
+
+    #[multipart(name="id")]
+    pub ids: Vec<i32>,
+
+    #[multipart(lowercase=true)]
+    pub codes: Vec<String>,
+
+    pub errors: usize,
+}
+
+impl MultipartParserTarget for Repeated
+{
+    fn error(&mut self, _error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        self.errors += 1;
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn repeated() -> ()
+{
+    let target: Repeated = parse_into(&body(&[("tag", b"a"), ("id", b"1"), ("tag", b"b"), ("id", b"x"), ("codes", b"AB"), ("tag", b"c"), ("id", b"3")]));
+    assert_eq!(vec!["a", "b", "c"], target.tags);
+    // part with conversion error is not pushed
+    assert_eq!(vec![1, 3], target.ids);
+    assert_eq!(vec!["ab"], target.codes);
+    assert_eq!(1, target.errors);
+
+    let target: Repeated = parse_into(&body(&[("id", b"7")]));
+    assert!(target.tags.is_empty());
+    assert_eq!(vec![7], target.ids);
+}
+
+#[derive(MultipartDerive)]
+#[multipart(derive_default=true)]
+struct DeriveDefault
+{
+    #[multipart]
+    pub title: String,
+
+    #[multipart]
+    pub count: Option<u32>,
+
+    #[multipart]
+    pub enabled: bool,
+
+    #[multipart]
+    pub data: Vec<u8>,
+}
+
+impl MultipartParserTarget for DeriveDefault {}
+
+/// Parse `body` into new `T::default()`
+fn parse_into<T>(body: &[u8]) -> T
+    where T: Default + MultipartParserTarget + gnitive_multipart::gnitive_multipart::MultipartParserTargetGenerated
+{
+    let mut multipart_parser: MultipartParser<T> = MultipartParser::new_owned("boundary", T::default());
+    multipart_parser.write_all(body).unwrap();
+    multipart_parser.flush().unwrap();
+    multipart_parser.into_target()
+}
+
+#[test]
+fn derive_default() -> ()
+{
+    let target = DeriveDefault::default();
+    assert_eq!(("", None, false, vec![]), (target.title.as_str(), target.count, target.enabled, target.data));
+
+    let target: DeriveDefault = parse_into(&body(&[("title", b"Report"), ("enabled", b"true")]));
+    assert_eq!("Report", target.title);
+    assert_eq!(None, target.count);
+    assert!(target.enabled);
+    assert!(target.data.is_empty());
+}
+
+#[derive(MultipartDerive)]
+#[multipart(collect_results=true)]
+struct CollectResults
+{
+    field_results: HashMap<String, Result<(), FieldError>>,
+
+    #[multipart]
+    pub count: u32,
+
+    #[multipart(non_empty=true)]
+    pub title: String,
+
+    #[multipart]
+    pub ratio: f32,
+
+    /// count of `error` calls
+    errors: usize
+}
+
+impl MultipartParserTarget for CollectResults
+{
+    fn error(&mut self, _error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        self.errors += 1;
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn collect_results() -> ()
+{
+    let target = CollectResults { field_results: HashMap::new(), count: 0, title: "untitled".to_string(), ratio: 0.0, errors: 0 };
+    let body = body(&[("count", b"42"), ("title", b""), ("ratio", b"abc")]);
+    let target = parse(target, &body, &|_| ());
+    let target = target.borrow();
+
+    assert_eq!(Some(true), target.field_result("count").map(|result| result.is_ok()));
+    assert_eq!(42, target.count);
+
+    match target.field_result("title")
+        {
+            Some(&Err(ref error)) => assert_eq!(("title", &b""[..], "empty value"), (error.name.as_str(), &error.raw_data[..], error.message.as_str())),
+            _ => panic!("Err expected for 'title'")
+        }
+    assert_eq!("untitled", target.title);
+
+    match target.field_result("ratio")
+        {
+            Some(&Err(ref error)) => assert_eq!(("ratio", &b"abc"[..]), (error.name.as_str(), &error.raw_data[..])),
+            _ => panic!("Err expected for 'ratio'")
+        }
+    assert!(target.field_result("other").is_none());
+    assert_eq!(0, target.errors);
+}
+
+#[derive(MultipartDerive)]
+#[multipart(case_insensitive=true)]
+struct CaseInsensitive
+{
+    #[multipart(required=false)]
+    pub email: String,
+
+    #[multipart(name="Full_Name")]
+    pub full_name: Option<String>,
+
+    /// names of parts, passed to `content_parser`
+    unmatched: Vec<String>
+}
+
+impl MultipartParserTarget for CaseInsensitive
+{
+    fn content_parser(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        self.unmatched.push(headers.get_name().cloned().unwrap_or_default());
+        None
+    }
+}
+
+#[test]
+fn case_insensitive() -> ()
+{
+    let target = CaseInsensitive { email: String::new(), full_name: None, unmatched: vec![] };
+    let body = body(&[("Email", b"john@example.com"), ("FULL_NAME", b"John Doe"), ("phone", b"123")]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!("john@example.com", target.email);
+    assert_eq!(Some("John Doe".to_string()), target.full_name);
+    assert_eq!(vec!["phone".to_string()], target.unmatched);
+}
+
+#[derive(MultipartDerive)]
+#[multipart(case_insensitive=true)]
+struct CaseInsensitiveNested
+{
+    #[multipart]
+    pub email: String,
+
+    #[multipart(name="Address")]
+    pub addresses: Vec<Address>,
+}
+
+impl MultipartParserTarget for CaseInsensitiveNested {}
+
+#[test]
+fn case_insensitive_nested() -> ()
+{
+    // parts of `Vec<NestedStruct>` are matched case-sensitively
+    let target = CaseInsensitiveNested { email: String::new(), addresses: vec![] };
+    let body = body(&[("EMAIL", b"john@example.com"), ("Address[0][city]", b"Paris"), ("address[1][city]", b"Rome")]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!("john@example.com", target.email);
+    assert_eq!(vec![Address { city: "Paris".to_string(), zip: None }], target.addresses);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Handler
+{
+    #[multipart(name="event", handler="on_event")]
+    pub event: String,
+
+    #[multipart(handler="on_count")]
+    pub count: Option<u32>,
+
+    /// values, passed to handlers
+    events: Vec<String>,
+    counts: Vec<Option<u32>>
+}
+
+impl Handler
+{
+    fn on_event(&mut self, value: String) -> ()
+    {
+        self.events.push(value);
+    }
+
+    fn on_count(&mut self, value: Option<u32>) -> ()
+    {
+        self.counts.push(value);
+    }
+}
+
+impl MultipartParserTarget for Handler {}
+
+#[test]
+fn handler() -> ()
+{
+    let target = Handler { event: String::new(), count: None, events: vec![], counts: vec![] };
+    let body = body(&[("event", b"login"), ("count", b"3"), ("event", b"logout"), ("count", b"x")]);
+    let target = parse(target, &body, &|_| ());
+
+    let target = target.borrow();
+    assert_eq!(vec!["login".to_string(), "logout".to_string()], target.events);
+    assert_eq!(vec![Some(3)], target.counts);
+    assert_eq!("", target.event);
+    assert_eq!(None, target.count);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct Finite
+{
+    #[multipart(name="price", finite=true)]
+    pub price: f64,
+
+    #[multipart(finite=true)]
+    pub discount: Option<f32>,
+
+    non_finite: Vec<String>
+}
+
+impl MultipartParserTarget for Finite
+{
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        if let &MultipartParseError::NonFiniteFloat(ref name) = error
+            {
+                self.non_finite.push(name.clone());
+            }
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn finite() -> ()
+{
+    let new = || Finite { price: 1.0, discount: None, non_finite: vec![] };
+
+    let target = parse(new(), &body(&[("price", b"NaN"), ("discount", b"-inf")]), &|_| ());
+    let target = target.borrow();
+    assert_eq!(1.0, target.price);
+    assert_eq!(None, target.discount);
+    assert_eq!(vec!["price".to_string(), "discount".to_string()], target.non_finite);
+
+    let target = parse(new(), &body(&[("price", b"9.99"), ("discount", b"0.5")]), &|_| ());
+    let target = target.borrow();
+    assert_eq!(9.99, target.price);
+    assert_eq!(Some(0.5), target.discount);
+    assert!(target.non_finite.is_empty());
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct EmptyAsNone
+{
+    #[multipart(name="mid", empty_as_none=true)]
+    pub mid: Option<String>,
+
+    #[multipart(empty_as_none=true)]
+    pub age: Option<i32>,
+
+    #[multipart]
+    pub note: Option<String>,
+
+    #[multipart]
+    pub count: Option<i32>,
+
+    errors: Vec<String>
+}
+
+impl MultipartParserTarget for EmptyAsNone
+{
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        if let &MultipartParseError::ParseIntError(ref name, _, _) = error
+            {
+                self.errors.push(name.clone());
+            }
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+#[test]
+fn empty_as_none() -> ()
+{
+    let new = || EmptyAsNone { mid: Some("default".to_string()), age: Some(1), note: None, count: None, errors: vec![] };
+
+    let target = parse(new(), &body(&[("mid", b""), ("age", b""), ("note", b""), ("count", b"")]), &|_| ());
+    let target = target.borrow();
+    assert_eq!(None, target.mid);
+    assert_eq!(None, target.age);
+    assert_eq!(Some("".to_string()), target.note);
+    assert_eq!(None, target.count);
+    assert_eq!(vec!["count".to_string()], target.errors);
+
+    let target = parse(new(), &body(&[("mid", b"m"), ("age", b"42")]), &|_| ());
+    let target = target.borrow();
+    assert_eq!(Some("m".to_string()), target.mid);
+    assert_eq!(Some(42), target.age);
+    assert!(target.errors.is_empty());
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct ConversionAction
+{
+    #[multipart]
+    pub count: i32,
+
+    #[multipart]
+    pub title: String,
+
+    abort: bool,
+    errors: usize,
+    aggregated: usize
+}
+
+impl MultipartParserTarget for ConversionAction
+{
+    fn error(&mut self, _error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        self.errors += 1;
+        match self.abort
+            {
+                true => Err(IOError::new(::std::io::ErrorKind::Other, "abort")),
+                false => Ok(OnError::ContinueWithoutError)
+            }
+    }
+
+    fn on_errors(&mut self, errors: &[MultipartParseError]) -> ()
+    {
+        self.aggregated = errors.iter().filter(|error| match error { &&MultipartParseError::FieldError(_) => true, _ => false }).count();
+    }
+}
+
+#[test]
+fn conversion_error_action() -> ()
+{
+    let new = |abort| Rc::new(RefCell::new(ConversionAction { count: 0, title: String::new(), abort, errors: 0, aggregated: 0 }));
+    let body = body(&[("count", b"x"), ("title", b"Report")]);
+
+    // `Err` of `error` aborts parsing
+    let target = new(true);
+    {
+        let mut multipart_parser: MultipartParser<ConversionAction> = MultipartParser::new_from_str("boundary", &target);
+        assert!(multipart_parser.write(&body).is_err());
+    }
+    assert_eq!(1, target.borrow().errors);
+    assert_eq!("", target.borrow().title);
+
+    // default action is applied without `error` call
+    let target = parse(ConversionAction { count: 0, title: String::new(), abort: true, errors: 0, aggregated: 0 }, &body, &|multipart_parser| { multipart_parser.set_default_on_error(OnError::Skip); });
+    let target = target.borrow();
+    assert_eq!(0, target.errors);
+    assert_eq!(0, target.count);
+    assert_eq!("Report", target.title);
+    assert_eq!(1, target.aggregated);
+
+    // passed to `error` and `on_errors` by default
+    let target = parse(ConversionAction { count: 0, title: String::new(), abort: false, errors: 0, aggregated: 0 }, &body, &|_| ());
+    let target = target.borrow();
+    assert_eq!(1, target.errors);
+    assert_eq!(1, target.aggregated);
+
+    let target = new(false);
+    {
+        let mut multipart_parser: MultipartParser<ConversionAction> = MultipartParser::new_from_str("boundary", &target);
+        multipart_parser.fail_fast(true);
+        assert!(multipart_parser.write(&body).is_err());
+    }
+    assert_eq!(0, target.borrow().errors);
+}
+
+/// Derive in module without `use` of `Rc`, `RefCell`, `Headers`, `TryFrom` and traits of `gnitive_multipart`
+mod no_imports
+{
+    #[derive(MultipartDerive, Default, Clone)]
+    #[multipart]
+    pub struct Item
+    {
+        #[multipart]
+        pub title: String,
+    }
+
+    #[derive(MultipartDerive, Default)]
+    #[multipart]
+    pub struct Form
+    {
+        #[multipart(name="count")]
+        pub count: Option<u32>,
+
+        #[multipart(non_empty=true)]
+        pub note: String,
+
+        #[multipart(name="files", dir="/tmp")]
+        pub files: Vec<::std::path::PathBuf>,
+
+        #[multipart(name="items")]
+        pub items: Vec<Item>,
+
+        pub errors: usize
+    }
+
+    impl ::gnitive_multipart::gnitive_multipart::MultipartParserTarget for Item {}
+
+    impl ::gnitive_multipart::gnitive_multipart::MultipartParserTarget for Form
+    {
+        fn error(&mut self, _error: &::gnitive_multipart::gnitive_multipart::MultipartParseError) -> Result<::gnitive_multipart::gnitive_multipart::OnError, ::std::io::Error>
+        {
+            self.errors += 1;
+            Ok(::gnitive_multipart::gnitive_multipart::OnError::ContinueWithoutError)
+        }
+    }
+}
+
+#[test]
+fn no_imports() -> ()
+{
+    let target = parse(no_imports::Form::default(), &body(&[("count", b"7"), ("note", b""), ("items[0][title]", b"first")]), &|_| ());
+    let target = target.borrow();
+    assert_eq!(Some(7), target.count);
+    assert_eq!("first", target.items[0].title);
+    assert_eq!(1, target.errors);
+}
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct FromHeader
+{
+    #[multipart(name="token", from_header="X-Token")]
+    pub token: String,
+
+    #[multipart(from_header="X-Count")]
+    pub count: Option<u32>
+}
+
+impl MultipartParserTarget for FromHeader {}
+
+#[test]
+fn from_header() -> ()
+{
+    let new = || FromHeader { token: "default".to_string(), count: None };
+
+    let with_headers = body_with_headers(&[("Content-Disposition: form-data; name=\"token\"\r\nx-token: secret", b"body bytes"), ("Content-Disposition: form-data; name=\"count\"\r\nX-Count: 5", b"")]);
+    let target = parse(new(), &with_headers, &|_| ());
+    let target = target.borrow();
+    assert_eq!("secret", target.token);
+    assert_eq!(Some(5), target.count);
+
+    // no header - field not changed
+    let target = parse(new(), &body(&[("token", b"body bytes")]), &|_| ());
+    assert_eq!("default", target.borrow().token);
+
+    // `Content-Transfer-Encoding` of body is not applied to header value
+    let encoded = body_with_headers(&[("Content-Disposition: form-data; name=\"token\"\r\nContent-Transfer-Encoding: base64\r\nX-Token: secret", b"Ym9keQ==")]);
+    let target = parse(new(), &encoded, &|_| ());
+    assert_eq!("secret", target.borrow().token);
+}
+
+#[derive(MultipartDerive)]
+#[multipart(ordered=true)]
+struct Ordered
+{
+    #[multipart]
+    pub first: String,
+
+    #[multipart]
+    pub second: String,
+
+    #[multipart]
+    pub third: Option<String>,
+
+    out_of_order: Vec<(String, String)>
+}
+
+impl MultipartParserTarget for Ordered
+{
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        if let &MultipartParseError::OutOfOrder { ref expected, ref found } = error
+            {
+                self.out_of_order.push((expected.clone(), found.clone()));
+            }
+        Ok(OnError::Skip)
+    }
+}
+
+#[test]
+fn ordered() -> ()
+{
+    let new = || Ordered { first: String::new(), second: String::new(), third: None, out_of_order: vec![] };
+
+    let target = parse(new(), &body(&[("second", b"2"), ("first", b"1"), ("third", b"3")]), &|_| ());
+    let target = target.borrow();
+    assert_eq!(vec![("third".to_string(), "first".to_string())], target.out_of_order);
+    assert_eq!("", target.first);
+    assert_eq!("2", target.second);
+    assert_eq!(Some("3".to_string()), target.third);
+
+    // optional `third` absent, unknown field and repeated field allowed
+    let target = parse(new(), &body(&[("first", b"1"), ("other", b"x"), ("second", b"2"), ("second", b"22")]), &|_| ());
+    let target = target.borrow();
+    assert!(target.out_of_order.is_empty());
+    assert_eq!("22", target.second);
+}
+
+#[derive(MultipartDerive)]
+#[multipart(require_name=true)]
+struct RequireName
+{
+    #[multipart]
+    pub title: String,
+
+    missing_name: usize,
+    content_parser_called: bool,
+
+    aggregated: usize
+}
+
+impl MultipartParserTarget for RequireName
+{
+    fn content_parser(&mut self, _self_: &Rc<RefCell<Self>>, _headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        self.content_parser_called = true;
+        None
+    }
+
+    fn error(&mut self, error: &MultipartParseError) -> Result<OnError, IOError>
+    {
+        if let &MultipartParseError::MissingName = error
+            {
+                self.missing_name += 1;
+            }
+        Ok(OnError::ContinueWithoutError)
+    }
+
+    fn on_errors(&mut self, errors: &[MultipartParseError]) -> ()
+    {
+        self.aggregated = errors.iter().filter(|error| match error { &&MultipartParseError::MissingName => true, _ => false }).count();
+    }
+}
+
+#[test]
+fn require_name() -> ()
+{
+    let new = || RequireName { title: String::new(), missing_name: 0, content_parser_called: false, aggregated: 0 };
+    let with_headers = body_with_headers(&[("Content-Disposition: form-data", b"nameless"), ("Content-Disposition: form-data; name=\"\"", b"empty"), ("Content-Disposition: form-data; name=\"title\"", b"Report")]);
+    let target = parse(new(), &with_headers, &|_| ());
+    let target = target.borrow();
+    assert_eq!(2, target.missing_name);
+    assert!(!target.content_parser_called);
+    assert_eq!("Report", target.title);
+    assert_eq!(2, target.aggregated);
+
+    // error is handled by parser: `default_on_error` instead of `error`, passed to `on_errors`
+    let target = parse(new(), &with_headers, &|multipart_parser| { multipart_parser.set_default_on_error(OnError::Skip); });
+    let target = target.borrow();
+    assert_eq!(0, target.missing_name);
+    assert_eq!("Report", target.title);
+    assert_eq!(2, target.aggregated);
+
+    let target = Rc::new(RefCell::new(new()));
+    let mut multipart_parser: MultipartParser<RequireName> = MultipartParser::new_from_str("boundary", &target);
+    multipart_parser.fail_fast(true);
+    assert!(multipart_parser.write(&with_headers).is_err());
+    assert_eq!(0, target.borrow().missing_name);
+}
+
+#[cfg(feature = "serde")]
+#[derive(MultipartDerive)]
+#[multipart]
+struct Json
+{
+    #[multipart(name="title")]
+    pub title: String,
+
+    #[multipart]
+    pub count: Option<u32>,
+
+    #[multipart]
+    pub missing: Option<String>,
+
+    #[multipart]
+    pub data: Vec<u8>,
+
+    pub not_in_form: bool
+}
+
+#[cfg(feature = "serde")]
+impl MultipartParserTarget for Json {}
+
+#[cfg(feature = "serde")]
+#[test]
+fn to_json_value() -> ()
+{
+    let target = Json { title: String::new(), count: None, missing: None, data: vec![], not_in_form: true };
+    let target = parse(target, &body(&[("title", b"Report"), ("count", b"3"), ("data", b"\x00\xFF\xFE")]), &|_| ());
+    let json = target.borrow().to_json_value();
+    assert_eq!("{\"count\":\"3\",\"data\":\"AP/+\",\"missing\":null,\"title\":\"Report\"}", json.to_string());
+}
\ No newline at end of file
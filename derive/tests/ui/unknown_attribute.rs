@@ -0,0 +1,20 @@
+#[macro_use]
+extern crate gnitive_multipart_derive;
+extern crate gnitive_multipart;
+
+use gnitive_multipart::gnitive_multipart::{MultipartParserTarget};
+
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct UnknownAttribute
+{
+    #[multipart(name="a", unknown=true)]
+    pub a: String
+}
+
+impl MultipartParserTarget for UnknownAttribute {}
+
+fn main() -> ()
+{
+}
\ No newline at end of file
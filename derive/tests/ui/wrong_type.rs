@@ -0,0 +1,20 @@
+#[macro_use]
+extern crate gnitive_multipart_derive;
+extern crate gnitive_multipart;
+
+use gnitive_multipart::gnitive_multipart::{MultipartParserTarget};
+
+
+#[derive(MultipartDerive)]
+#[multipart]
+struct WrongType
+{
+    #[multipart(lowercase=true)]
+    pub a: Option<u32>
+}
+
+impl MultipartParserTarget for WrongType {}
+
+fn main() -> ()
+{
+}
\ No newline at end of file
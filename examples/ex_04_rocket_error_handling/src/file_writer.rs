@@ -4,6 +4,7 @@ use gnitive_multipart::gnitive_multipart::{ProcessContent, ProcessParams, Header
 use std::env;
 use std::fs::{File};
 use std::io::prelude::*;
+use std::io::{Error as IOError};
 use std::path::{PathBuf};
 
 
@@ -70,22 +71,24 @@ impl ProcessContent for FileWriter
         self.file = Some(File::create(&self.path).unwrap());
     }
 
-    fn write(&mut self, _headers: &Headers, data: &Vec<u8>) -> ()
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
     {
         if let Some(ref mut file) = self.file
             {
-                file.write(data).unwrap();
+                file.write_all(data)?;
                 self.size += data.len();
             }
+        Ok(())
     }
 
-    fn flush(&mut self, _headers: &Headers) -> ()
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
     {
         if let Some(ref mut file) = self.file
             {
-                file.flush().unwrap();
+                file.flush()?;
             }
         self.file = None;
+        Ok(())
     }
 
     /// Return parameters for processing current field.
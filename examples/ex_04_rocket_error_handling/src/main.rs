@@ -243,6 +243,111 @@ impl MultipartParserTarget for Test
                         Ok(OnError::ContinueWithoutError)
                     }
 
+                &MultipartParseError::FieldErrors(ref _field_errors) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::FieldError(ref _field_error) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::DisallowedExtension(ref _name, ref _extension) =>
+                    {
+                        Ok(OnError::Skip)
+                    }
+
+                &MultipartParseError::DisallowedContentType(ref _name, ref _content_type) =>
+                    {
+                        Ok(OnError::Skip)
+                    }
+
+                &MultipartParseError::EmptyField(ref _name) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::NonFiniteFloat(ref _name) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::ChecksumMismatch(ref _name) =>
+                    {
+                        Ok(OnError::Skip)
+                    }
+
+                &MultipartParseError::OutOfOrder { expected: _, found: _ } =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::ValidationFailed(ref _name, ref _message) =>
+                    {
+                        Ok(OnError::Skip)
+                    }
+
+                &MultipartParseError::UnsupportedEncoding(ref _name, ref _encoding) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::InvalidEncoding(ref _name, ref _message) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::MemoryLimit(_max_memory) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::MalformedBoundary(_offset) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::HeaderRefeedLimit(_max_refeed) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::TotalSizeLimit(_max_size) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::TooManyParts(_max_parts) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::PartTimeout(ref _name) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::InvalidParameter(ref _name, ref _reason) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::MissingName =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::DuplicateContentType(ref _name) =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
+                &MultipartParseError::TooFewParts { expected: _, received: _ } =>
+                    {
+                        Ok(OnError::ContinueWithoutError)
+                    }
+
 
                 &MultipartParseError::NoError => { Ok(OnError::ContinueWithoutError) }
             }
@@ -0,0 +1,138 @@
+//! Count heap allocations of `MultipartParser` for one large text part (10000 lines), written by one buffer,
+//! with processor, which only count bytes (like hashing processors).
+//!
+//! Content is passed to `ProcessContent::write` by runs between possible starts of boundary (every `\r`),
+//! and `\r` is written after mismatch, so this part is written by about 20000 calls. Data of each call is passed
+//! as subslice of caller's buffer (`ProcessContent::write` takes `&[u8]`), without copy: bench fails, if whole form
+//! takes `LINES / 100` allocations or more (allocations per call).
+//!
+//! ```text
+//! cargo bench --bench content_allocations
+//! ```
+
+#![feature(test)]
+extern crate gnitive_multipart;
+extern crate test;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{RefCell};
+use std::io::{Write, Error as IOError};
+use std::rc::{Rc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use test::{Bencher, black_box};
+
+use gnitive_multipart::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessParams, Headers};
+use gnitive_multipart::multipart_parser::{MultipartParser};
+
+
+/// Count of lines in part
+const LINES: usize = 10000;
+
+
+/// System allocator, which count allocations
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8
+    {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) -> ()
+    {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Count of allocations in `f`
+fn allocations<F: FnOnce() -> R, R>(f: F) -> usize
+{
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+
+/// Count bytes of part, data is not stored
+struct Counter
+{
+    params: ProcessParams,
+    size: Rc<RefCell<usize>>
+}
+
+impl ProcessContent for Counter
+{
+    fn open(&mut self, _headers: &Headers) -> () {}
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        *self.size.borrow_mut() += data.len();
+        Ok(())
+    }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError> { Ok(()) }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+}
+
+/// Select `Counter` for every part
+struct Target
+{
+    size: Rc<RefCell<usize>>
+}
+
+impl MultipartParserTarget for Target {}
+
+impl MultipartParserTargetGenerated for Target
+{
+    fn get_all_required(&self) -> Vec<String> { vec![] }
+
+    fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        Some(Rc::new(RefCell::new(Counter { params: ProcessParams::new(headers.get_name()?.as_str(), None), size: self.size.clone() })))
+    }
+}
+
+/// Form with one part of `LINES` lines
+fn body() -> Vec<u8>
+{
+    let mut result: Vec<u8> = b"--boundary\r\nContent-Disposition: form-data; name=\"text\"\r\n\r\n".to_vec();
+    for i in 0..LINES
+        {
+            result.extend(format!("line {} of large text part\r\n", i).as_bytes());
+        }
+    result.extend(b"\r\n--boundary--\r\n");
+    result
+}
+
+fn parse(body: &[u8]) -> usize
+{
+    let size = Rc::new(RefCell::new(0));
+    {
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Target { size: size.clone() });
+        multipart_parser.write_all(body).unwrap();
+    }
+    let result = *size.borrow();
+    result
+}
+
+
+#[bench]
+fn parse_large_part(b: &mut Bencher)
+{
+    let body = body();
+    let total = allocations(|| parse(&body));
+    assert!(total < LINES / 100, "{} allocations", total);
+    b.bytes = body.len() as u64;
+    b.iter(|| parse(&body));
+}
\ No newline at end of file
@@ -0,0 +1,101 @@
+//! Compare boundary matching of `MultipartParser` on content full of near-boundary sequences (every byte is matched
+//! against boundary, mismatch falls back by KMP failure table) with plain content (skipped to possible boundary start
+//! by `memchr`). Both benches write body by chunks of `CHUNK_SIZE` to `MultipartParser::write` (headers, processor
+//! calls etc included), so both paths are measured by the same entry point. Time of near-boundary content grows
+//! linearly with `CONTENT_SIZE`.
+//!
+//! ```text
+//! cargo bench --bench content_matching
+//! ```
+
+#![feature(test)]
+extern crate gnitive_multipart;
+extern crate test;
+
+use std::cell::{RefCell};
+use std::io::{Write};
+use std::rc::{Rc};
+use test::{Bencher};
+
+use gnitive_multipart::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, Headers};
+use gnitive_multipart::multipart_parser::{MultipartParser};
+use gnitive_multipart::process_content::{NullProcessor};
+
+
+const BOUNDARY: &str = "----------------------------735323031399963166993862150";
+
+/// Size of content of one part
+const CONTENT_SIZE: usize = 1024 * 1024;
+
+/// Size of `write` call
+const CHUNK_SIZE: usize = 8192;
+
+
+struct Target {}
+
+impl MultipartParserTarget for Target {}
+
+impl MultipartParserTargetGenerated for Target
+{
+    fn get_all_required(&self) -> Vec<String> { vec![] }
+
+    fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, _headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        Some(Rc::new(RefCell::new(NullProcessor::new())))
+    }
+}
+
+
+/// `\r\n--<boundary>` without last symbol, repeated - worst case for matching
+fn near_boundary_content() -> Vec<u8>
+{
+    let mut near_boundary = format!("\r\n--{}", BOUNDARY).into_bytes();
+    near_boundary.pop();
+
+    let mut result: Vec<u8> = vec![];
+    while result.len() < CONTENT_SIZE
+        {
+            result.extend(&near_boundary);
+        }
+    result
+}
+
+/// Content without `\r`
+fn plain_content() -> Vec<u8>
+{
+    (0..CONTENT_SIZE).map(|i| b'a' + (i % 26) as u8).collect()
+}
+
+fn body(content: &[u8]) -> Vec<u8>
+{
+    let mut result = format!("--{}\r\nContent-Disposition: form-data; name=\"file1\"\r\n\r\n", BOUNDARY).into_bytes();
+    result.extend(content);
+    result.extend(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+    result
+}
+
+fn parse(body: &[u8])
+{
+    let mut multipart_parser = MultipartParser::new_owned(BOUNDARY, Target {});
+    for data in body.chunks(CHUNK_SIZE)
+        {
+            multipart_parser.write_all(data).unwrap();
+        }
+}
+
+
+#[bench]
+fn parser_near_boundary(b: &mut Bencher)
+{
+    let body = body(&near_boundary_content());
+    b.bytes = body.len() as u64;
+    b.iter(|| parse(&body));
+}
+
+#[bench]
+fn parser_plain(b: &mut Bencher)
+{
+    let body = body(&plain_content());
+    b.bytes = body.len() as u64;
+    b.iter(|| parse(&body));
+}
\ No newline at end of file
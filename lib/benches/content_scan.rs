@@ -0,0 +1,109 @@
+//! Compare scan of content for possible start of boundary (`\r`) byte by byte (`Iterator::position`)
+//! with `memchr`, used by `MultipartParser`, on binary content with rare `\r` (ex.: compressed file).
+//!
+//! `scan_*` benches measure bare scan loop, `parser_binary` - whole parsing (headers, processor calls etc).
+//!
+//! ```text
+//! cargo bench --bench content_scan
+//! ```
+
+#![feature(test)]
+extern crate gnitive_multipart;
+extern crate memchr;
+extern crate test;
+
+use std::cell::{RefCell};
+use std::io::{Write};
+use std::rc::{Rc};
+use memchr::{memchr};
+use test::{Bencher, black_box};
+
+use gnitive_multipart::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, Headers};
+use gnitive_multipart::multipart_parser::{MultipartParser};
+use gnitive_multipart::process_content::{NullProcessor};
+
+
+const BOUNDARY: &str = "----------------------------735323031399963166993862150";
+
+/// Size of content of one part
+const CONTENT_SIZE: usize = 4 * 1024 * 1024;
+
+
+struct Target {}
+
+impl MultipartParserTarget for Target {}
+
+impl MultipartParserTargetGenerated for Target
+{
+    fn get_all_required(&self) -> Vec<String> { vec![] }
+
+    fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, _headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        Some(Rc::new(RefCell::new(NullProcessor::new())))
+    }
+}
+
+
+/// Pseudo-random bytes (xorshift), `\r` about once per 256 bytes
+fn binary_content() -> Vec<u8>
+{
+    let mut state: u32 = 2463534242;
+    (0..CONTENT_SIZE).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as u8
+    }).collect()
+}
+
+fn body(content: &[u8]) -> Vec<u8>
+{
+    let mut result = format!("--{}\r\nContent-Disposition: form-data; name=\"file1\"\r\n\r\n", BOUNDARY).into_bytes();
+    result.extend(content);
+    result.extend(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+    result
+}
+
+/// Count positions of `\r` by `scan`, as parser skips runs between them
+fn count_candidates<F: Fn(&[u8]) -> Option<usize>>(content: &[u8], scan: F) -> usize
+{
+    let mut pos: usize = 0;
+    let mut count: usize = 0;
+    while let Some(offset) = scan(&content[pos..])
+        {
+            count += 1;
+            pos += offset + 1;
+        }
+    count
+}
+
+
+#[bench]
+fn scan_position(b: &mut Bencher)
+{
+    let content = binary_content();
+    b.bytes = content.len() as u64;
+    b.iter(|| black_box(count_candidates(&content, |data| data.iter().position(|&c| c == b'\r'))));
+}
+
+#[bench]
+fn scan_memchr(b: &mut Bencher)
+{
+    let content = binary_content();
+    b.bytes = content.len() as u64;
+    b.iter(|| black_box(count_candidates(&content, |data| memchr(b'\r', data))));
+}
+
+#[bench]
+fn parser_binary(b: &mut Bencher)
+{
+    let body = body(&binary_content());
+    b.bytes = body.len() as u64;
+    b.iter(|| {
+        let mut multipart_parser = MultipartParser::new_owned(BOUNDARY, Target {});
+        for data in body.chunks(65536)
+            {
+                multipart_parser.write_all(data).unwrap();
+            }
+    });
+}
\ No newline at end of file
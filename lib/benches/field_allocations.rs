@@ -0,0 +1,125 @@
+//! Count heap allocations of processors for form with 50 small fields: `Rc<RefCell<ProcessContent>>`
+//! (returned by generated `content_parser_generated`) against `Box<ProcessContent>`.
+//!
+//! Both wrappers allocate once per field (`Rc` stores counters and `RefCell` flag in the same allocation),
+//! so owning processors by `Box` in `MultipartParser` saves only counter updates, not allocations.
+//! Parsing of whole form takes about 20 allocations per field (headers, name, data buffers), wrapper is one of them.
+//!
+//! ```text
+//! cargo bench --bench field_allocations
+//! ```
+
+#![feature(test)]
+extern crate gnitive_multipart;
+extern crate test;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{RefCell};
+use std::io::{Write};
+use std::rc::{Rc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use test::{Bencher, black_box};
+
+use gnitive_multipart::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessParams, Headers};
+use gnitive_multipart::multipart_parser::{MultipartParser};
+use gnitive_multipart::process_content::{DefaultProcessor};
+
+
+/// Count of form fields
+const FIELDS: usize = 50;
+
+
+/// System allocator, which count allocations
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8
+    {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) -> ()
+    {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Count of allocations in `f`
+fn allocations<F: FnOnce() -> R, R>(f: F) -> usize
+{
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+
+/// Select `DefaultProcessor` for every field, like generated code
+struct Target {}
+
+impl MultipartParserTarget for Target {}
+
+impl MultipartParserTargetGenerated for Target
+{
+    fn get_all_required(&self) -> Vec<String> { vec![] }
+
+    fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        Some(Rc::new(RefCell::new(processor(headers.get_name()?))))
+    }
+}
+
+fn processor(name: &str) -> DefaultProcessor
+{
+    DefaultProcessor::new(ProcessParams::new(name, None))
+}
+
+/// Form with fields `f0`...`f49`, value of each field is its index
+fn body() -> Vec<u8>
+{
+    let mut result: Vec<u8> = vec![];
+    for i in 0..FIELDS
+        {
+            result.extend(format!("--boundary\r\nContent-Disposition: form-data; name=\"f{}\"\r\n\r\n{}\r\n", i, i).as_bytes());
+        }
+    result.extend(b"--boundary--\r\n");
+    result
+}
+
+fn parse(body: &[u8]) -> ()
+{
+    let mut multipart_parser = MultipartParser::new_owned("boundary", Target {});
+    multipart_parser.write_all(body).unwrap();
+}
+
+
+#[bench]
+fn wrapper_rc_refcell(b: &mut Bencher)
+{
+    assert_eq!(1, allocations(|| Rc::new(RefCell::new(processor(""))) as Rc<RefCell<ProcessContent>>));
+    b.iter(|| black_box(Rc::new(RefCell::new(processor(""))) as Rc<RefCell<ProcessContent>>));
+}
+
+#[bench]
+fn wrapper_box(b: &mut Bencher)
+{
+    assert_eq!(1, allocations(|| Box::new(processor("")) as Box<ProcessContent>));
+    b.iter(|| black_box(Box::new(processor("")) as Box<ProcessContent>));
+}
+
+#[bench]
+fn parse_50_fields(b: &mut Bencher)
+{
+    let body = body();
+    let total = allocations(|| parse(&body));
+    // one wrapper allocation per field, the same count for `Box`
+    assert!(total > FIELDS);
+    b.bytes = body.len() as u64;
+    b.iter(|| parse(&body));
+}
\ No newline at end of file
@@ -0,0 +1,105 @@
+//! Compare dispatch of part name to field by `match` (generated by derive in `content_parser_generated`)
+//! with name to field index table (`HashMap`), built once per thread, on form with 100 fields.
+//!
+//! `Headers::get_name` returns reference, so dispatch itself does not allocate, only comparisons are measured.
+//! `match` on `&str` compares length first and bytes only for names with the same length, so even for 100 names
+//! (90 of equal length) it is not slower than hashing of name: about 24 ns per name for `match` against 33 ns for table.
+//! Both are negligible against parsing of part (about 20 allocations per field, see `field_allocations`),
+//! so derive keeps `match` and no table is generated.
+//!
+//! ```text
+//! cargo bench --bench field_dispatch
+//! ```
+
+#![feature(test)]
+extern crate test;
+
+use std::collections::{HashMap};
+use test::{Bencher, black_box};
+
+
+/// Count of form fields
+const FIELDS: usize = 100;
+
+
+/// Field index by `match`, like generated `content_parser_generated`
+fn dispatch_match(name: &str) -> Option<usize>
+{
+    match name
+        {
+            "field_0" => Some(0), "field_1" => Some(1), "field_2" => Some(2), "field_3" => Some(3), "field_4" => Some(4),
+            "field_5" => Some(5), "field_6" => Some(6), "field_7" => Some(7), "field_8" => Some(8), "field_9" => Some(9),
+            "field_10" => Some(10), "field_11" => Some(11), "field_12" => Some(12), "field_13" => Some(13), "field_14" => Some(14),
+            "field_15" => Some(15), "field_16" => Some(16), "field_17" => Some(17), "field_18" => Some(18), "field_19" => Some(19),
+            "field_20" => Some(20), "field_21" => Some(21), "field_22" => Some(22), "field_23" => Some(23), "field_24" => Some(24),
+            "field_25" => Some(25), "field_26" => Some(26), "field_27" => Some(27), "field_28" => Some(28), "field_29" => Some(29),
+            "field_30" => Some(30), "field_31" => Some(31), "field_32" => Some(32), "field_33" => Some(33), "field_34" => Some(34),
+            "field_35" => Some(35), "field_36" => Some(36), "field_37" => Some(37), "field_38" => Some(38), "field_39" => Some(39),
+            "field_40" => Some(40), "field_41" => Some(41), "field_42" => Some(42), "field_43" => Some(43), "field_44" => Some(44),
+            "field_45" => Some(45), "field_46" => Some(46), "field_47" => Some(47), "field_48" => Some(48), "field_49" => Some(49),
+            "field_50" => Some(50), "field_51" => Some(51), "field_52" => Some(52), "field_53" => Some(53), "field_54" => Some(54),
+            "field_55" => Some(55), "field_56" => Some(56), "field_57" => Some(57), "field_58" => Some(58), "field_59" => Some(59),
+            "field_60" => Some(60), "field_61" => Some(61), "field_62" => Some(62), "field_63" => Some(63), "field_64" => Some(64),
+            "field_65" => Some(65), "field_66" => Some(66), "field_67" => Some(67), "field_68" => Some(68), "field_69" => Some(69),
+            "field_70" => Some(70), "field_71" => Some(71), "field_72" => Some(72), "field_73" => Some(73), "field_74" => Some(74),
+            "field_75" => Some(75), "field_76" => Some(76), "field_77" => Some(77), "field_78" => Some(78), "field_79" => Some(79),
+            "field_80" => Some(80), "field_81" => Some(81), "field_82" => Some(82), "field_83" => Some(83), "field_84" => Some(84),
+            "field_85" => Some(85), "field_86" => Some(86), "field_87" => Some(87), "field_88" => Some(88), "field_89" => Some(89),
+            "field_90" => Some(90), "field_91" => Some(91), "field_92" => Some(92), "field_93" => Some(93), "field_94" => Some(94),
+            "field_95" => Some(95), "field_96" => Some(96), "field_97" => Some(97), "field_98" => Some(98), "field_99" => Some(99),
+            _ => None
+        }
+}
+
+thread_local!
+{
+    /// Field index by name, built once per thread
+    static TABLE: HashMap<&'static str, usize> = table();
+}
+
+fn table() -> HashMap<&'static str, usize>
+{
+    let mut result: HashMap<&'static str, usize> = HashMap::new();
+    for (i, name) in names().into_iter().enumerate()
+        {
+            result.insert(Box::leak(name.into_boxed_str()), i);
+        }
+    result
+}
+
+/// Field index by table
+fn dispatch_table(name: &str) -> Option<usize>
+{
+    TABLE.with(|table| table.get(name).cloned())
+}
+
+/// Names of all fields, `field_0`...`field_99`
+fn names() -> Vec<String>
+{
+    (0..FIELDS).map(|i| format!("field_{}", i)).collect()
+}
+
+
+#[bench]
+fn dispatch_100_fields_match(b: &mut Bencher)
+{
+    let names = names();
+    for (i, name) in names.iter().enumerate()
+        {
+            assert_eq!(Some(i), dispatch_match(name));
+        }
+    assert_eq!(None, dispatch_match("unknown"));
+    b.iter(|| for name in &names { black_box(dispatch_match(black_box(name))); });
+}
+
+#[bench]
+fn dispatch_100_fields_table(b: &mut Bencher)
+{
+    let names = names();
+    for (i, name) in names.iter().enumerate()
+        {
+            assert_eq!(Some(i), dispatch_table(name));
+        }
+    assert_eq!(None, dispatch_table("unknown"));
+    b.iter(|| for name in &names { black_box(dispatch_table(black_box(name))); });
+}
\ No newline at end of file
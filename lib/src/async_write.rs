@@ -0,0 +1,118 @@
+//! `tokio::io::AsyncWrite` for `MultipartParser` (feature `async`), so data can be written without blocking executor.
+//!
+//! ```rust,ignore
+//! let mut multipart_parser = MultipartParser::new_owned("boundary", Form::default());
+//! tokio::io::copy(&mut body, &mut multipart_parser).await?;
+//! multipart_parser.shutdown().await?;
+//! let form = multipart_parser.into_target();
+//! ```
+//!
+//! Data is parsed synchronously inside `poll_write` (as by `std::io::Write::write`), so methods of target and processors
+//! must not block. `MultipartParser` is not `Send` (target is shared by `Rc`), use it in current thread (ex.: `tokio::task::LocalSet`).
+
+use std::io::{Write, Error as IOError};
+use std::pin::{Pin};
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncWrite};
+
+use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated};
+use multipart_parser::{MultipartParser};
+
+
+impl <T>AsyncWrite for MultipartParser<T>
+    where T: MultipartParserTarget + MultipartParserTargetGenerated
+{
+    /// All data is parsed at once, never pending
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, IOError>>
+    {
+        Poll::Ready(Write::write(self.get_mut(), buf))
+    }
+
+    /// Nothing is buffered - no-op. Called in middle of data (ex.: by `tokio::io::copy`, when reader is pending),
+    /// so data is not finished here, see `poll_shutdown`
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), IOError>>
+    {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Same as `std::io::Write::flush` (incomplete last part, see `flush_incomplete_final_part`), call it after all data written
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), IOError>>
+    {
+        Poll::Ready(Write::flush(self.get_mut()))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::io::{Error as IOError};
+    use std::pin::{Pin};
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
+    use tokio::runtime::{Builder};
+    use ::capturing_target::{CapturingTarget};
+    use ::multipart_parser::{MultipartParser};
+
+    #[test]
+    fn async_copy() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"count\"\r\n\r\n42\r\n--boundary\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nReport\r\n--boundary--\r\n";
+        let runtime = Builder::new_current_thread().build().unwrap();
+        let mut multipart_parser = MultipartParser::new_owned("boundary", CapturingTarget::new());
+        let mut reader = body;
+        assert_eq!(body.len() as u64, runtime.block_on(::tokio::io::copy(&mut reader, &mut multipart_parser)).unwrap());
+        runtime.block_on(multipart_parser.shutdown()).unwrap();
+
+        let fields = multipart_parser.into_target().into_fields();
+        let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(vec!["count", "title"], names);
+        assert_eq!(b"Report".to_vec(), fields[1].raw);
+    }
+
+    /// Return `chunks` one by one, pending before each of them
+    struct PendingReader
+    {
+        chunks: Vec<&'static [u8]>,
+        pending: bool
+    }
+
+    impl AsyncRead for PendingReader
+    {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<(), IOError>>
+        {
+            if self.pending
+                {
+                    self.pending = false;
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+            self.pending = true;
+            if !self.chunks.is_empty()
+                {
+                    let chunk = self.chunks.remove(0);
+                    buf.put_slice(chunk);
+                }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn async_copy_pending_reader() -> ()
+    {
+        // `tokio::io::copy` flushes writer, when reader is pending - part must not be finished
+        let chunks: Vec<&'static [u8]> = vec![b"--boundary\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nRep", b"ort\r\n--boun", b"dary\r\nContent-Disposition: form-data; name=\"count\"\r\n\r\n42\r\n--boundary--\r\n"];
+        let runtime = Builder::new_current_thread().build().unwrap();
+        let mut multipart_parser = MultipartParser::new_owned("boundary", CapturingTarget::new());
+        multipart_parser.flush_incomplete_final_part(true).set_min_parts(2);
+        let mut reader = PendingReader { chunks, pending: true };
+        runtime.block_on(::tokio::io::copy(&mut reader, &mut multipart_parser)).unwrap();
+        runtime.block_on(multipart_parser.shutdown()).unwrap();
+
+        let fields = multipart_parser.into_target().into_fields();
+        let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(vec!["title", "count"], names);
+        assert_eq!(b"Report".to_vec(), fields[0].raw);
+        assert_eq!(b"42".to_vec(), fields[1].raw);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,173 @@
+//! Collect all parts as raw `ParsedField`, typed conversion is deferred until `ParsedField::convert`.
+//!
+//! All data of all parts is held in memory without limit: for untrusted input limit it by
+//! `MultipartParser::set_max_in_flight_memory` (captured parts are counted up to end of data).
+//!
+//! ```rust,ignore
+//! let mut multipart_parser = MultipartParser::new_owned("boundary", CapturingTarget::new());
+//! multipart_parser.set_max_in_flight_memory(Some(10 * 1024 * 1024));
+//! multipart_parser.write_all(body)?;
+//! multipart_parser.flush()?;
+//! for field in multipart_parser.into_target().iter()
+//!     {
+//!         let count: i32 = field.convert()?;
+//!     }
+//! ```
+
+use std::cell::{RefCell};
+use std::convert::{TryFrom};
+use std::io::{Error as IOError};
+use std::rc::{Rc};
+use std::slice;
+use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessParams, Headers};
+use ::process_content::{DefaultProcessor};
+
+
+/// Completed part: name, headers and raw (not converted) data
+#[derive(Clone)]
+pub struct ParsedField
+{
+    /// Value of `name` from `Content-Disposition`, empty for part without name
+    pub name: String,
+
+    /// All data of part
+    pub raw: Vec<u8>,
+
+    /// Headers of part
+    pub headers: Headers
+}
+
+impl ParsedField
+{
+    /// Convert raw data to `T` by `TryFrom<DefaultProcessor>` (same conversion as for fields of `gnitive-multipart-derive`)
+    pub fn convert<T>(&self) -> Result<T, <T as TryFrom<DefaultProcessor>>::Error>
+        where T: TryFrom<DefaultProcessor>
+    {
+        T::try_from(DefaultProcessor::with_raw_data(ProcessParams::new(self.name.as_str(), None), self.raw.clone()))
+    }
+}
+
+
+/// `MultipartParserTarget`, which store every part as `ParsedField` in memory (size is not limited by target itself)
+pub struct CapturingTarget
+{
+    fields: Vec<ParsedField>
+}
+
+impl CapturingTarget
+{
+    pub fn new() -> CapturingTarget
+    {
+        CapturingTarget
+            {
+                fields: vec![]
+            }
+    }
+
+    /// Iterate over completed parts in order of receiving
+    pub fn iter(&self) -> slice::Iter<ParsedField>
+    {
+        self.fields.iter()
+    }
+
+    /// Get completed parts
+    pub fn into_fields(self) -> Vec<ParsedField>
+    {
+        self.fields
+    }
+}
+
+impl MultipartParserTarget for CapturingTarget {}
+
+impl MultipartParserTargetGenerated for CapturingTarget
+{
+    fn get_all_required(&self) -> Vec<String> { vec![] }
+
+    fn content_parser_generated(&mut self, self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        let name = headers.get_name().cloned().unwrap_or_default();
+        Some(Rc::new(RefCell::new(CapturingProcessor
+            {
+                params: ProcessParams::new(name, None),
+                raw: vec![],
+                target: self_.clone()
+            })))
+    }
+}
+
+
+/// Collect data of one part, add `ParsedField` to `CapturingTarget` in `flush`
+struct CapturingProcessor
+{
+    params: ProcessParams,
+    raw: Vec<u8>,
+    target: Rc<RefCell<CapturingTarget>>
+}
+
+impl ProcessContent for CapturingProcessor
+{
+    fn open(&mut self, _headers: &Headers) -> () {}
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        self.raw.extend(data);
+        Ok(())
+    }
+
+    fn flush(&mut self, headers: &Headers) -> Result<(), IOError>
+    {
+        let field = ParsedField
+            {
+                name: self.params.name.clone(),
+                raw: self.raw.split_off(0),
+                headers: headers.clone()
+            };
+        self.target.borrow_mut().fields.push(field);
+        Ok(())
+    }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+
+    fn buffered_size(&self) -> usize
+    {
+        self.raw.len()
+    }
+
+    /// Skipped part is not captured
+    fn abort(&mut self, _headers: &Headers) -> Result<(), IOError>
+    {
+        self.raw.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{CapturingTarget};
+    use std::io::{Write};
+    use ::multipart_parser::{MultipartParser};
+
+    #[test]
+    fn convert_after_parse() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"count\"\r\n\r\n42\r\n--boundary\r\nContent-Disposition: form-data; name=\"title\"\r\nContent-Type: text/plain\r\n\r\nReport\r\n--boundary--\r\n";
+        let mut multipart_parser = MultipartParser::new_owned("boundary", CapturingTarget::new());
+        multipart_parser.write_all(body).unwrap();
+        multipart_parser.flush().unwrap();
+
+        let fields = multipart_parser.into_target().into_fields();
+        let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(vec!["count", "title"], names);
+        assert_eq!(b"42".to_vec(), fields[0].raw);
+        assert_eq!(Some("text/plain"), fields[1].headers.get_mime_type());
+
+        assert_eq!(42, fields[0].convert::<i32>().unwrap());
+        assert_eq!("Report", fields[1].convert::<String>().unwrap());
+        assert!(fields[1].convert::<i32>().is_err());
+        assert_eq!(Some(42), fields[0].convert::<Option<i32>>().unwrap());
+    }
+}
\ No newline at end of file
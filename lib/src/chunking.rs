@@ -0,0 +1,220 @@
+//! Record and replay chunking of data (sizes of `write` calls), to reproduce chunking-dependent behavior deterministically.
+//!
+//! ```rust,ignore
+//! // record chunking of network data
+//! let mut chunk_recorder = ChunkRecorder::new(multipart_parser);
+//! io::copy(&mut socket, &mut chunk_recorder)?;
+//! let chunks = chunk_recorder.chunks().to_vec();
+//!
+//! // replay the same chunking in test
+//! ChunkReplayer::new(chunks).replay(&body, &mut other_multipart_parser)?;
+//! ```
+
+use std::cmp::{min};
+use std::io::{Write, Error as IOError};
+
+
+/// Forward all data to inner `std::io::Write` (ex.: `MultipartParser`) and record size of each `write` call
+pub struct ChunkRecorder<W: Write>
+{
+    inner: W,
+    chunks: Vec<usize>
+}
+
+impl <W: Write>ChunkRecorder<W>
+{
+    pub fn new(inner: W) -> ChunkRecorder<W>
+    {
+        ChunkRecorder
+            {
+                inner,
+                chunks: vec![]
+            }
+    }
+
+    /// Sizes of written chunks, in order of `write` calls
+    pub fn chunks(&self) -> &[usize]
+    {
+        &self.chunks
+    }
+
+    /// Get access to inner writer
+    pub fn get_mut(&mut self) -> &mut W
+    {
+        &mut self.inner
+    }
+
+    /// Return inner writer and sizes of written chunks
+    pub fn into_inner(self) -> (W, Vec<usize>)
+    {
+        (self.inner, self.chunks)
+    }
+}
+
+impl <W: Write>Write for ChunkRecorder<W>
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IOError>
+    {
+        let size = self.inner.write(buf)?;
+        if size > 0
+            {
+                self.chunks.push(size);
+            }
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> Result<(), IOError>
+    {
+        self.inner.flush()
+    }
+}
+
+
+/// Write data by chunks of recorded (see `ChunkRecorder`) or explicit sizes
+pub struct ChunkReplayer
+{
+    chunks: Vec<usize>
+}
+
+impl ChunkReplayer
+{
+    pub fn new(chunks: Vec<usize>) -> ChunkReplayer
+    {
+        ChunkReplayer
+            {
+                chunks
+            }
+    }
+
+    /// Write `data` to `writer` by chunks, one `write_all` per chunk.
+    /// Data after all chunks is written by one chunk, chunks after end of data are ignored.
+    pub fn replay<W: Write>(&self, data: &[u8], writer: &mut W) -> Result<(), IOError>
+    {
+        let mut pos: usize = 0;
+        for &chunk in &self.chunks
+            {
+                if pos == data.len()
+                    {
+                        break;
+                    }
+                let end = min(pos + chunk, data.len());
+                writer.write_all(&data[pos..end])?;
+                pos = end;
+            }
+        if pos < data.len()
+            {
+                writer.write_all(&data[pos..])?;
+            }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{ChunkRecorder, ChunkReplayer};
+    use std::cell::{RefCell};
+    use std::io::{self, Read, Error as IOError};
+    use std::rc::{Rc};
+    use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessParams, Headers};
+    use ::multipart_parser::{MultipartParser};
+
+    /// Return data from `data` by chunks of sizes from `chunks` (repeated)
+    struct Uneven<'a>
+    {
+        data: &'a [u8],
+        chunks: Vec<usize>,
+        pos: usize
+    }
+
+    impl <'a>Read for Uneven<'a>
+    {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError>
+        {
+            let chunk = self.chunks[self.pos % self.chunks.len()];
+            self.pos += 1;
+            let size = [chunk, buf.len(), self.data.len()].iter().cloned().min().unwrap();
+            buf[..size].copy_from_slice(&self.data[..size]);
+            self.data = &self.data[size..];
+            Ok(size)
+        }
+    }
+
+    /// Store every processor callback as string
+    struct Callbacks
+    {
+        params: ProcessParams,
+        callbacks: Rc<RefCell<Vec<String>>>
+    }
+
+    impl ProcessContent for Callbacks
+    {
+        fn open(&mut self, _headers: &Headers) -> ()
+        {
+            self.callbacks.borrow_mut().push(format!("open {}", self.params.name));
+        }
+
+        fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+        {
+            self.callbacks.borrow_mut().push(format!("write {:?}", String::from_utf8_lossy(data)));
+            Ok(())
+        }
+
+        fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
+        {
+            self.callbacks.borrow_mut().push(format!("flush {}", self.params.name));
+            Ok(())
+        }
+
+        fn get_process_params(&self) -> &ProcessParams
+        {
+            &self.params
+        }
+    }
+
+    struct Target
+    {
+        callbacks: Rc<RefCell<Vec<String>>>
+    }
+
+    impl MultipartParserTarget for Target {}
+
+    impl MultipartParserTargetGenerated for Target
+    {
+        fn get_all_required(&self) -> Vec<String> { vec![] }
+
+        fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+        {
+            Some(Rc::new(RefCell::new(Callbacks { params: ProcessParams::new(headers.get_name()?.as_str(), None), callbacks: self.callbacks.clone() })))
+        }
+    }
+
+    fn parser() -> MultipartParser<Target>
+    {
+        MultipartParser::new_owned("boundary", Target { callbacks: Rc::new(RefCell::new(vec![])) })
+    }
+
+    #[test]
+    fn record_replay() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nab\r\n--boundar\r\ncd\r\n--boundary\r\nContent-Disposition: form-data; name=\"text2\"\r\n\r\nef\r\n--boundary--\r\n";
+        let mut chunk_recorder = ChunkRecorder::new(parser());
+        io::copy(&mut Uneven { data: body, chunks: vec![7, 54, 3, 13], pos: 0 }, &mut chunk_recorder).unwrap();
+        let (multipart_parser, chunks) = chunk_recorder.into_inner();
+        assert_eq!(body.len(), chunks.iter().sum());
+        assert_eq!(vec![7, 54, 3, 13, 7], chunks[..5].to_vec());
+        let recorded = multipart_parser.into_target().callbacks.borrow().clone();
+
+        let mut multipart_parser = parser();
+        ChunkReplayer::new(chunks).replay(body, &mut multipart_parser).unwrap();
+        let replayed = multipart_parser.into_target().callbacks.borrow().clone();
+        assert_eq!(recorded, replayed);
+
+        // other chunking - other callbacks, same data
+        let mut multipart_parser = parser();
+        ChunkReplayer::new(vec![]).replay(body, &mut multipart_parser).unwrap();
+        let whole = multipart_parser.into_target().callbacks.borrow().clone();
+        assert!(recorded != whole);
+        assert_eq!(vec!["open text1", "write \"ab\"", "write \"\\r\\n--boundar\"", "write \"\\r\\n\"", "write \"cd\"", "flush text1", "open text2", "write \"ef\"", "flush text2"], whole);
+    }
+}
\ No newline at end of file
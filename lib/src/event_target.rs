@@ -0,0 +1,172 @@
+//! Event-based API: parts are delivered as `MultipartEvent`s, no own `MultipartParserTarget` or `ProcessContent` required.
+//!
+//! ```rust,ignore
+//! let mut multipart_parser = MultipartParser::new_events("boundary");
+//! for chunk in body.chunks(4096)
+//!     {
+//!         multipart_parser.write(chunk)?;
+//!         for event in multipart_parser.events()
+//!             {
+//!                 match event
+//!                     {
+//!                         MultipartEvent::Headers(headers) => println!("part {:?}", headers.get_name()),
+//!                         MultipartEvent::Data(data) => file.write_all(&data)?,
+//!                         MultipartEvent::PartEnd => (),
+//!                         MultipartEvent::Finished => break
+//!                     }
+//!             }
+//!     }
+//! ```
+
+use std::cell::{RefCell};
+use std::io::{Error as IOError};
+use std::rc::{Rc};
+use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessParams, Headers};
+
+
+/// Event of multipart data, in order of receiving
+#[derive(Clone)]
+pub enum MultipartEvent
+{
+    /// Headers of next part
+    Headers(Headers),
+
+    /// Data of current part, may be fragmented (ex.: by network packets)
+    Data(Vec<u8>),
+
+    /// All data of current part received. Not emitted for part, skipped on error (ex.: `MultipartParser::set_max_total_size`)
+    PartEnd,
+
+    /// Final boundary received, no events more
+    Finished
+}
+
+
+/// `MultipartParserTarget`, which buffer `MultipartEvent`s until they are taken
+pub struct EventTarget
+{
+    events: Vec<MultipartEvent>
+}
+
+impl EventTarget
+{
+    pub fn new() -> EventTarget
+    {
+        EventTarget
+            {
+                events: vec![]
+            }
+    }
+
+    /// Take buffered events, buffer is empty after call
+    pub fn take_events(&mut self) -> Vec<MultipartEvent>
+    {
+        self.events.split_off(0)
+    }
+}
+
+impl MultipartParserTarget for EventTarget
+{
+    fn finish(&mut self) -> ()
+    {
+        self.events.push(MultipartEvent::Finished);
+    }
+}
+
+impl MultipartParserTargetGenerated for EventTarget
+{
+    fn get_all_required(&self) -> Vec<String> { vec![] }
+
+    fn content_parser_generated(&mut self, self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        self.events.push(MultipartEvent::Headers(headers.clone()));
+        let name = headers.get_name().cloned().unwrap_or_default();
+        Some(Rc::new(RefCell::new(EventProcessor
+            {
+                params: ProcessParams::new(name, None),
+                target: self_.clone()
+            })))
+    }
+}
+
+
+/// Add `Data` and `PartEnd` events of one part to `EventTarget`
+struct EventProcessor
+{
+    params: ProcessParams,
+    target: Rc<RefCell<EventTarget>>
+}
+
+impl ProcessContent for EventProcessor
+{
+    fn open(&mut self, _headers: &Headers) -> () {}
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        if !data.is_empty()
+            {
+                self.target.borrow_mut().events.push(MultipartEvent::Data(data.to_vec()));
+            }
+        Ok(())
+    }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
+    {
+        self.target.borrow_mut().events.push(MultipartEvent::PartEnd);
+        Ok(())
+    }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{MultipartEvent};
+    use std::io::{Write};
+    use ::multipart_parser::{MultipartParser};
+
+    /// Short description of event for comparing
+    fn describe(event: &MultipartEvent) -> String
+    {
+        match event
+            {
+                &MultipartEvent::Headers(ref headers) => format!("headers {}", headers.get_name().cloned().unwrap_or_default()),
+                &MultipartEvent::Data(ref data) => format!("data {}", String::from_utf8_lossy(data)),
+                &MultipartEvent::PartEnd => "end".to_string(),
+                &MultipartEvent::Finished => "finished".to_string()
+            }
+    }
+
+    #[test]
+    fn events() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nReport\r\n--boundary\r\nContent-Disposition: form-data; name=\"empty\"\r\n\r\n\r\n--boundary--\r\n";
+        for &chunk in &[1, 5, body.len()]
+            {
+                let mut multipart_parser = MultipartParser::new_events("boundary");
+                let mut events: Vec<String> = vec![];
+                for data in body.chunks(chunk)
+                    {
+                        multipart_parser.write_all(data).unwrap();
+                        events.extend(multipart_parser.events().map(|event| describe(&event)));
+                    }
+                assert!(multipart_parser.events().next().is_none());
+
+                // data may be fragmented - join it
+                let mut joined: Vec<String> = vec![];
+                for event in events
+                    {
+                        match (joined.last_mut(), event.starts_with("data "))
+                            {
+                                (Some(ref mut last), true) if last.starts_with("data ") => last.push_str(&event[5..]),
+                                _ => joined.push(event)
+                            }
+                    }
+                assert_eq!(vec!["headers title", "data Report", "end", "headers empty", "end", "finished"], joined, "chunk {}", chunk);
+            }
+    }
+}
\ No newline at end of file
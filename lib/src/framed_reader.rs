@@ -0,0 +1,165 @@
+//! Read multipart data wrapped in length-prefixed frames (feature `framed`)
+//!
+//! ```text
+//!  length (4 bytes, big-endian)     payload (length bytes)
+//!          /                              /
+//! [00 00 00 2A] [--boundary\r\nContent-Disposition: ...] [00 00 01 00] [...]
+//! ```
+//!
+//! `FramedReader` implements `std::io::Read` and returns payloads of all frames one by one,
+//! so data can be redirected to `MultipartParser` by `std::io::copy`.
+
+use std::io::{Read, Error as IOError, ErrorKind};
+
+
+/// Unwrap length-prefixed frames from inner `std::io::Read`
+pub struct FramedReader<R: Read>
+{
+    /// Inner reader
+    reader: R,
+
+    /// Unread bytes of current frame
+    remaining: usize
+}
+
+impl <R: Read>FramedReader<R>
+{
+    pub fn new(reader: R) -> FramedReader<R>
+    {
+        FramedReader
+            {
+                reader,
+                remaining: 0
+            }
+    }
+
+    /// Return inner reader
+    pub fn into_inner(self) -> R
+    {
+        self.reader
+    }
+
+    /// Read 4-byte big-endian length of next frame.
+    /// Return `Ok(None)` if inner reader finished exactly at frame bound.
+    fn read_length(&mut self) -> Result<Option<usize>, IOError>
+    {
+        let mut length = [0u8; 4];
+        let mut pos: usize = 0;
+        while pos < length.len()
+            {
+                match self.reader.read(&mut length[pos..])
+                    {
+                        Ok(0) if pos == 0 => return Ok(None),
+                        Ok(0) => return Err(IOError::new(ErrorKind::UnexpectedEof, "Frame length truncated")),
+                        Ok(size) => pos += size,
+                        Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                        Err(e) => return Err(e)
+                    }
+            }
+
+        let length = ((length[0] as usize) << 24)
+            | ((length[1] as usize) << 16)
+            | ((length[2] as usize) << 8)
+            | (length[3] as usize);
+        Ok(Some(length))
+    }
+}
+
+impl <R: Read>Read for FramedReader<R>
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError>
+    {
+        if buf.is_empty()
+            {
+                return Ok(0);
+            }
+
+        // skip empty frames
+        while self.remaining == 0
+            {
+                match self.read_length()?
+                    {
+                        Some(length) => self.remaining = length,
+                        None => return Ok(0)
+                    }
+            }
+
+        let to = if buf.len() < self.remaining { buf.len() } else { self.remaining };
+        let size = self.reader.read(&mut buf[..to])?;
+        if size == 0
+            {
+                return Err(IOError::new(ErrorKind::UnexpectedEof, "Frame payload truncated"));
+            }
+        self.remaining -= size;
+        Ok(size)
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::{FramedReader};
+    use std::cell::{RefCell};
+    use std::convert::{TryFrom};
+    use std::io::{self, Read};
+    use std::rc::{Rc};
+    use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessParams, Headers};
+    use ::multipart_parser::{MultipartParser};
+    use ::process_content::{DefaultProcessor};
+
+    struct Target
+    {
+        text1: Rc<RefCell<DefaultProcessor>>
+    }
+
+    impl MultipartParserTarget for Target {}
+
+    impl MultipartParserTargetGenerated for Target
+    {
+        fn get_all_required(&self) -> Vec<String> { vec![] }
+
+        fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, _headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+        {
+            Some(self.text1.clone())
+        }
+    }
+
+    fn frame(payload: &[u8]) -> Vec<u8>
+    {
+        let length = payload.len();
+        let mut result = vec![(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8];
+        result.extend(payload);
+        result
+    }
+
+    #[test]
+    fn framed_reader() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nHello, world\r\n--boundary--\r\n";
+        let mut framed: Vec<u8> = vec![];
+        framed.extend(frame(&body[..20]));
+        framed.extend(frame(&[]));
+        framed.extend(frame(&body[20..]));
+
+        let target = Rc::new(RefCell::new(Target { text1: Rc::new(RefCell::new(DefaultProcessor::new(ProcessParams::new("text1", None)))) }));
+        let mut multipart_parser: MultipartParser<Target> = MultipartParser::new_from_str("boundary", &target);
+
+        let mut framed_reader = FramedReader::new(framed.as_slice());
+        io::copy(&mut framed_reader, &mut multipart_parser).unwrap();
+
+        let text1 = target.borrow().text1.clone();
+        assert_eq!("Hello, world", String::try_from(&*text1.borrow()).unwrap());
+    }
+
+    #[test]
+    fn framed_reader_truncated() -> ()
+    {
+        let mut framed = frame(b"0123456789");
+        framed.pop();
+
+        let mut buf: Vec<u8> = vec![];
+        let result = FramedReader::new(framed.as_slice()).read_to_end(&mut buf);
+        assert!(result.is_err());
+    }
+}
\ No newline at end of file
@@ -1,58 +1,148 @@
 //! Parse and store multipart field header
 
-use std::collections::{HashMap};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use ::gnitive_multipart::{Header, Headers};
+use url_decode::{percent_decode, ext_value_decode};
+use multipart_parser::{MAX_HEADER_PARAMS};
 
 
 impl Header
 {
-    fn new(s: &str) -> Header
+    /// Parse header line, return `None` if line has no `:`.
+    /// Parameters without `=` (ex.: `form-data; flag`) are ignored, parameters after first `max_params` are ignored.
+    /// Decoded value of extended parameter (RFC 5987, ex.: `filename*=UTF-8''%e2%82%ac.txt`) replaces value of plain
+    /// parameter (`filename`), raw value is kept by `filename*`.
+    fn new(s: &str, max_params: usize) -> Option<Header>
     {
-        let mut strings: Vec<&str>  = s.split(';').collect();
+        let mut strings: Vec<&str>  = Header::split_params(s, max_params + 1);
 
         let first = strings.remove(0);
-        let (name, value) =  Header::to_key_value(first.as_ref(), ':');
+        let (name, value) =  Header::to_key_value(first.as_ref(), ':')?;
 
         let mut fields: HashMap<String,String> = HashMap::new();
+        let mut field_order: Vec<String> = vec![];
         for string in strings
             {
-                let (key, mut value) = Header::to_key_value(string, '=');
-                let value = value.trim_matches('"').to_string();
+                if let Some((key, value)) = Header::to_key_value(string, '=')
+                    {
+                        let value = value.trim_matches('"').to_string();
+                        if !fields.contains_key(&key)
+                            {
+                                field_order.push(key.clone());
+                            }
+                        fields.insert(key, value);
+                    }
+            }
+
+        let extended: Vec<(String, String)> = fields
+            .iter()
+            .filter(|&(key, _)| key.len() > 1 && key.ends_with('*'))
+            .filter_map(|(key, value)| ext_value_decode(value).map(|decoded| (key[..key.len() - 1].to_string(), decoded)))
+            .collect();
+        for (key, value) in extended
+            {
+                // plain parameter, absent in line, is placed before extended one
+                if !fields.contains_key(&key)
+                    {
+                        let pos = field_order.iter().position(|field| *field == format!("{}*", key)).unwrap_or(field_order.len());
+                        field_order.insert(pos, key.clone());
+                    }
                 fields.insert(key, value);
             }
 
-        Header
+        Some(Header
             {
                 name,
                 value,
-                fields
+                fields,
+                field_order
+            })
+    }
+
+    /// Escape parameter value for quoted string: `"`, `\r`, `\n` are percent-encoded (as browsers do)
+    pub fn quote(s: &str) -> String
+    {
+        s.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A")
+    }
+
+    /// Names of `fields` in order of header line (names, added to `fields` directly, are not included)
+    pub fn field_order(&self) -> &Vec<String>
+    {
+        &self.field_order
+    }
+
+    /// Names of `fields` in order of header line, then names, added to `fields` directly (sorted)
+    fn ordered_fields(&self) -> Vec<&String>
+    {
+        let mut rest: Vec<&String> = self.fields.keys().filter(|key| !self.field_order.contains(key)).collect();
+        rest.sort();
+        self.field_order.iter().filter(|key| self.fields.contains_key(*key)).chain(rest).collect()
+    }
+
+
+    /// Split header line by `;`, except `;` inside quoted values (ex.: `filename="a;b.txt"`).
+    /// Empty parameters (ex.: `form-data; ; name="x";`) are skipped.
+    /// Return at most `limit` parts, rest of line is dropped.
+    fn split_params(s: &str, limit: usize) -> Vec<&str>
+    {
+        let mut result: Vec<&str> = vec![];
+        let mut quoted = false;
+        let mut start: usize = 0;
+        for (pos, c) in s.char_indices()
+            {
+                if result.len() == limit
+                    {
+                        return result;
+                    }
+                match c
+                    {
+                        '"' => quoted = !quoted,
+                        ';' if !quoted =>
+                            {
+                                Header::push_param(&mut result, &s[start..pos]);
+                                start = pos + 1;
+                            },
+                        _ => ()
+                    }
+            }
+        if result.len() < limit
+            {
+                Header::push_param(&mut result, &s[start..]);
             }
+        result
     }
 
+    /// Push part of header line, empty parameter (not first part - header name and value) is skipped
+    fn push_param<'a>(result: &mut Vec<&'a str>, s: &'a str) -> ()
+    {
+        if result.is_empty() || !s.trim().is_empty()
+            {
+                result.push(s);
+            }
+    }
 
-    /// Split `s` by `separator` into 2 `String`
-    fn to_key_value(s: &str, separator: char) -> (String, String)
+    /// Split `s` by first `separator` into 2 `String`, whitespaces around `separator` ignored.
+    /// Return `None` if no `separator` in `s`.
+    fn to_key_value(s: &str, separator: char) -> Option<(String, String)>
     {
-        let strings: Vec<&str> = s.split(separator).collect();
+        let strings: Vec<&str> = s.splitn(2, separator).collect();
         if strings.len() != 2
             {
-                panic!("Cannot parse header part '{}' with separator '{}'", s, separator);
+                return None;
             }
-        (strings[0].trim().to_string(), strings[1].trim().to_string())
+        Some((strings[0].trim().to_string(), strings[1].trim().to_string()))
     }
 }
 
+/// Header line: parameters in order of received line, values quoted (see `Header::quote`)
 impl fmt::Display for Header
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut result = format!("{}: {}", self.name, self.value);
-        if self.fields.len() > 0
+        for key in self.ordered_fields()
             {
-                for (key, value) in self.fields.iter()
-                    {
-                        result.push_str(format!("; {}=\"{}\"", key, value).as_ref());
-                    }
+                result.push_str(format!("; {}=\"{}\"", key, Header::quote(&self.fields[key])).as_ref());
             }
         write!(f, "{}", result)
     }
@@ -61,21 +151,76 @@ impl fmt::Display for Header
 
 impl Headers
 {
+    /// Parse header lines, malformed lines (without `:`) are ignored.
+    /// For repeated header (ex.: two `Content-Type` lines, names compared ignoring case) first line wins.
+    /// At most `MAX_HEADER_PARAMS` parameters are parsed per header.
     pub fn new(header_lines: &Vec<String>) -> Headers
+    {
+        Headers::new_with_max_params(header_lines, MAX_HEADER_PARAMS)
+    }
+
+    /// Same as `Headers::new`, but at most `max_params` parameters are parsed per header, rest are ignored.
+    pub fn new_with_max_params(header_lines: &Vec<String>, max_params: usize) -> Headers
     {
         let mut headers: HashMap<String, Header> = HashMap::new();
+        let mut header_order: Vec<String> = vec![];
+        let mut names: HashSet<String> = HashSet::new();
         for line in header_lines
             {
-                let header = Header::new(line);
-                headers.insert(header.name.clone(), header);
+                if let Some(header) = Header::new(line, max_params)
+                    {
+                        if names.insert(header.name.to_lowercase())
+                            {
+                                header_order.push(header.name.clone());
+                                headers.insert(header.name.clone(), header);
+                            }
+                    }
             }
 
         Headers
             {
-                headers
+                headers,
+                header_order
             }
     }
 
+    /// Keys of `headers` in order of header lines (headers, added to `headers` directly, are not included)
+    pub fn header_order(&self) -> &Vec<String>
+    {
+        &self.header_order
+    }
+
+    /// Header lines (ex.: to re-emit part by `MultipartWriter`): in order of received lines, then headers,
+    /// added to `headers` directly (sorted). Parameters are in order of line, values are quoted (see `Header::quote`).
+    pub fn to_lines(&self) -> Vec<String>
+    {
+        let mut rest: Vec<&String> = self.headers.keys().filter(|key| !self.header_order.contains(key)).collect();
+        rest.sort();
+        self.header_order
+            .iter()
+            .filter(|key| self.headers.contains_key(*key))
+            .chain(rest)
+            .map(|key| self.headers[key].to_string())
+            .collect()
+    }
+
+    /// Parse raw header block (ex.: wire bytes of part before content), lines separated by `\r\n`.
+    /// Empty lines (ex.: trailing `\r\n\r\n`) are skipped, lines not valid UTF-8 and malformed lines are ignored.
+    ///
+    /// ```rust,ignore
+    /// let headers = Headers::parse(b"Content-Disposition: form-data; name=\"file1\"\r\nContent-Type: text/plain\r\n\r\n");
+    /// ```
+    pub fn parse(raw: &[u8]) -> Headers
+    {
+        let lines: Vec<String> = raw
+            .split(|&c| c == b'\n')
+            .map(|line| if line.last() == Some(&b'\r') { &line[..line.len() - 1] } else { line })
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| String::from_utf8(line.to_vec()).ok())
+            .collect();
+        Headers::new(&lines)
+    }
+
     /// Get value from header body.
     /// Ex: get "name" from multipart data part.
     ///
@@ -95,7 +240,7 @@ impl Headers
     {
         let name: String = name.into();
         let field_name: String = field_name.into();
-        match self.headers.get(&name)
+        match self.get_header(&name)
             {
                 None => None,
                 Some(header) =>
@@ -105,6 +250,33 @@ impl Headers
             }
     }
 
+    /// Get header by `name`, ignoring case (RFC 7230, ex.: `content-type` for `Content-Type`).
+    /// `Header::name` keeps spelling of part.
+    #[allow(dead_code)]
+    pub fn get_header(&self, name: &str) -> Option<&Header>
+    {
+        match self.headers.get(name)
+            {
+                Some(header) => Some(header),
+                None => self.headers.values().find(|header| header.name.eq_ignore_ascii_case(name))
+            }
+    }
+
+    /// Same as `get_header`, but mutable
+    fn get_header_mut(&mut self, name: &str) -> Option<&mut Header>
+    {
+        let key = match self.headers.contains_key(name)
+            {
+                true => Some(name.to_string()),
+                false => self.headers.keys().find(|key| key.eq_ignore_ascii_case(name)).cloned()
+            };
+        match key
+            {
+                Some(key) => self.headers.get_mut(&key),
+                None => None
+            }
+    }
+
     /// Get `name` from header
     #[allow(dead_code)]
     pub fn get_name(&self) -> Option<&String>
@@ -112,12 +284,146 @@ impl Headers
         self.get("Content-Disposition", "name")
     }
 
-    /// Get `filename` from header
+    /// Get `filename` from header, decoded `filename*` (RFC 5987) preferred
     #[allow(dead_code)]
     pub fn get_filename(&self) -> Option<&String>
     {
         self.get("Content-Disposition", "filename")
     }
+
+    /// Get MIME type from `Content-Type` header (ex.: `text/plain`)
+    #[allow(dead_code)]
+    pub fn get_mime_type(&self) -> Option<&str>
+    {
+        self.get_header("Content-Type").map(|header| header.value.as_str())
+    }
+
+    /// Get `charset` from `Content-Type` header
+    #[allow(dead_code)]
+    pub fn get_charset(&self) -> Option<&String>
+    {
+        self.get("Content-Type", "charset")
+    }
+
+    /// Get extension of `filename` (part after last `.`), `None` if no `filename` or no `.` in it
+    #[allow(dead_code)]
+    pub fn get_extension(&self) -> Option<&str>
+    {
+        match self.get_filename()
+            {
+                Some(filename) => filename.rfind('.').map(|pos| &filename[pos + 1..]),
+                None => None
+            }
+    }
+
+    /// Split indexed `name` in format `<prefix>[<index>][<field>]` (ex.: `address[0][city]`) to `(index, field)`.
+    /// Return `None` if `name` has another format or another prefix.
+    #[allow(dead_code)]
+    pub fn get_indexed_name(&self, prefix: &str) -> Option<(usize, String)>
+    {
+        let name = self.get_name()?;
+        if name.len() <= prefix.len() || !name.starts_with(prefix) || !name.ends_with(']')
+            {
+                return None;
+            }
+
+        let rest = &name[prefix.len()..name.len() - 1];
+        if !rest.starts_with('[')
+            {
+                return None;
+            }
+        let strings: Vec<&str> = rest[1..].splitn(2, "][").collect();
+        if strings.len() != 2 || strings[1].is_empty() || strings[1].contains(|c| c == '[' || c == ']')
+            {
+                return None;
+            }
+        match strings[0].parse::<usize>()
+            {
+                Ok(index) => Some((index, strings[1].to_string())),
+                Err(_) => None
+            }
+    }
+
+    /// Copy of headers with `name` in `Content-Disposition` replaced.
+    /// If part has no `Content-Disposition` (ex.: `multipart/related`), `Content-Disposition: form-data; name="<name>"` is added.
+    #[allow(dead_code)]
+    pub fn with_name(&self, name: &str) -> Headers
+    {
+        let mut result = self.clone();
+        if result.get_header("Content-Disposition").is_none()
+            {
+                result.headers.insert("Content-Disposition".to_string(), Header { name: "Content-Disposition".to_string(), value: "form-data".to_string(), fields: HashMap::new(), field_order: vec![] });
+                result.header_order.insert(0, "Content-Disposition".to_string());
+            }
+        if let Some(header) = result.get_header_mut("Content-Disposition")
+            {
+                if !header.fields.contains_key("name")
+                    {
+                        header.field_order.insert(0, "name".to_string());
+                    }
+                header.fields.insert("name".to_string(), name.to_string());
+            }
+        result
+    }
+
+    /// Get `Content-ID` (ex.: `<root>` to `root`), used to identify parts of `multipart/related` and `multipart/mixed`
+    #[allow(dead_code)]
+    pub fn get_content_id(&self) -> Option<&str>
+    {
+        self.get_header("Content-ID").map(|header| header.value.trim_start_matches('<').trim_end_matches('>'))
+    }
+
+    /// Get declared size of part data from `Content-Length`, `None` if header absent or invalid
+    #[allow(dead_code)]
+    pub fn get_content_length(&self) -> Option<usize>
+    {
+        self.get_header("Content-Length").and_then(|header| header.value.trim().parse().ok())
+    }
+
+    /// Percent-decode `name` and `filename` parameters of `Content-Disposition` (ex.: `user%5Bid%5D` to `user[id]`).
+    /// Invalid sequences and values, not valid UTF-8 after decoding, are left as is.
+    #[allow(dead_code)]
+    pub fn decode_param_names(&mut self) -> ()
+    {
+        if let Some(header) = self.get_header_mut("Content-Disposition")
+            {
+                for param in &["name", "filename"]
+                    {
+                        if let Some(value) = header.fields.get_mut(*param)
+                            {
+                                *value = percent_decode(value);
+                            }
+                    }
+            }
+    }
+
+    /// Remove NUL bytes (may truncate paths on some filesystems) from all parameter values (ex.: `filename="a\0b.txt"`).
+    /// Return names of changed parameters.
+    pub fn strip_nul(&mut self) -> Vec<String>
+    {
+        let mut result: Vec<String> = vec![];
+        for header in self.headers.values_mut()
+            {
+                for (key, value) in header.fields.iter_mut()
+                    {
+                        if value.contains('\0')
+                            {
+                                *value = value.replace('\0', "");
+                                result.push(key.clone());
+                            }
+                    }
+            }
+        result.sort();
+        result
+    }
+
+    /// Return `true` if part is file, i.e. `filename` present in header.
+    /// Empty `filename` (file input without selected file) is file too.
+    #[allow(dead_code)]
+    pub fn is_file(&self) -> bool
+    {
+        self.get_filename().is_some()
+    }
 }
 
 
@@ -126,6 +432,7 @@ pub struct HeadersBuilder
 {
     tmp: Vec<u8>,
     lines: Vec<String>,
+    max_params: usize,
 }
 
 impl HeadersBuilder
@@ -135,10 +442,17 @@ impl HeadersBuilder
         Self
             {
                 tmp: vec![],
-                lines: vec![]
+                lines: vec![],
+                max_params: MAX_HEADER_PARAMS
             }
     }
 
+    /// Maximum count of parameters parsed per header
+    pub fn max_params(&mut self, max_params: usize) -> ()
+    {
+        self.max_params = max_params;
+    }
+
     pub fn write(&mut self, c: u8) -> ()
     {
         self.tmp.push(c);
@@ -154,9 +468,31 @@ impl HeadersBuilder
         self.tmp.clear();
     }
 
+    /// Count of lines of header `name` (case-insensitive) in current headers
+    pub fn count(&self, name: &str) -> usize
+    {
+        self.lines
+            .iter()
+            .filter(|line| line.splitn(2, ':').next().map(|line_name| line_name.trim().eq_ignore_ascii_case(name)).unwrap_or(false))
+            .count()
+    }
+
+    /// Complete lines and incomplete line of current headers
+    pub fn export(&self) -> (Vec<String>, Vec<u8>)
+    {
+        (self.lines.clone(), self.tmp.clone())
+    }
+
+    /// Restore state from `export`
+    pub fn import(&mut self, lines: Vec<String>, tmp: Vec<u8>) -> ()
+    {
+        self.lines = lines;
+        self.tmp = tmp;
+    }
+
     pub fn build(&mut self) -> Headers
     {
-        let result = Headers::new(&self.lines);
+        let result = Headers::new_with_max_params(&self.lines, self.max_params);
         self.lines.clear();
         self.tmp.clear();
         result
@@ -166,7 +502,7 @@ impl HeadersBuilder
 #[cfg(test)]
 mod tests
 {
-    use super::{Headers};
+    use super::{Headers, HeadersBuilder};
 
     #[test]
     fn headers() -> ()
@@ -180,4 +516,237 @@ mod tests
         assert_eq!("file1", headers.get_name().unwrap());
         assert_eq!("a.txt", headers.get_filename().unwrap());
     }
+
+    #[test]
+    fn headers_odd_spaces() -> ()
+    {
+        let v: Vec<String> = vec![
+            "Content-Disposition:form-data ;name = \"file1\" ;  filename= \"a b.txt\"  ".to_string(),
+        ];
+
+        let headers = Headers::new(&v);
+        assert_eq!("file1", headers.get_name().unwrap());
+        assert_eq!("a b.txt", headers.get_filename().unwrap());
+    }
+
+    #[test]
+    fn headers_reordered() -> ()
+    {
+        let v: Vec<String> = vec![
+            "Content-Disposition: form-data; filename=\"a=b;c.txt\"; name=\"file1\"".to_string(),
+        ];
+
+        let headers = Headers::new(&v);
+        assert_eq!("file1", headers.get_name().unwrap());
+        assert_eq!("a=b;c.txt", headers.get_filename().unwrap());
+    }
+
+    #[test]
+    fn headers_is_file() -> ()
+    {
+        let file = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"".to_string()]);
+        let text = Headers::new(&vec!["Content-Disposition: form-data; name=\"text1\"".to_string()]);
+        assert!(file.is_file());
+        assert!(!text.is_file());
+    }
+
+    #[test]
+    fn headers_get_extension() -> ()
+    {
+        let file = |filename: &str| Headers::new(&vec![format!("Content-Disposition: form-data; name=\"file1\"; filename=\"{}\"", filename)]);
+        assert_eq!(Some("gz"), file("a.tar.gz").get_extension());
+        assert_eq!(Some("PDF"), file("a.PDF").get_extension());
+        assert_eq!(None, file("README").get_extension());
+        assert_eq!(None, Headers::new(&vec!["Content-Disposition: form-data; name=\"text1\"".to_string()]).get_extension());
+    }
+
+    #[test]
+    fn headers_indexed_name() -> ()
+    {
+        let field = |name: &str| Headers::new(&vec![format!("Content-Disposition: form-data; name=\"{}\"", name)]);
+        assert_eq!(Some((0, "city".to_string())), field("address[0][city]").get_indexed_name("address"));
+        assert_eq!(Some((12, "zip".to_string())), field("address[12][zip]").get_indexed_name("address"));
+        assert_eq!(None, field("address[0][city]").get_indexed_name("addr"));
+        assert_eq!(None, field("address").get_indexed_name("address"));
+        assert_eq!(None, field("address[x][city]").get_indexed_name("address"));
+        assert_eq!(None, field("address[0][]").get_indexed_name("address"));
+        assert_eq!(None, field("address[0][a][b]").get_indexed_name("address"));
+
+        let nested = field("address[1][city]").with_name("city");
+        assert_eq!("city", nested.get_name().unwrap());
+    }
+
+    #[test]
+    fn headers_malformed() -> ()
+    {
+        let v: Vec<String> = vec![
+            "garbage".to_string(),
+            "Content-Disposition: form-data; flag; name=\"file1\"; =".to_string(),
+            ":".to_string(),
+        ];
+
+        let headers = Headers::new(&v);
+        assert_eq!("file1", headers.get_name().unwrap());
+        assert_eq!(None, headers.get_filename());
+        assert!(headers.headers.get("garbage").is_none());
+    }
+
+    #[test]
+    fn headers_case_insensitive_names() -> ()
+    {
+        let headers = Headers::new(&vec!["content-disposition: form-data; name=\"x\"".to_string(), "CONTENT-TYPE: text/plain".to_string(), "Content-Type: image/png".to_string()]);
+        assert_eq!("x", headers.get_name().unwrap());
+        assert_eq!("x", headers.get("Content-Disposition", "name").unwrap());
+        // first line wins, spelling of part kept
+        assert_eq!("text/plain", headers.get_mime_type().unwrap());
+        assert_eq!("CONTENT-TYPE", headers.get_header("content-type").unwrap().name);
+        assert_eq!(2, headers.headers.len());
+
+        let renamed = headers.with_name("y");
+        assert_eq!("y", renamed.get_name().unwrap());
+        assert_eq!(2, renamed.headers.len());
+    }
+
+    #[test]
+    fn headers_extended_filename() -> ()
+    {
+        let euro = |line: &str| -> Option<String>
+            {
+                Headers::new(&vec![line.to_string()]).get_filename().cloned()
+            };
+        assert_eq!(Some("\u{20ac}.txt".to_string()), euro("Content-Disposition: form-data; name=\"file1\"; filename*=UTF-8''%e2%82%ac.txt"));
+        // `filename*` preferred, in any order
+        assert_eq!(Some("\u{20ac}.txt".to_string()), euro("Content-Disposition: form-data; name=\"file1\"; filename=\"EUR.txt\"; filename*=UTF-8''%e2%82%ac.txt"));
+        assert_eq!(Some("\u{20ac}.txt".to_string()), euro("Content-Disposition: form-data; name=\"file1\"; filename*=utf-8'en'%E2%82%AC.txt; filename=\"EUR.txt\""));
+        // not decoded - plain `filename`
+        assert_eq!(Some("EUR.txt".to_string()), euro("Content-Disposition: form-data; name=\"file1\"; filename=\"EUR.txt\"; filename*=KOI8-R''%C1.txt"));
+        assert_eq!(None, euro("Content-Disposition: form-data; name=\"file1\"; filename*=%e2%82%ac.txt"));
+    }
+
+    #[test]
+    fn headers_content_type() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"text1\"".to_string(), "Content-Type: text/plain; charset=UTF-8".to_string()]);
+        assert_eq!("text/plain", headers.get_mime_type().unwrap());
+        assert_eq!("UTF-8", headers.get_charset().unwrap());
+
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"text1\"".to_string()]);
+        assert_eq!(None, headers.get_mime_type());
+        assert_eq!(None, headers.get_charset());
+    }
+
+    #[test]
+    fn headers_empty_params() -> ()
+    {
+        for line in &[
+            "Content-Disposition: ; form-data; name=\"x\"",
+            "Content-Disposition: form-data; ; name=\"x\"",
+            "Content-Disposition: form-data;; name=\"x\"",
+            "Content-Disposition: form-data; name=\"x\";",
+            "Content-Disposition: form-data; name=\"x\" ; ; ",
+            "Content-Disposition: form-data;;;;name=\"x\";;filename=\"a;b.txt\"",
+        ]
+            {
+                let headers = Headers::new(&vec![line.to_string()]);
+                assert_eq!(Some(&"x".to_string()), headers.get_name(), "{}", line);
+            }
+
+        // empty parameters are not counted in `max_params`
+        let headers = Headers::new_with_max_params(&vec!["Content-Disposition: form-data; ; ; ; name=\"x\"".to_string()], 1);
+        assert_eq!(Some(&"x".to_string()), headers.get_name());
+    }
+
+    #[test]
+    fn headers_decode_param_names() -> ()
+    {
+        let v: Vec<String> = vec![
+            "Content-Disposition: form-data; name=\"user%5Bid%5D\"; filename=\"a%20b%22%zz%4.txt\"".to_string(),
+        ];
+
+        let mut headers = Headers::new(&v);
+        headers.decode_param_names();
+        assert_eq!("user[id]", headers.get_name().unwrap());
+        assert_eq!("a b\"%zz%4.txt", headers.get_filename().unwrap());
+
+        let mut headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"%FF\"".to_string()]);
+        headers.decode_param_names();
+        assert_eq!("%FF", headers.get_name().unwrap());
+    }
+
+    #[test]
+    fn headers_parse() -> ()
+    {
+        let raw: &[u8] = b"Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\n\r\n";
+        let headers = Headers::parse(raw);
+        assert_eq!("file1", headers.get_name().unwrap());
+        assert_eq!("a.txt", headers.get_filename().unwrap());
+        assert_eq!("text/plain", headers.headers.get("Content-Type").unwrap().value);
+        assert_eq!(2, headers.headers.len());
+
+        assert!(Headers::parse(b"").headers.is_empty());
+        assert_eq!("text1", Headers::parse(b"\xFF\r\nContent-Disposition: form-data; name=\"text1\"").get_name().unwrap());
+    }
+
+    #[test]
+    fn headers_duplicate_content_type() -> ()
+    {
+        let v: Vec<String> = vec![
+            "Content-Disposition: form-data; name=\"file1\"".to_string(),
+            "Content-Type: text/plain; charset=utf-8".to_string(),
+            "Content-Type: application/octet-stream; charset=latin1".to_string(),
+        ];
+
+        let headers = Headers::new(&v);
+        assert_eq!(Some("text/plain"), headers.get_mime_type());
+        assert_eq!("utf-8", headers.get_charset().unwrap());
+
+        let mut headers_builder = HeadersBuilder::new();
+        for line in &v
+            {
+                for &c in line.as_bytes()
+                    {
+                        headers_builder.write(c);
+                    }
+                headers_builder.flush();
+            }
+        assert_eq!(2, headers_builder.count("content-type"));
+        assert_eq!(1, headers_builder.count("Content-Disposition"));
+    }
+
+    #[test]
+    fn headers_content_id() -> ()
+    {
+        let headers = Headers::parse(b"Content-ID: <root.0@example.com>\r\nContent-Type: application/json\r\n");
+        assert_eq!(Some("root.0@example.com"), headers.get_content_id());
+        assert_eq!(None, headers.get_name());
+        assert_eq!(None, Headers::parse(b"Content-Type: text/plain").get_content_id());
+
+        let named = headers.with_name("root");
+        assert_eq!("root", named.get_name().unwrap());
+        assert_eq!("form-data", named.headers.get("Content-Disposition").unwrap().value);
+    }
+    #[test]
+    fn headers_to_lines() -> ()
+    {
+        let v: Vec<String> = vec![
+            "Content-Type: text/plain; charset=utf-8".to_string(),
+            "Content-Disposition: form-data; name=\"file1\"; size=3; filename=\"a\"b.txt\"".to_string(),
+        ];
+        let expected: Vec<String> = vec![
+            "Content-Type: text/plain; charset=\"utf-8\"".to_string(),
+            "Content-Disposition: form-data; name=\"file1\"; size=\"3\"; filename=\"a%22b.txt\"".to_string(),
+        ];
+        let headers = Headers::new(&v);
+        assert_eq!(expected, headers.to_lines());
+        assert_eq!(expected, Headers::new(&headers.to_lines()).to_lines());
+
+        // added header and parameter are first
+        let headers = Headers::parse(b"Content-ID: <root>\r\nContent-Type: application/json; charset=utf-8\r\n").with_name("root");
+        let expected: Vec<String> = vec![
+            "Content-Disposition: form-data; name=\"root\"".to_string(),
+            "Content-ID: <root>".to_string(),
+            "Content-Type: application/json; charset=\"utf-8\"".to_string(),
+        ];
+        assert_eq!(expected, headers.to_lines());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,115 @@
+//! Parse streaming `http_body::Body` (hyper, reqwest, axum etc) by `MultipartParser` (feature `http-body`)
+//!
+//! ```rust,ignore
+//! let target = Rc::new(RefCell::new(Form::default()));
+//! let content_type = request.headers().get(http::header::CONTENT_TYPE).unwrap();
+//! parse_http_body(content_type, request.into_body(), &target)?.await?;
+//! ```
+//!
+//! Data frames are written to `MultipartParser` as they arrive, trailers are ignored.
+
+use std::cell::{RefCell};
+use std::error::{Error};
+use std::future::{Future};
+use std::io::{Write, Error as IOError, ErrorKind};
+use std::pin::{Pin};
+use std::rc::{Rc};
+use std::task::{Context, Poll};
+
+use bytes::{Buf};
+use http::{HeaderValue};
+use http_body::{Body};
+
+use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated};
+use multipart_parser::{MultipartParser, MultipartParserState};
+pub use multipart_parser::{boundary_from_content_type};
+
+
+/// Create future, which write all data frames of `body` to `MultipartParser` for `target`.
+///
+/// * `content_type` - value of `Content-Type` header, must be `multipart/form-data` with `boundary`
+///
+/// Return `Err` with kind `InvalidInput` if `content_type` has no `boundary`.
+pub fn parse_http_body<B, T>(content_type: &HeaderValue, body: B, target: &Rc<RefCell<T>>) -> Result<ParseHttpBody<B, T>, IOError>
+    where B: Body,
+          B::Error: Into<Box<Error + Send + Sync>>,
+          T: MultipartParserTarget + MultipartParserTargetGenerated
+{
+    let boundary = content_type
+        .to_str()
+        .ok()
+        .and_then(boundary_from_content_type)
+        .ok_or_else(|| IOError::new(ErrorKind::InvalidInput, "Content-Type is not multipart/form-data with boundary"))?;
+
+    Ok(ParseHttpBody
+        {
+            body: Box::pin(body),
+            parser: MultipartParser::new_from_str(&boundary, target)
+        })
+}
+
+
+/// Future of `parse_http_body`, resolved after end of `body`.
+///
+/// Return `Err` from `MultipartParser::write`, error of `body` (kind `Other`), or error with kind `UnexpectedEof`
+/// if `body` finished before end of multipart data.
+pub struct ParseHttpBody<B: Body, T: MultipartParserTarget + MultipartParserTargetGenerated>
+{
+    body: Pin<Box<B>>,
+    parser: MultipartParser<T>
+}
+
+impl <B, T>ParseHttpBody<B, T>
+    where B: Body,
+          T: MultipartParserTarget + MultipartParserTargetGenerated
+{
+    /// Parser for setup before first poll (ex.: `defer_field_errors`, `fail_fast`)
+    pub fn parser(&mut self) -> &mut MultipartParser<T>
+    {
+        &mut self.parser
+    }
+}
+
+impl <B, T>Future for ParseHttpBody<B, T>
+    where B: Body,
+          B::Error: Into<Box<Error + Send + Sync>>,
+          T: MultipartParserTarget + MultipartParserTargetGenerated
+{
+    type Output = Result<(), IOError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output>
+    {
+        let this = self.get_mut();
+        loop
+            {
+                match this.body.as_mut().poll_frame(cx)
+                    {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(None) =>
+                            {
+                                if *this.parser.state() != MultipartParserState::Finished
+                                    {
+                                        return Poll::Ready(Err(IOError::new(ErrorKind::UnexpectedEof, "Multipart data truncated")));
+                                    }
+                                return Poll::Ready(this.parser.flush());
+                            },
+                        Poll::Ready(Some(Err(error))) => return Poll::Ready(Err(IOError::new(ErrorKind::Other, error))),
+                        Poll::Ready(Some(Ok(frame))) =>
+                            {
+                                if let Ok(mut data) = frame.into_data()
+                                    {
+                                        while data.has_remaining()
+                                            {
+                                                let size = data.chunk().len();
+                                                if let Err(error) = this.parser.write_all(data.chunk())
+                                                    {
+                                                        return Poll::Ready(Err(error));
+                                                    }
+                                                data.advance(size);
+                                            }
+                                    }
+                            }
+                    }
+            }
+    }
+}
\ No newline at end of file
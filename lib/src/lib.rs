@@ -2,12 +2,30 @@
 //! Read and parse headers (like `name`, `filename`), redirect stream for each part of data to user implementation `ProcessContent` trait.
 //! To use `MultipartParser` user must implement traits `MultipartParserTarget`, `MultipartParserTargetGenerated` for whole form-data,
 //! and implement `ProcessContent` for each form field.
-//! Or just use `gnitive-multipart-derive`.
+//! Or just use `gnitive-multipart-derive`, or iterate over parts as events (see `event_target`).
+//!
+//! Commonly used items can be imported at once by `use gnitive_multipart::prelude::*;`.
+//!
+//! Parser never panics on untrusted input: malformed data (ex.: invalid boundary) stops parsing and
+//! `MultipartParser::write` returns `std::io::Error` with kind `InvalidData`, malformed header lines and parameters are ignored.
 
-#![feature(vec_remove_item)]
 #![feature(try_from)]
 #![feature(never_type)]
 
+extern crate memchr;
+#[cfg(feature = "http-body")]
+extern crate bytes;
+#[cfg(feature = "http-body")]
+extern crate http;
+#[cfg(feature = "http-body")]
+extern crate http_body;
+#[cfg(feature = "content-md5")]
+extern crate md5;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "async")]
+extern crate tokio;
+
 pub mod gnitive_multipart
 {
     use std::cell::{RefCell};
@@ -41,21 +59,99 @@ pub mod gnitive_multipart
         /// Write `data` of multipart field. May be called many times (fragmentation by network packets, logic of boundary processing etc)
         ///
         /// * `headers` - headers for current field, equal to `headers` in `open`
-        /// * `data` - part of multipart field
-        fn write(&mut self, headers: &Headers, data: &Vec<u8>) -> ();
+        /// * `data` - part of multipart field. It is slice of buffer, passed to `MultipartParser::write` (no copy per call),
+        ///   so it is valid only during call - copy data to keep it (ex.: `raw_data.extend(data)`).
+        ///
+        /// `Err` will be returned from `MultipartParser::write`.
+        fn write(&mut self, headers: &Headers, data: &[u8]) -> Result<(), IOError>;
 
         /// Finish writing data. No `write` called for this field after `flush`.
         ///
         /// * `headers` - headers for current field, equal to `headers` in `open` and `write`
-        fn flush(&mut self, headers: &Headers) -> ();
+        ///
+        /// `Err` will be returned from `MultipartParser::write`.
+        fn flush(&mut self, headers: &Headers) -> Result<(), IOError>;
 
         /// Return parameters for processing current field.
         fn get_process_params(&self) -> &ProcessParams;
+
+        /// Count of bytes of current field, held in memory by processor (see `MultipartParser::set_max_in_flight_memory`),
+        /// default `0` - data is not buffered (ex.: written to file).
+        fn buffered_size(&self) -> usize { 0 }
+
+        /// Return hooks of processor, called by `MultipartParser` around `open`, `write` and `flush`,
+        /// default `None` - no hooks (see `ProcessContentHooks`).
+        fn hooks(&mut self) -> Option<&mut ProcessContentHooks> { None }
+
+        /// Data of field truncated: `MultipartParser` dropped after `open`, but before `flush`
+        /// (see `MultipartParser::cleanup_on_drop`). Release resources of partial data (ex.: remove partial file).
+        ///
+        /// * `headers` - headers for current field
+        fn cleanup(&mut self, _headers: &Headers) -> () {}
+
+        /// Field skipped by `OnError::SkipAndClose` after `open`, no `write` and `flush` calls more.
+        /// Release resources (ex.: close file), default - `flush`.
+        ///
+        /// * `headers` - headers for current field
+        fn abort(&mut self, headers: &Headers) -> Result<(), IOError>
+        {
+            self.flush(headers)
+        }
+    }
+
+
+    /// Additional control of field processing by `MultipartParser`, returned by `ProcessContent::hooks`.
+    /// Implemented by processors, generated by derive `MultipartParserTarget` (conversion errors of field),
+    /// and by processors, which reject or stop field themselves. All methods are optional.
+    pub trait ProcessContentHooks
+    {
+        /// Called by `MultipartParser` before `open`. If `defer` is `true`, conversion error must not be
+        /// passed to `MultipartParserTarget::error` in `flush`, but stored and returned by `take_deferred_error`.
+        fn set_defer_errors(&mut self, _defer: bool) -> () {}
+
+        /// Return conversion error, found in `flush`, called by `MultipartParser` after `flush`. If errors are not deferred
+        /// (see `set_defer_errors`), error is passed to `MultipartParserTarget::error` by processor too, `MultipartParser`
+        /// stores it for `MultipartParserTarget::on_errors` as `MultipartParseError::FieldError`.
+        fn take_deferred_error(&mut self) -> Option<FieldError> { None }
+
+        /// Called by `MultipartParser` before `open` with value of `MultipartParser::set_default_on_error`. If it is not
+        /// `OnError::ContinueWithError`, conversion error must not be passed to `MultipartParserTarget::error` in `flush`
+        /// (default action is applied by `MultipartParser`).
+        fn set_default_on_error(&mut self, _on_error: OnError) -> () {}
+
+        /// Return result of `MultipartParserTarget::error`, called by processor for conversion error in `flush`,
+        /// called by `MultipartParser` after `flush`: `Err` is returned from `MultipartParser::write`. Default `None`.
+        fn take_error_result(&mut self) -> Option<Result<OnError, IOError>> { None }
+
+        /// Return error of field, found by its headers in `open` (ex.: not allowed extension), called by `MultipartParser` after `open`.
+        /// Error is handled as other errors of field (`MultipartParser::set_default_on_error` or `MultipartParserTarget::error`,
+        /// `fail_fast`, `on_errors`), processor rejects data of field itself. Default `None`.
+        fn take_open_error(&mut self) -> Option<MultipartParseError<'static>> { None }
+
+        /// Called by `MultipartParser` after each `write`. Return `WriteControl::StopPart`, if rest of field is not needed
+        /// (ex.: streaming validator found enough), default `WriteControl::Continue`.
+        fn write_control(&mut self) -> WriteControl { WriteControl::Continue }
+    }
+
+
+    /// Conversion error for one field, stored for deferred reporting (see `MultipartParser::defer_field_errors`)
+    #[derive(Clone)]
+    pub struct FieldError
+    {
+        /// Name of field
+        pub name: String,
+
+        /// Raw data of field
+        pub raw_data: Vec<u8>,
+
+        /// Text of conversion error (ex.: `invalid digit found in string`)
+        pub message: String
     }
 
 
 
     /// Type of error, used in `MultipartParserTarget::error` trait.
+    #[derive(Clone)]
     pub enum MultipartParseError<'a>
     {
         NoError,
@@ -66,7 +162,9 @@ pub mod gnitive_multipart
         /// * `Vec<String>` - list of field names, absent in multipart data.
         RequiredMissing(&'a Vec<String>),
 
-        /// Limit of `max_size` was exceeded.
+        /// Limit of `max_size` was exceeded. If `Content-Length` of part exceeds `max_size`, error is fired before
+        /// reading data: on `OnError::Skip` (or `SkipAndClose`) part is skipped without opening of processor,
+        /// on `OnError::ContinueWithoutError` (or `ContinueWithError`) part is read, error is not fired again.
         ///
         /// * `String` - field name
         /// * `usize` - limit
@@ -89,11 +187,143 @@ pub mod gnitive_multipart
 
         /// * `String` - field name
         /// * `FromUtf8Error` - `std::string::FromUtf8Error`
-        ParseStrError(String, &'a FromUtf8Error)
+        ParseStrError(String, &'a FromUtf8Error),
+
+        /// All conversion errors, collected when `MultipartParser::defer_field_errors` enabled.
+        /// This error fired after finish receiving all data.
+        ///
+        /// * `Vec<FieldError>` - list of conversion errors
+        FieldErrors(&'a Vec<FieldError>),
+
+        /// Conversion error of one field, passed only to `MultipartParserTarget::on_errors` (`error` receives error
+        /// of conversion itself, ex.: `ParseIntError`), if `MultipartParser::defer_field_errors` is not enabled.
+        ///
+        /// * `FieldError` - conversion error
+        FieldError(FieldError),
+
+        /// Extension of uploaded file not in list of field `extensions`, field skipped.
+        ///
+        /// * `String` - field name
+        /// * `String` - extension, empty if filename has no extension
+        DisallowedExtension(String, String),
+
+        /// `Content-Type` of part (without parameters) differs from field `require_content_type`, field skipped.
+        ///
+        /// * `String` - field name
+        /// * `String` - `Content-Type` of part, empty if part has no `Content-Type`
+        DisallowedContentType(String, String),
+
+        /// Value of field, marked as `non_empty=true`, is empty, field value is not changed.
+        ///
+        /// * `String` - field name
+        EmptyField(String),
+
+        /// Value of float field, marked as `finite=true`, is `NaN` or infinity, field value is not changed.
+        ///
+        /// * `String` - field name
+        NonFiniteFloat(String),
+
+        /// MD5 of part data differs from `Content-MD5` header, fired only in `MultipartParser::verify_content_md5` mode
+        /// (feature `content-md5`) before `ProcessContent::flush`. `OnError::Skip` skips `flush` (field value is not changed).
+        ///
+        /// * `String` - field name
+        ChecksumMismatch(String),
+
+        /// Validator of field (see `MultipartParser::add_validator`) rejected its data, fired before `ProcessContent::flush`.
+        /// `OnError::Skip` skips `flush` (field value is not changed).
+        ///
+        /// * `String` - field name
+        /// * `String` - message of validator
+        ValidationFailed(String, String),
+
+        /// Part has unknown `Content-Transfer-Encoding` (not `7bit`, `8bit`, `binary`, `base64` or `quoted-printable`),
+        /// fired by derive in `ProcessContent::open`, field value is not changed.
+        ///
+        /// * `String` - field name
+        /// * `String` - value of `Content-Transfer-Encoding`
+        UnsupportedEncoding(String, String),
+
+        /// Data of part is invalid for its `Content-Transfer-Encoding` (ex.: not base64 symbol), fired by derive in
+        /// `ProcessContent::flush`, field value is not changed. Conversion error: deferred with `MultipartParser::defer_field_errors`.
+        ///
+        /// * `String` - field name
+        /// * `String` - error of decoding
+        InvalidEncoding(String, String),
+
+        /// Sum of data of all fields, buffered in memory, exceeds `MultipartParser::set_max_in_flight_memory`,
+        /// rest of current part skipped (`ProcessContent::abort` is called for opened processor).
+        ///
+        /// * `usize` - memory limit (in bytes)
+        MemoryLimit(usize),
+
+        /// First boundary of data is invalid, parsing stopped (`MultipartParser::write` returns `Err` with `ErrorKind::InvalidData`).
+        ///
+        /// * `usize` - offset of invalid byte in data
+        MalformedBoundary(usize),
+
+        /// Symbols of partial `\r\n` in headers are re-fed more than `MultipartParser::max_header_refeed` times per received
+        /// symbol, parsing stopped (`MultipartParser::write` returns `Err` with `ErrorKind::InvalidData`).
+        ///
+        /// * `usize` - limit
+        HeaderRefeedLimit(usize),
+
+        /// Size of all data exceeds `MultipartParser::set_max_total_size`, parsing stopped (`ProcessContent::abort` is called
+        /// for opened processor, `MultipartParser::write` returns `Err` with `ErrorKind::InvalidData`).
+        ///
+        /// * `usize` - limit (in bytes)
+        TotalSizeLimit(usize),
+
+        /// Count of parts exceeds `MultipartParser::set_max_parts`, fired before selecting processor for next part.
+        /// Parsing is finished (`MultipartParserTarget::finish` is called), rest of data ignored.
+        ///
+        /// * `usize` - limit
+        TooManyParts(usize),
+
+        /// Part of declared field received after part of field, declared later (see derive `ordered`).
+        /// Parts of not declared fields and repeated parts of the same field are allowed.
+        OutOfOrder
+        {
+            /// Next declared field after last received one in order, empty if last declared field received
+            expected: String,
+
+            /// Name of received field
+            found: String
+        },
+
+        /// Part is processed longer than `MultipartParser::max_part_duration`, rest of part skipped
+        /// (`ProcessContent::abort` is called for opened processor).
+        ///
+        /// * `String` - field name
+        PartTimeout(String),
+
+        /// Parameter value of header is invalid (ex.: contains NUL byte), fired only in `MultipartParser::strict` mode.
+        /// Invalid characters are removed from value anyway.
+        ///
+        /// * `String` - parameter name (ex.: `filename`)
+        /// * `String` - reason
+        InvalidParameter(String, String),
+
+        /// Part without `name` (or with empty `name`), fired by derive with `require_name`, part is dropped.
+        MissingName,
+
+        /// Part has several `Content-Type` headers (first is used), fired only in `MultipartParser::strict` mode.
+        ///
+        /// * `String` - field name
+        DuplicateContentType(String),
+
+        /// Count of parts less than `MultipartParser::set_min_parts`, fired at end of data.
+        TooFewParts
+        {
+            /// Minimum count of parts
+            expected: usize,
+
+            /// Count of received parts
+            received: usize
+        }
     }
 
     /// Action after processing `MultipartParseError` in `MultipartParserTarget::error`.
-    #[derive(PartialEq)]
+    #[derive(PartialEq, Clone)]
     pub enum OnError
     {
         /// Continue call write for current field, no `error` call more for this field.
@@ -104,6 +334,21 @@ pub mod gnitive_multipart
 
         /// Skip current field, no `write` and `flush` calls for this field.
         Skip,
+
+        /// Skip current field as `Skip`, but call `ProcessContent::abort` of opened processor before,
+        /// so it can release resources (ex.: close file). Before `ProcessContent::open` it is the same as `Skip`.
+        SkipAndClose,
+    }
+
+    /// Result of `ProcessContentHooks::write_control`: deliver rest of current part or not.
+    #[derive(Debug, PartialEq, Clone)]
+    pub enum WriteControl
+    {
+        /// Continue call write for current field.
+        Continue,
+
+        /// Skip rest of current field as `OnError::Skip` (no `write` and `flush` calls for this field), without error.
+        StopPart,
     }
 
 
@@ -127,6 +372,31 @@ pub mod gnitive_multipart
         /// * `Error(std::io::Error)` - finish read data, `Error` will be return to stream reader
         fn error(&mut self, _error: &MultipartParseError) -> Result<OnError, IOError> { Ok(OnError::ContinueWithoutError) }
 
+        /// Completed text part (not file), for which `content_parser` returned `None`, unless disabled by `MultipartParser::buffer_text_fields`.
+        /// Value is buffered by `MultipartParser` up to `MultipartParser::text_field_max_size` bytes.
+        ///
+        /// * `_name` - name of field
+        /// * `_value` - content of field
+        fn on_text_field(&mut self, _name: &str, _value: &[u8]) -> () {}
+
+        /// Part declares multipart content type (`Content-Type: multipart/...`), ex.: to detect and reject nested multipart uploads.
+        /// Called before `content_parser`, nested data is not parsed.
+        ///
+        /// * `_headers` - all headers for current field
+        ///
+        /// Return `OnError::Skip` to skip part (no `content_parser`, `write` and `flush` calls), other values - process part as usual.
+        fn on_nested_multipart(&mut self, _headers: &Headers) -> OnError { OnError::ContinueWithoutError }
+
+        /// Data truncated: `MultipartParser` dropped before end of data (see `MultipartParser::cleanup_on_drop`).
+        /// Called before `finish`.
+        fn truncated(&mut self) -> () {}
+
+        /// Errors of data, fired by `MultipartParser`, in order of firing; called once before `finish` if there were errors.
+        /// Contains every error fired by `MultipartParser` (ex.: `SizeLimit`, `OutOfOrder`, `TooFewParts`), even if it is not
+        /// passed to `error` because of `MultipartParser::set_default_on_error`, conversion errors of fields as `FieldError`
+        /// (or `FieldErrors` with `MultipartParser::defer_field_errors`) and `RequiredMissing` at end of data.
+        fn on_errors(&mut self, _errors: &[MultipartParseError]) -> () {}
+
         /// Finish of all data, no `content_parser` or `error` will be called.
         fn finish(&mut self) -> () {}
     }
@@ -135,6 +405,13 @@ pub mod gnitive_multipart
     pub trait MultipartParserTargetGenerated
     {
         fn get_all_required(&self) -> Vec<String>;
+
+        /// All declared field names (required and optional), see `MultipartParser::unreceived_optional`
+        fn get_all_declared(&self) -> Vec<String> { vec![] }
+
+        /// Field names in required order of parts, empty if order is not checked (see derive `ordered`)
+        fn get_ordered(&self) -> Vec<String> { vec![] }
+
         fn content_parser_generated(&mut self, self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>;
     }
 
@@ -146,6 +423,7 @@ pub mod gnitive_multipart
     ///    /                    /               \                /
     /// Content-Disposition: form-data; name="file1"; filename="a.txt"
     /// ```
+    #[derive(Clone)]
     pub struct Header
     {
         /// Header name (ex.: `Content-Type`, `Content-Disposition`)
@@ -155,16 +433,23 @@ pub mod gnitive_multipart
         pub value: String,
 
         /// Rest of header body (ex.: `charset` => `UTF-8`, `filename` => `a.txt`)
-        pub fields: HashMap<String, String>
+        pub fields: HashMap<String, String>,
+
+        /// Names of `fields` in order of header line, used by `Display`, see `Header::field_order`
+        pub(crate) field_order: Vec<String>
     }
 
 
     /// Multipart/form-data headers (for one part of data!)
+    #[derive(Clone)]
     pub struct Headers
     {
         /// All headers for this part of data.
-        /// Key = header name (ex.: `Content-Type`, `Content-Disposition`)
-        pub headers: HashMap<String, Header>
+        /// Key = header name as received (ex.: `Content-Type`, `content-disposition`), see `Headers::get_header` for lookup ignoring case
+        pub headers: HashMap<String, Header>,
+
+        /// Keys of `headers` in order of header lines, see `Headers::header_order`, `Headers::to_lines`
+        pub(crate) header_order: Vec<String>
     }
 
 
@@ -176,6 +461,21 @@ pub mod gnitive_multipart
         fn to_multipart_parse_error(&'a self, name: String, raw_data: &'a Vec<u8>) -> MultipartParseError;
     }
 
+    impl FieldError
+    {
+        pub fn new<T>(name: T, raw_data: Vec<u8>, message: String) -> FieldError
+            where T: Into<String>
+        {
+            let name: String = name.into();
+            FieldError
+                {
+                    name,
+                    raw_data,
+                    message
+                }
+        }
+    }
+
     impl ProcessParams
     {
         pub fn new<T>(name: T, max_size: Option<usize>) -> ProcessParams
@@ -192,8 +492,23 @@ pub mod gnitive_multipart
 }
 
 
+#[cfg(feature = "async")]
+pub mod async_write;
 mod boundary_builder;
+pub mod capturing_target;
+pub mod chunking;
+pub mod event_target;
+#[cfg(feature = "framed")]
+pub mod framed_reader;
 mod header;
+#[cfg(feature = "http-body")]
+pub mod http_body_parser;
 pub mod multipart_parser;
+pub mod multipart_writer;
+pub mod part_reader;
+pub mod prelude;
 pub mod process_content;
-pub mod to_multipart_parse_error;
\ No newline at end of file
+pub mod related_collector;
+pub mod to_multipart_parse_error;
+pub mod transfer_encoding;
+pub mod url_decode;
\ No newline at end of file
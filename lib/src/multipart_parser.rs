@@ -2,11 +2,60 @@ use std::cell::{RefCell};
 use std::rc::{Rc};
 use header::{HeadersBuilder};
 use boundary_builder::{BoundaryBuilder};
-use std::io::{Write, Error};
-use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, MultipartParseError, ProcessContent, Headers, OnError};
+use std::io::{Write, Error, ErrorKind};
+use std::time::{Duration, Instant};
+use std::collections::{HashMap};
+use std::path::{PathBuf};
+use std::vec;
+use memchr::{memchr};
+#[cfg(feature = "content-md5")]
+use md5::{Context as Md5Context};
+#[cfg(feature = "content-md5")]
+use transfer_encoding::{Cte, Decoder};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, MultipartParseError, ProcessContent, ProcessParams, Headers, OnError, WriteControl, FieldError};
+use ::event_target::{EventTarget, MultipartEvent};
+use ::process_content::{HybridProcessor, SPILL_THRESHOLD};
+
+/// Default maximum size of text part, passed to `MultipartParserTarget::on_text_field`
+pub const TEXT_FIELD_MAX_SIZE: usize = 64 * 1024;
+
+/// Default maximum count of parameters (ex.: `name="file1"`), parsed per header line
+pub const MAX_HEADER_PARAMS: usize = 32;
+
+/// Default maximum count of symbols, processed in headers per received symbol (re-fed symbols of partial `\r\n` included)
+pub const MAX_HEADER_REFEED: usize = 16;
+
+/// Get `boundary` parameter of `multipart/form-data` content type (ex.: `multipart/form-data; boundary="abc"` to `abc`)
+pub fn boundary_from_content_type(content_type: &str) -> Option<String>
+{
+    let mut params = content_type.split(';');
+    if !params.next()?.trim().eq_ignore_ascii_case("multipart/form-data")
+        {
+            return None;
+        }
+
+    for param in params
+        {
+            let strings: Vec<&str> = param.splitn(2, '=').collect();
+            if strings.len() == 2 && strings[0].trim().eq_ignore_ascii_case("boundary")
+                {
+                    let boundary = strings[1].trim().trim_matches('"');
+                    return match boundary.is_empty()
+                        {
+                            true => None,
+                            false => Some(boundary.to_string())
+                        };
+                }
+        }
+    None
+}
 
 #[derive(Debug)]
 #[derive(PartialEq)]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Internal state for `MultipartParser`
 pub enum MultipartParserState
 {
@@ -29,6 +78,47 @@ pub enum MultipartParserState
     Finished,
 }
 
+/// Snapshot of `MultipartParser` position in data, see `MultipartParser::export_state`.
+/// Processor of current part and its buffers are not included.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParserState
+{
+    pub state: MultipartParserState,
+    pub compare_pos: usize,
+    pub content_size: usize,
+    pub unprocessed: Vec<String>,
+    pub unreceived: Vec<String>,
+    pub ordered_pos: usize,
+    pub parts_seen: usize,
+
+    /// Header lines of current part (in `Content` state)
+    pub headers: Vec<String>,
+
+    /// Header lines of next part, read before snapshot (in `Header` and `PostHeader` states)
+    pub pending_header_lines: Vec<String>,
+
+    /// Incomplete header line
+    pub pending_header_line: Vec<u8>,
+
+    /// Symbols of possible boundary in content as received (`compare_pos` symbols, see `case_insensitive_boundary`)
+    pub boundary_received: Vec<u8>
+}
+
+/// How part is identified for `MultipartParserTarget::content_parser`, see `MultipartParser::routing`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Routing
+{
+    /// By `name` of `Content-Disposition` (`multipart/form-data`)
+    Name,
+
+    /// By `Content-ID` (`multipart/related`, `multipart/mixed`), parts without `Content-ID` - by `name`
+    ContentId,
+
+    /// By position of part, from `0` (`multipart/mixed`)
+    Index
+}
+
 pub struct MultipartParser<T: MultipartParserTarget + MultipartParserTargetGenerated>
 {
     /// First boundary in body - without `\r\n` in head
@@ -66,6 +156,17 @@ pub struct MultipartParser<T: MultipartParserTarget + MultipartParserTargetGener
     /// ```
     empty_string: Rc<RefCell<Vec<u8>>>,
 
+    /// Prefix function (KMP failure table) of `boundary_middle`: `boundary_failure[i]` - length of longest
+    /// proper prefix of `boundary_middle[0..i + 1]`, which is suffix of it too
+    boundary_failure: Vec<usize>,
+
+    /// Compare boundary ignoring ASCII case, see `case_insensitive_boundary`
+    case_insensitive_boundary: bool,
+
+    /// Symbols of possible boundary in content as received (with `case_insensitive_boundary` they may differ by case
+    /// from `boundary_middle`), written to processor on boundary mismatch
+    boundary_received: Vec<u8>,
+
     /// State
     state: MultipartParserState,
 
@@ -80,14 +181,144 @@ pub struct MultipartParser<T: MultipartParserTarget + MultipartParserTargetGener
 
     compare_pos: usize,
     content_start: usize,
-    content_end: usize,
     content_size: usize,
     content_size_max: Option<usize>,
     buf_pos: usize,
     unprocessed: Vec<String>,
+
+    /// Declared field names, not received yet
+    unreceived: Vec<String>,
+
+    /// Field names in required order, see `MultipartParserTargetGenerated::get_ordered`
+    ordered: Vec<String>,
+
+    /// Position in `ordered` after last received field
+    ordered_pos: usize,
     on_error: OnError,
+
+    /// Action on error for current field, applied without `MultipartParserTarget::error` call, if not `ContinueWithError`
+    default_on_error: OnError,
     error_fired: bool,
 
+    /// Store conversion errors and fire `MultipartParseError::FieldErrors` at end of data
+    defer_field_errors: bool,
+
+    /// Conversion errors, stored if `defer_field_errors` is `true`
+    field_errors: Vec<FieldError>,
+
+    /// Errors of parts, fired by parser, passed to `MultipartParserTarget::on_errors` at end of data
+    fired_errors: Vec<MultipartParseError<'static>>,
+
+    /// Return `Err` from `write` instead of any `MultipartParserTarget::error` call
+    fail_fast: bool,
+
+    /// Percent-decode `name` and `filename` parameters of `Content-Disposition`
+    decode_param_names: bool,
+
+    /// Buffer text parts without processor for `MultipartParserTarget::on_text_field`, see `buffer_text_fields`
+    buffer_text_fields: bool,
+
+    /// Buffer for current text part without processor, see `MultipartParserTarget::on_text_field`
+    text_field: Option<Vec<u8>>,
+
+    /// Maximum size of `text_field`
+    text_field_max_size: usize,
+
+    /// Count of parts, which headers are read
+    parts_seen: usize,
+
+    /// Report malformed, but parseable data (ex.: duplicate `Content-Type`) by `MultipartParserTarget::error`
+    strict: bool,
+
+    /// How part is identified
+    routing: Routing,
+
+    /// Minimum count of parts, checked at end of data
+    min_parts: usize,
+
+    /// On `Drop` before end of data call `ProcessContent::cleanup`, `MultipartParserTarget::truncated` and `finish`
+    cleanup_on_drop: bool,
+
+    /// Parse header block after final boundary, see `parse_epilogue_headers`
+    parse_epilogue_headers: bool,
+
+    /// Data after final boundary, collected up to end of header block (if `parse_epilogue_headers`)
+    epilogue_data: Option<Vec<u8>>,
+
+    /// Headers after final boundary
+    epilogue_headers: Option<Headers>,
+
+    /// Record duration of each part, see `collect_timings`
+    collect_timings: bool,
+
+    /// Start of current part (if `collect_timings`)
+    part_started: Option<Instant>,
+
+    /// Name and duration of each part
+    part_timings: Vec<(String, Duration)>,
+
+    /// In `flush` deliver last part, if data ends without final boundary, see `flush_incomplete_final_part`
+    flush_incomplete_final_part: bool,
+
+    /// Maximum duration of part, see `max_part_duration`
+    max_part_duration: Option<Duration>,
+
+    /// Deadline of current part (if `max_part_duration`)
+    part_deadline: Option<Instant>,
+
+    /// Processor factories for parts without processor by pattern of `Content-Type`, see `route_by_content_type`
+    content_type_routes: Vec<(String, Box<Fn(&Headers) -> Rc<RefCell<ProcessContent>>>)>,
+
+    /// Directory for parts without processor, spilled by `HybridProcessor`, see `spill_by_default`
+    spill_dir: Option<PathBuf>,
+
+    /// `HybridProcessor::spill_threshold` of parts without processor
+    spill_threshold: usize,
+
+    /// Names and processors of parts, stored by `spill_by_default`
+    default_parts: Vec<(String, Rc<RefCell<HybridProcessor>>)>,
+
+    /// Validators of complete field data by field name, see `add_validator`
+    validators: HashMap<String, Box<Fn(&[u8]) -> Result<(), String>>>,
+
+    /// Data of current part (if it has validator)
+    validated_data: Option<Vec<u8>>,
+
+    /// Maximum size of `validated_data`: `max_size` of processor or `text_field_max_size`
+    validated_data_max: Option<usize>,
+
+    /// Data of current part exceeds `validated_data_max`, rest of data is not buffered
+    validated_data_exceeded: bool,
+
+    /// Maximum sum of data of all fields, buffered in memory, see `set_max_in_flight_memory`
+    max_in_flight_memory: Option<usize>,
+
+    /// Data of completed fields, buffered in memory (`ProcessContent::buffered_size` before `flush`, text parts)
+    in_flight_memory: usize,
+
+    /// Maximum size of all data, passed to `write`, see `set_max_total_size`
+    max_total_size: Option<usize>,
+
+    /// Size of all data, passed to `write`
+    total_size: usize,
+
+    /// Maximum count of parts, see `set_max_parts`
+    max_parts: Option<usize>,
+
+    /// Maximum count of symbols, processed in headers per received symbol, see `max_header_refeed`
+    max_header_refeed: usize,
+
+    /// Symbols to process in `process_header`, last is next (kept to reuse allocation)
+    header_queue: Vec<u8>,
+
+    /// Compare MD5 of part data with `Content-MD5` header
+    #[cfg(feature = "content-md5")]
+    verify_content_md5: bool,
+
+    /// MD5 of data of current part and decoded `Content-MD5` header
+    #[cfg(feature = "content-md5")]
+    content_md5: Option<(Md5Context, Vec<u8>)>,
+
     /// Target struct
     target: Rc<RefCell<T>>
 }
@@ -98,17 +329,37 @@ impl <T>Write for MultipartParser<T>
     fn write(&mut self, buf: &[u8]) -> Result<usize, Error>
     {
         self.content_start = 0;
+        self.check_total_size(buf.len())?;
+        self.check_part_duration()?;
 
-        for pos in 0..buf.len()
+        let mut pos: usize = 0;
+        while pos < buf.len()
             {
+                if self.state == MultipartParserState::Content && self.compare_pos == 0
+                    {
+                        // fast path: skip content up to possible start of boundary, it is written by runs anyway
+                        let first = self.boundary_middle.borrow()[0];
+                        match memchr(first, &buf[pos..])
+                            {
+                                Some(offset) => pos += offset,
+                                None => break
+                            }
+                    }
+
+                if self.state == MultipartParserState::Finished
+                    {
+                        self.epilogue_write(&buf[pos..]);
+                        break;
+                    }
+
                 self.buf_pos = pos;
                 let c = buf[pos];
 
                 match self.state
                     {
-                        MultipartParserState::BoundaryFirst => self.process_boundary_first(c),
-                        MultipartParserState::Header => self.process_header(c),
-                        MultipartParserState::PostHeader => self.process_post_header(c),
+                        MultipartParserState::BoundaryFirst => self.process_boundary_first(c)?,
+                        MultipartParserState::Header => self.process_header(c)?,
+                        MultipartParserState::PostHeader => self.process_post_header(c)?,
                         MultipartParserState::Content => {
                             // Only in this state `MultipartParserTarget::error` function might be called
                             match self.process_content(c, buf)
@@ -117,35 +368,90 @@ impl <T>Write for MultipartParser<T>
                                     Err(io_error) => return Err(io_error)
                                 }
                         },
-                        MultipartParserState::PostBoundary => self.process_post_boundary(c),
+                        MultipartParserState::PostBoundary => self.process_post_boundary(c)?,
                         MultipartParserState::Finished => ()
                     };
+                pos += 1;
             }
 
-        if self.state == MultipartParserState::Content
+        // symbols of possible boundary (`compare_pos > 0`) are not written until boundary mismatch
+        if self.state == MultipartParserState::Content && self.compare_pos == 0
             {
-                let from = self.content_start.clone();
-                let to = self.buf_pos.clone() + 1;
-                if let Some(ref  process_content) = self.process_content
-                    {
-                        let process_content =process_content.borrow();
-                        let params = process_content.get_process_params();
-
-                        println!("{} from {} to  {}", &params.name, &from, &to);
-
-                    }
+                let from = self.content_start;
+                let to = buf.len();
                 self.processor_write_from_to(buf, from, to)?;
             }
         Ok(buf.len())
     }
 
+    /// Deliver incomplete last part, see `flush_incomplete_final_part`: `Err` of `ProcessContent::flush` is returned.
+    /// Data is not buffered, otherwise no-op.
     fn flush(&mut self) -> Result<(), Error>
     {
+        if self.flush_incomplete_final_part
+            {
+                if self.state == MultipartParserState::BoundaryFirst && self.compare_pos == 0
+                    {
+                        self.to_finished()?;
+                    }
+                if self.state == MultipartParserState::Content
+                    {
+                        self.compare_pos = 0;
+                        self.processor_flush()?;
+                        self.record_part_timing();
+                        self.state = MultipartParserState::PostBoundary;
+                    }
+                if self.state == MultipartParserState::PostBoundary
+                    {
+                        self.to_finished()?;
+                    }
+            }
         Ok(())
     }
 }
 
 
+impl <T>Drop for MultipartParser<T>
+    where T: MultipartParserTarget + MultipartParserTargetGenerated
+{
+    fn drop(&mut self) -> ()
+    {
+        self.cleanup_truncated();
+    }
+}
+
+
+impl <T>MultipartParser<T>
+    where T: MultipartParserTarget + MultipartParserTargetGenerated
+{
+    /// With `cleanup_on_drop` and incomplete data call `ProcessContent::cleanup` for current part,
+    /// `MultipartParserTarget::truncated` and finish parsing (see `cleanup_on_drop`)
+    fn cleanup_truncated(&mut self) -> ()
+    {
+        // target borrowed (ex.: parser dropped while unwinding from `content_parser`) - nothing can be called
+        if !self.cleanup_on_drop || self.state == MultipartParserState::Finished || self.target.try_borrow_mut().is_err()
+            {
+                return;
+            }
+
+        if self.state == MultipartParserState::Content
+            {
+                if let (&Some(ref process_content), &Some(ref headers)) = (&self.process_content, &self.headers)
+                    {
+                        if let Ok(mut process_content) = process_content.try_borrow_mut()
+                            {
+                                process_content.cleanup(headers);
+                            }
+                    }
+            }
+        self.process_content = None;
+        self.target.borrow_mut().truncated();
+        // all data processed - `fail_fast` error can be ignored
+        let _unused = self.to_finished();
+    }
+}
+
+
 impl <T>MultipartParser<T>
     where T: MultipartParserTarget + MultipartParserTargetGenerated
 {
@@ -157,6 +463,32 @@ impl <T>MultipartParser<T>
         MultipartParser::new_from_vec(boundary, target)
     }
 
+    /// Create `MultipartParser`, which owns `target`, with known string `boundary`.
+    /// Use `into_target` to get `target` back after all data written.
+    pub fn new_owned(boundary: &str, target: T) -> Self
+    {
+        let target = Rc::new(RefCell::new(target));
+        MultipartParser::new_from_str(boundary, &target)
+    }
+
+    /// Return target struct, usually after all data written and `flush` called.
+    ///
+    /// Panics if `target` is still shared (ex.: `Rc` passed to `new_from_str` is alive or cloned by `content_parser`).
+    pub fn into_target(mut self) -> T
+    {
+        // incomplete part is cleaned up before its processor is released
+        self.cleanup_truncated();
+        // current processor may hold clone of `target`
+        self.process_content = None;
+        let target = self.target.clone();
+        drop(self);
+        match Rc::try_unwrap(target)
+            {
+                Ok(target) => target.into_inner(),
+                Err(_) => panic!("Cannot return target of MultipartParser, it is still shared")
+            }
+    }
+
     /// Create `MultipartParser` for struct `target` with known vector `boundary`
     pub fn new_from_vec(boundary: Vec<u8>, target: &Rc<RefCell<T>>) -> Self
     {
@@ -184,7 +516,11 @@ impl <T>MultipartParser<T>
         empty_string
             .append_crlf();
 
+        let boundary_failure = MultipartParser::<T>::failure_table(&boundary_middle);
+
         let unprocessed = target.borrow().get_all_required();
+        let unreceived = target.borrow().get_all_declared();
+        let ordered = target.borrow().get_ordered();
 
 
         MultipartParser
@@ -194,6 +530,9 @@ impl <T>MultipartParser<T>
                 divider: Rc::new(RefCell::new(divider)),
                 epilogue: Rc::new(RefCell::new(epilogue)),
                 empty_string: Rc::new(RefCell::new(empty_string)),
+                boundary_failure,
+                case_insensitive_boundary: false,
+                boundary_received: vec![],
 
                 state: MultipartParserState::BoundaryFirst,
                 headers: None,
@@ -201,403 +540,3272 @@ impl <T>MultipartParser<T>
                 process_content: None,
                 compare_pos: 0,
                 content_start: 0,
-                content_end: 0,
                 content_size: 0,
                 content_size_max: None,
                 buf_pos: 0,
                 unprocessed,
+                unreceived,
+                ordered,
+                ordered_pos: 0,
                 on_error: OnError::ContinueWithError,
+                default_on_error: OnError::ContinueWithError,
                 error_fired: false,
+                defer_field_errors: false,
+                field_errors: vec![],
+                fired_errors: vec![],
+                fail_fast: false,
+                decode_param_names: false,
+                buffer_text_fields: true,
+                text_field: None,
+                text_field_max_size: TEXT_FIELD_MAX_SIZE,
+                parts_seen: 0,
+                strict: false,
+                routing: Routing::Name,
+                min_parts: 0,
+                cleanup_on_drop: false,
+                parse_epilogue_headers: false,
+                epilogue_data: None,
+                epilogue_headers: None,
+                collect_timings: false,
+                part_started: None,
+                part_timings: vec![],
+                flush_incomplete_final_part: false,
+                max_part_duration: None,
+                part_deadline: None,
+                content_type_routes: vec![],
+                spill_dir: None,
+                spill_threshold: SPILL_THRESHOLD,
+                default_parts: vec![],
+                validators: HashMap::new(),
+                validated_data: None,
+                validated_data_max: None,
+                validated_data_exceeded: false,
+                max_in_flight_memory: None,
+                in_flight_memory: 0,
+                max_total_size: None,
+                total_size: 0,
+                max_parts: None,
+                max_header_refeed: MAX_HEADER_REFEED,
+                header_queue: vec![],
+                #[cfg(feature = "content-md5")]
+                verify_content_md5: false,
+                #[cfg(feature = "content-md5")]
+                content_md5: None,
 
                 target: target.clone()
             }
     }
 
-
-    /// Change internal state to `Header`
-    fn to_header(&mut self) -> ()
+    /// Collect conversion errors of all fields and pass them to `MultipartParserTarget::error`
+    /// once at end of data as `MultipartParseError::FieldErrors`, instead of immediately.
+    pub fn defer_field_errors(&mut self, defer: bool) -> &mut Self
     {
-        self.compare_pos = 0;
-        self.headers = None;
-        self.state = MultipartParserState::Header;
+        self.defer_field_errors = defer;
+        self
     }
 
-    /// Change internal state to `Header` (from `PostHeader`)
-    fn to_header_continue(&mut self) -> ()
+    /// Abort parsing on first error: instead of any `MultipartParserTarget::error` call `write` returns
+    /// `Err(std::io::Error)` with kind `InvalidData`, and all next data ignored.
+    pub fn fail_fast(&mut self, fail_fast: bool) -> &mut Self
     {
-        self.compare_pos = 0;
-        self.state = MultipartParserState::Header;
+        self.fail_fast = fail_fast;
+        self
     }
 
-    /// Change internal state to `PostHeader`
-    fn to_post_header(&mut self) -> ()
+    /// Action on error of current field (ex.: `SizeLimit`), default `OnError::ContinueWithError` - ask `MultipartParserTarget::error`.
+    /// Other value is applied without `MultipartParserTarget::error` call, ex.: `OnError::Skip` for "best effort" parsing, which never aborts.
+    /// Conversion errors of derive fields are not passed to `MultipartParserTarget::error` too (field value is not changed).
+    /// Errors at end of data (`RequiredMissing`, `FieldErrors`) are reported anyway.
+    pub fn set_default_on_error(&mut self, on_error: OnError) -> &mut Self
     {
-        self.compare_pos = 0;
-        self.headers_builder.flush();
-        self.state = MultipartParserState::PostHeader;
+        self.default_on_error = on_error;
+        self
     }
 
+    /// Percent-decode `name` and `filename` parameters of `Content-Disposition` (ex.: `user%5Bid%5D` to `user[id]`),
+    /// before selecting processor for part. Default `false`.
+    pub fn decode_param_names(&mut self, decode: bool) -> &mut Self
+    {
+        self.decode_param_names = decode;
+        self
+    }
 
-    /// Change internal state to `Content`
-    fn to_content(&mut self) -> ()
+    /// Buffer text parts (not files), for which `MultipartParserTarget::content_parser` returned `None`, and pass them
+    /// to `MultipartParserTarget::on_text_field`, up to `text_field_max_size` bytes. Default `true`, `false` - such parts are dropped.
+    pub fn buffer_text_fields(&mut self, buffer: bool) -> &mut Self
     {
-        self.content_start = self.buf_pos+1;
-        self.content_size = 0;
-        self.on_error = OnError::ContinueWithError;
-        self.error_fired = false;
+        self.buffer_text_fields = buffer;
+        self
+    }
 
+    /// Maximum size of text part, buffered for `MultipartParserTarget::on_text_field`, default `TEXT_FIELD_MAX_SIZE`.
+    /// Greater parts fire `MultipartParseError::SizeLimit` and are not passed to `on_text_field`.
+    pub fn text_field_max_size(&mut self, max_size: usize) -> &mut Self
+    {
+        self.text_field_max_size = max_size;
+        self
+    }
 
-        let headers = self.headers_builder.build();
+    /// How part is identified, default `Routing::Name`. For `Routing::ContentId` and `Routing::Index`
+    /// `Content-ID` or index of part (as string) replaces `name` in headers, passed to processor and
+    /// `MultipartParserTarget::content_parser`, so structs of `gnitive-multipart-derive` can be used for
+    /// `multipart/related` and `multipart/mixed` data (ex.: `#[multipart(name="root")]` for `Content-ID: <root>`).
+    pub fn routing(&mut self, routing: Routing) -> &mut Self
+    {
+        self.routing = routing;
+        self
+    }
 
-        {
-            let mut target = self.target.borrow_mut();
-            self.process_content = target.content_parser_generated(&self.target.clone(), &headers);
+    /// Maximum count of parameters, parsed per header line, default `MAX_HEADER_PARAMS`.
+    /// Rest of parameters (ex.: `Content-Disposition` with thousands of `;`-separated parameters) are ignored.
+    pub fn max_header_params(&mut self, max_params: usize) -> &mut Self
+    {
+        self.headers_builder.max_params(max_params);
+        self
+    }
 
-            self.content_size_max = match &self.process_content
-                {
-                    &Some(ref process_content) => process_content.borrow().get_process_params().max_size.clone(),
-                    &None => None
-                };
+    /// Maximum count of symbols, processed in headers per received symbol, default `MAX_HEADER_REFEED`.
+    /// Symbols of partial `\r\n`, which turned out to be header data, are processed again with next symbol.
+    /// If exceeded, `MultipartParseError::HeaderRefeedLimit` is fired (not fired in `fail_fast` mode) and parsing stopped:
+    /// `write` returns `Err` with `ErrorKind::InvalidData`.
+    pub fn max_header_refeed(&mut self, max_refeed: usize) -> &mut Self
+    {
+        self.max_header_refeed = max_refeed;
+        self
+    }
 
-            {
-                let name = &headers.get_name();
-                if let &Some(ref name) = name
-                    {
-                        self.unprocessed.remove_item(&name);
-                    }
-            }
+    /// Strict mode: report malformed, but parseable data by `MultipartParserTarget::error` (or `Err` in `fail_fast` mode),
+    /// ex.: `MultipartParseError::DuplicateContentType`, `MultipartParseError::InvalidParameter`. Default `false` - such data parsed silently (first `Content-Type` is used).
+    pub fn strict(&mut self, strict: bool) -> &mut Self
+    {
+        self.strict = strict;
+        self
+    }
 
-            self.headers = Some(headers);
-        }
+    /// Minimum count of parts (ex.: at least one file for upload form), default `0`.
+    /// If less parts received, `MultipartParseError::TooFewParts` is fired at end of data (final boundary
+    /// or `flush` with `flush_incomplete_final_part`), before `RequiredMissing` (or `Err` is returned in `fail_fast` mode).
+    pub fn set_min_parts(&mut self, min_parts: usize) -> &mut Self
+    {
+        self.min_parts = min_parts;
+        self
+    }
 
-        self.processor_open();
+    /// Handle connection drop in the middle of data. If `MultipartParser` is dropped (or `into_target` is called) before end of data:
+    ///
+    /// * `ProcessContent::cleanup` is called for processor of current (partial) field, ex.: `FileProcessor` removes partial file
+    /// * `MultipartParserTarget::truncated` is called
+    /// * errors (`RequiredMissing`, deferred `FieldErrors`) are fired and `MultipartParserTarget::finish` is called, as at end of data
+    ///
+    /// Default `false` - nothing is called on `Drop`.
+    ///
+    /// ```rust,ignore
+    /// let mut multipart_parser = MultipartParser::new_from_str(boundary, &target);
+    /// multipart_parser.cleanup_on_drop(true);
+    /// io::copy(&mut connection, &mut multipart_parser)?; // connection dropped - partial files removed
+    /// ```
+    pub fn cleanup_on_drop(&mut self, cleanup: bool) -> &mut Self
+    {
+        self.cleanup_on_drop = cleanup;
+        self
+    }
 
-        self.compare_pos = 0;
-        self.state = MultipartParserState::Content;
+    /// Parse header block after final boundary (trailers, ex.: signature of data), see `epilogue_headers`.
+    /// Default `false` - all data after final boundary is ignored.
+    ///
+    /// ```text
+    /// --<boundary>--\r\n
+    /// X-Signature: abc\r\n
+    /// \r\n
+    /// ```
+    pub fn parse_epilogue_headers(&mut self, parse: bool) -> &mut Self
+    {
+        self.parse_epilogue_headers = parse;
+        self
     }
 
-    /// Change internal state to `PostBoundary`
-    fn to_post_boundary(&mut self) -> ()
+    /// Compute MD5 of data of parts with `Content-MD5` header (RFC 1864, base64 of digest) and compare it before
+    /// `ProcessContent::flush`, on mismatch fire `MultipartParseError::ChecksumMismatch` (or return `Err` in `fail_fast` mode).
+    /// Default `false`. Feature `content-md5`.
+    #[cfg(feature = "content-md5")]
+    pub fn verify_content_md5(&mut self, verify: bool) -> &mut Self
     {
-        self.content_start = 0;
-        self.compare_pos = 0;
-        self.state = MultipartParserState::PostBoundary;
+        self.verify_content_md5 = verify;
+        self
     }
 
-    /// Change internal state to `Finished`
-    fn to_finished(&mut self) -> ()
+    /// Record wall-clock duration of each part, from end of its headers (`ProcessContent::open`) to `ProcessContent::flush` completed,
+    /// see `part_timings`. Useful to find, whether slow processor (ex.: disk-bound) or network is bottleneck. Default `false`.
+    pub fn collect_timings(&mut self, collect: bool) -> &mut Self
     {
-        if !self.unprocessed.is_empty()
-            {
-                // All data processed - result of `error` can be ignored.
-                match self.target.borrow_mut().error( &MultipartParseError::RequiredMissing(&self.unprocessed) )
-                    {
-                        Ok(_) => (),
-                        Err(_) => ()
-                    }
-            }
-        self.target.borrow_mut().finish();
+        self.collect_timings = collect;
+        self
+    }
 
-        self.state = MultipartParserState::Finished;
+    /// Accept data without final boundary (ex.: truncated stream): if `flush` is called in the middle of last part content,
+    /// `ProcessContent::flush` is called for this part, then end of data is processed as for final boundary
+    /// (`RequiredMissing`, `MultipartParserTarget::finish`). Bytes of possible boundary at end of data (ex.: `\r\n`) are dropped.
+    /// Empty data is processed as data without parts (ex.: `TooFewParts` of `set_min_parts`).
+    /// Default `false` - last part is not delivered.
+    pub fn flush_incomplete_final_part(&mut self, flush: bool) -> &mut Self
+    {
+        self.flush_incomplete_final_part = flush;
+        self
     }
 
+    /// Maximum wall-clock duration of part, from end of its headers (best-effort guard against slow processor).
+    /// Checked at start of each `write` call, so processor is never interrupted: if exceeded, `MultipartParseError::PartTimeout`
+    /// is fired (or `Err` returned in `fail_fast` mode), `ProcessContent::abort` is called and rest of part is skipped.
+    /// Default `None` - unlimited.
+    pub fn max_part_duration(&mut self, max_part_duration: Option<Duration>) -> &mut Self
+    {
+        self.max_part_duration = max_part_duration;
+        self
+    }
 
+    /// Select processor for part by its `Content-Type`, if `MultipartParserTarget::content_parser` returned `None` for it
+    /// (ex.: not declared field). `pattern` is MIME type (`image/png`), type with any subtype (`image/*`) or any type (`*/*`),
+    /// ignoring case. Patterns are checked in order of adding, first matched `factory` creates processor.
+    ///
+    /// ```rust,ignore
+    /// multipart_parser.route_by_content_type("image/*", Box::new(|headers: &Headers| -> Rc<RefCell<ProcessContent>> {
+    ///     Rc::new(RefCell::new(FileProcessor::new(ProcessParams::new(headers.get_name().cloned().unwrap_or_default(), None), dir.clone())))
+    /// }));
+    /// ```
+    pub fn route_by_content_type(&mut self, pattern: &str, factory: Box<Fn(&Headers) -> Rc<RefCell<ProcessContent>>>) -> &mut Self
+    {
+        self.content_type_routes.push((pattern.trim().to_lowercase(), factory));
+        self
+    }
 
-    /// Compare `boundary[compare_pos]` and `c`
-    /// Return (`boundary[compare_pos]==c`, `compare_pos+1 = boundary.len()`)
-    fn compare (&self, c: u8, boundary: &Rc<RefCell<Vec<u8>>>) -> (bool, bool)
+    /// Store parts without processor (not selected by `MultipartParserTarget::content_parser` or `route_by_content_type`,
+    /// not buffered by `buffer_text_fields`) by `HybridProcessor`: part up to `spill_threshold` bytes stays in memory,
+    /// larger part is spilled to file in `dir`. Processors are available by `default_parts`.
+    /// Default `None` - such parts are skipped.
+    pub fn spill_by_default(&mut self, dir: Option<PathBuf>) -> &mut Self
     {
-        self.compare_at(c, boundary, self.compare_pos)
+        self.spill_dir = dir;
+        self
     }
 
-    /// Compare `boundary[pos]` and `c`
-    /// Return (`boundary[pos]==c`, `pos+1 = boundary.len()`)
-    fn compare_at (&self, c: u8, boundary: &Rc<RefCell<Vec<u8>>>, pos: usize) -> (bool, bool)
+    /// Maximum size of part in memory for `spill_by_default`, default `SPILL_THRESHOLD`
+    pub fn spill_threshold(&mut self, spill_threshold: usize) -> &mut Self
     {
-        let vec = boundary.borrow();
-        if c == vec[pos]
-            {
-                (true, pos +1 == vec.len())
-            }
-            else
-            {
-                (false, false)
-            }
+        self.spill_threshold = spill_threshold;
+        self
     }
 
-    /// Read boundary from stream, switch to `Header` when boundary completed
+    /// Validate complete data of field `name` before `ProcessContent::flush` (or `MultipartParserTarget::on_text_field`),
+    /// ex.: custom format, checksum. Data of field is buffered for validator (counted by `set_max_in_flight_memory`).
+    /// On `Err(message)` fire `MultipartParseError::ValidationFailed` (or return `Err` in `fail_fast` mode), `OnError::Skip` skips `flush`.
+    /// Data greater than `max_size` of field (`text_field_max_size` for part without processor) is not buffered,
+    /// validation fails without call of validator.
     ///
-    /// First boundary in multipart/form-data is different to other - without `\r\n` in head
-    fn process_boundary_first(&mut self, c: u8) ->()
+    /// ```rust,ignore
+    /// multipart_parser.add_validator("code", Box::new(|data: &[u8]| match data.len() == 6 {
+    ///     true => Ok(()),
+    ///     false => Err("6 symbols expected".to_string())
+    /// }));
+    /// ```
+    pub fn add_validator(&mut self, name: &str, validator: Box<Fn(&[u8]) -> Result<(), String>>) -> &mut Self
     {
-        let (sym_equal, boundary_equal) = self.compare(c, &self.boundary_first);
-        if boundary_equal
-            {
-                self.to_header();
-                return;
-            }
+        self.validators.insert(name.to_string(), validator);
+        self
+    }
 
-        if !sym_equal
-            {
-                panic!("Cannot parse first boundary, invalid symbol '{}' at position {}", c, &self.compare_pos);
-            }
-        self.compare_pos += 1;
+    /// Maximum sum of data of all fields, buffered in memory (`ProcessContent::buffered_size` of processors, text parts
+    /// for `MultipartParserTarget::on_text_field`), ex.: many fields, each just under its `max_size`. Data of completed field is counted
+    /// up to end of data, as it is usually kept by target (converted value). If exceeded, `MultipartParseError::MemoryLimit` is fired
+    /// (or `Err` returned in `fail_fast` mode), `ProcessContent::abort` is called and rest of part is skipped.
+    /// Default `None` - unlimited.
+    pub fn set_max_in_flight_memory(&mut self, max_memory: Option<usize>) -> &mut Self
+    {
+        self.max_in_flight_memory = max_memory;
+        self
     }
 
-    /// Read header from stream, switch to `PostHeader` when `\r\n` readed
-    fn process_header(&mut self, c: u8) ->()
+    /// Maximum size of whole multipart data (sum of all `write` calls), ex.: many small parts, each under its `max_size`.
+    /// If exceeded, `MultipartParseError::TotalSizeLimit` is fired (not fired in `fail_fast` mode), `ProcessContent::abort`
+    /// is called for opened processor and parsing stopped: `write` returns `Err` with `ErrorKind::InvalidData`, data of this
+    /// and next calls is not processed. Default `None` - unlimited.
+    pub fn set_max_total_size(&mut self, max_total_size: Option<usize>) -> &mut Self
     {
-        let (sym_equal, boundary_equal) = self.compare(c, &self.empty_string);
-        if boundary_equal
-            {
-                self.to_post_header();
-                return;
-            }
+        self.max_total_size = max_total_size;
+        self
+    }
 
-        if sym_equal
-            {
+    /// Maximum count of parts, ex.: tens of thousands of tiny parts. If next part received, `MultipartParseError::TooManyParts`
+    /// is fired (or `Err` returned in `fail_fast` mode) before selecting its processor, and parsing is finished as on final boundary:
+    /// rest of data is ignored. Default `None` - unlimited.
+    pub fn set_max_parts(&mut self, max_parts: Option<usize>) -> &mut Self
+    {
+        self.max_parts = max_parts;
+        self
+    }
+
+    /// Match boundary ignoring ASCII case (ex.: boundary case is altered by buggy proxy): declared boundary and data are lowercased
+    /// on compare. Content, which turned out not to be boundary, is delivered as received.
+    /// Default `false` - boundary is compared strictly, as spec requires.
+    pub fn case_insensitive_boundary(&mut self, case_insensitive: bool) -> &mut Self
+    {
+        self.case_insensitive_boundary = case_insensitive;
+        let boundary_middle = match case_insensitive
+            {
+                true => self.boundary_middle.borrow().to_ascii_lowercase(),
+                false => self.boundary_middle.borrow().clone()
+            };
+        self.boundary_failure = MultipartParser::<T>::failure_table(&boundary_middle);
+        self
+    }
+
+    /// Name and duration of each completed part (if `collect_timings`), in order of receiving
+    pub fn part_timings(&self) -> Vec<(String, Duration)>
+    {
+        self.part_timings.clone()
+    }
+
+    /// Headers after final boundary (if `parse_epilogue_headers`), `None` if there are no headers
+    pub fn epilogue_headers(&self) -> Option<&Headers>
+    {
+        self.epilogue_headers.as_ref()
+    }
+
+    /// Snapshot of position in data (ex.: to continue parsing of huge upload by another parser later), call it between `write` calls.
+    /// Processor of current part (with its in-flight buffers) is not included and must be saved separately,
+    /// text part without processor (`MultipartParserTarget::on_text_field`), timings and `Content-MD5` of current part are lost.
+    pub fn export_state(&self) -> ParserState
+    {
+        let (pending_header_lines, pending_header_line) = self.headers_builder.export();
+        ParserState
+            {
+                state: self.state.clone(),
+                compare_pos: self.compare_pos,
+                content_size: self.content_size,
+                unprocessed: self.unprocessed.clone(),
+                unreceived: self.unreceived.clone(),
+                ordered_pos: self.ordered_pos,
+                parts_seen: self.parts_seen,
+                headers: match self.headers
+                    {
+                        Some(ref headers) => headers.to_lines(),
+                        None => vec![]
+                    },
+                pending_header_lines,
+                pending_header_line,
+                boundary_received: self.boundary_received.clone()
+            }
+    }
+
+    /// Restore position in data from `export_state` (usually in new parser with same boundary and settings).
+    /// `process_content` - processor of current part (in `Content` state) with data, written before snapshot,
+    /// it is not opened again. If `None`, rest of current part is dropped.
+    pub fn import_state(&mut self, state: ParserState, process_content: Option<Rc<RefCell<ProcessContent>>>) -> ()
+    {
+        self.headers_builder.import(state.pending_header_lines, state.pending_header_line);
+        self.headers = match state.state
+            {
+                MultipartParserState::Content => Some(Headers::new(&state.headers)),
+                _ => None
+            };
+        self.content_size_max = match &process_content
+            {
+                &Some(ref process_content) => process_content.borrow().get_process_params().max_size.clone(),
+                &None => None
+            };
+        self.on_error = match process_content
+            {
+                Some(_) => OnError::ContinueWithError,
+                None => OnError::Skip
+            };
+        self.process_content = process_content;
+        self.text_field = None;
+        self.error_fired = false;
+
+        self.state = state.state;
+        self.compare_pos = state.compare_pos;
+        self.boundary_received = state.boundary_received;
+        self.content_size = state.content_size;
+        self.unprocessed = state.unprocessed;
+        self.unreceived = state.unreceived;
+        self.ordered_pos = state.ordered_pos;
+        self.parts_seen = state.parts_seen;
+    }
+
+    /// Declared optional (not `required`) fields, not received yet - after all data written, list of fields,
+    /// absent in form. Useful for development, to catch field name mismatches between client and server.
+    pub fn unreceived_optional(&self) -> Vec<String>
+    {
+        let required = self.target.borrow().get_all_required();
+        self.unreceived
+            .iter()
+            .filter(|name| !required.contains(name))
+            .cloned()
+            .collect()
+    }
+
+    /// Current state, for tests and debugging
+    pub fn state(&self) -> &MultipartParserState
+    {
+        &self.state
+    }
+
+    /// Count of parts, which headers are read (including current part)
+    pub fn parts_seen(&self) -> usize
+    {
+        self.parts_seen
+    }
+
+    /// Names (empty for part without name) and processors of parts, stored by `spill_by_default`, in order of parts.
+    /// Data of part is available by `HybridProcessor::bytes` or `HybridProcessor::path` after its end.
+    pub fn default_parts(&self) -> &Vec<(String, Rc<RefCell<HybridProcessor>>)>
+    {
+        &self.default_parts
+    }
+
+    /// Required fields, not received yet
+    pub fn pending_required(&self) -> &Vec<String>
+    {
+        &self.unprocessed
+    }
+
+    /// Error returned from `write` in `fail_fast` mode
+    fn fail_fast_error(description: String) -> Error
+    {
+        Error::new(ErrorKind::InvalidData, description)
+    }
+
+    /// Stop parsing of malformed data, all next data ignored.
+    /// Return error for `write`.
+    fn invalid_data(&mut self, description: String) -> Error
+    {
+        self.state = MultipartParserState::Finished;
+        Error::new(ErrorKind::InvalidData, description)
+    }
+
+
+    /// Change internal state to `Header`
+    fn to_header(&mut self) -> ()
+    {
+        self.compare_pos = 0;
+        self.headers = None;
+        self.state = MultipartParserState::Header;
+    }
+
+    /// Change internal state to `Header` (from `PostHeader`)
+    fn to_header_continue(&mut self) -> ()
+    {
+        self.compare_pos = 0;
+        self.state = MultipartParserState::Header;
+    }
+
+    /// Change internal state to `PostHeader`
+    fn to_post_header(&mut self) -> ()
+    {
+        self.compare_pos = 0;
+        self.headers_builder.flush();
+        self.state = MultipartParserState::PostHeader;
+    }
+
+
+    /// Change internal state to `Content`
+    fn to_content(&mut self) -> Result<(), Error>
+    {
+        if self.collect_timings
+            {
+                self.part_started = Some(Instant::now());
+            }
+        self.part_deadline = self.max_part_duration.map(|max_part_duration| Instant::now() + max_part_duration);
+        self.content_start = self.buf_pos+1;
+        self.content_size = 0;
+        self.parts_seen += 1;
+        self.on_error = OnError::ContinueWithError;
+        self.error_fired = false;
+
+        if let Some(max_parts) = self.max_parts
+            {
+                if self.parts_seen > max_parts
+                    {
+                        return self.too_many_parts(max_parts);
+                    }
+            }
+
+        let duplicate_content_type = self.headers_builder.count("Content-Type") > 1;
+        let mut headers = self.headers_builder.build();
+        if self.decode_param_names
+            {
+                headers.decode_param_names();
+            }
+        // after decoding: percent-encoded NUL (`a%00b`) is stripped too
+        let nul_params = headers.strip_nul();
+        let headers = match self.routing
+            {
+                Routing::Name => headers,
+                Routing::ContentId => match headers.get_content_id().map(|content_id| content_id.to_string())
+                    {
+                        Some(content_id) => headers.with_name(&content_id),
+                        None => headers
+                    },
+                Routing::Index => headers.with_name(&(self.parts_seen - 1).to_string())
+            };
+
+        let nested_multipart = headers.get_mime_type().map(|mime_type| mime_type.to_lowercase().starts_with("multipart/")).unwrap_or(false);
+        let nested_skip = nested_multipart && match self.target.borrow_mut().on_nested_multipart(&headers)
+            {
+                OnError::Skip | OnError::SkipAndClose => true,
+                _ => false
+            };
+        if nested_skip
+            {
+                self.process_content = None;
+                self.content_size_max = None;
+                self.text_field = None;
+                self.headers = Some(headers);
+                self.on_error = OnError::Skip;
+                self.compare_pos = 0;
+                self.state = MultipartParserState::Content;
+                return Ok(());
+            }
+
+        {
+            let mut target = self.target.borrow_mut();
+            self.process_content = target.content_parser_generated(&self.target.clone(), &headers);
+            if self.process_content.is_none()
+                {
+                    self.process_content = self.route_content_type(&headers);
+                }
+
+            let text_field = self.buffer_text_fields && !headers.is_file() && headers.get_name().is_some();
+            if self.process_content.is_none() && !text_field
+                {
+                    if let Some(ref dir) = self.spill_dir
+                        {
+                            let name = headers.get_name().cloned().unwrap_or_default();
+                            let mut processor = HybridProcessor::new(ProcessParams::new(name.clone(), None), dir.clone());
+                            processor.spill_threshold(self.spill_threshold);
+                            let processor = Rc::new(RefCell::new(processor));
+                            self.default_parts.push((name, processor.clone()));
+                            self.process_content = Some(processor);
+                        }
+                }
+
+            self.content_size_max = match &self.process_content
+                {
+                    &Some(ref process_content) => process_content.borrow().get_process_params().max_size.clone(),
+                    &None => None
+                };
+
+            if let Some(ref process_content) = self.process_content
+                {
+                    if let Some(hooks) = process_content.borrow_mut().hooks()
+                        {
+                            hooks.set_defer_errors(self.defer_field_errors || self.fail_fast);
+                            hooks.set_default_on_error(self.default_on_error.clone());
+                        }
+                }
+
+            self.text_field = match self.process_content
+                {
+                    None if text_field => Some(vec![]),
+                    _ => None
+                };
+
+            // processor may be selected ignoring case of name (ex.: derive `case_insensitive`) - declared name is in `ProcessParams`
+            if let Some(ref process_content) = self.process_content
+                {
+                    let declared = process_content.borrow().get_process_params().name.clone();
+                    if let Some(i) = self.unprocessed.iter().position(|n| *n == declared)
+                        {
+                            self.unprocessed.remove(i);
+                        }
+                    self.unreceived.retain(|name| *name != declared);
+                }
+
+            {
+                let name = &headers.get_name();
+                if let &Some(ref name) = name
+                    {
+                        if let Some(i) = self.unprocessed.iter().position(|n| n == *name)
+                            {
+                                self.unprocessed.remove(i);
+                            }
+                        // `Vec<NestedStruct>` field `<declared>` receives parts `<declared>[<index>][<field>]`
+                        self.unreceived.retain(|declared| declared != *name && !name.starts_with(&format!("{}[", declared)));
+                    }
+            }
+
+            self.headers = Some(headers);
+        }
+
+        self.validated_data = match self.headers.as_ref().and_then(|headers| headers.get_name())
+            {
+                Some(name) if self.validators.contains_key(name) => Some(vec![]),
+                _ => None
+            };
+        self.validated_data_max = match self.process_content
+            {
+                Some(ref process_content) => process_content.borrow().get_process_params().max_size,
+                None => Some(self.text_field_max_size)
+            };
+        self.validated_data_exceeded = false;
+
+        #[cfg(feature = "content-md5")]
+        {
+            self.content_md5 = match self.headers.as_ref().and_then(|headers| headers.get_header("Content-MD5"))
+                {
+                    Some(header) if self.verify_content_md5 =>
+                        {
+                            // invalid base64 - empty digest, never equal to computed
+                            let mut expected: Vec<u8> = vec![];
+                            let mut decoder = Decoder::new(Cte::Base64);
+                            if decoder.update(header.value.trim().as_bytes(), &mut expected).and_then(|_| decoder.finish(&mut expected)).is_err()
+                                {
+                                    expected.clear();
+                                }
+                            Some((Md5Context::new(), expected))
+                        },
+                    _ => None
+                };
+        }
+
+        self.compare_pos = 0;
+        self.state = MultipartParserState::Content;
+
+        if self.strict && duplicate_content_type
+            {
+                let name = self.headers.as_ref().and_then(|headers| headers.get_name().cloned()).unwrap_or_default();
+                if self.fail_fast
+                    {
+                        self.state = MultipartParserState::Finished;
+                        return Err(MultipartParser::<T>::fail_fast_error(format!("Duplicate Content-Type for field '{}'", name)));
+                    }
+                self.on_error = self.fire_error(MultipartParseError::DuplicateContentType(name))?;
+                self.on_error = MultipartParser::<T>::on_error_before_open(self.on_error.clone());
+                if self.on_error == OnError::Skip
+                    {
+                        self.text_field = None;
+                        return Ok(());
+                    }
+            }
+
+        if self.strict && !nul_params.is_empty()
+            {
+                if self.fail_fast
+                    {
+                        self.state = MultipartParserState::Finished;
+                        return Err(MultipartParser::<T>::fail_fast_error(format!("NUL byte in parameter '{}'", nul_params[0])));
+                    }
+                self.on_error = self.fire_error(MultipartParseError::InvalidParameter(nul_params[0].clone(), "NUL byte".to_string()))?;
+                self.on_error = MultipartParser::<T>::on_error_before_open(self.on_error.clone());
+                if self.on_error == OnError::Skip
+                    {
+                        self.text_field = None;
+                        return Ok(());
+                    }
+            }
+
+        if let Some((expected, found)) = self.check_order()
+            {
+                if self.fail_fast
+                    {
+                        self.state = MultipartParserState::Finished;
+                        return Err(MultipartParser::<T>::fail_fast_error(format!("Field '{}' out of order, expected '{}'", found, expected)));
+                    }
+                self.on_error = self.fire_error(MultipartParseError::OutOfOrder { expected, found })?;
+                self.on_error = MultipartParser::<T>::on_error_before_open(self.on_error.clone());
+                if self.on_error == OnError::Skip
+                    {
+                        self.text_field = None;
+                        return Ok(());
+                    }
+            }
+
+        // declared `Content-Length` exceeds `max_size` - skip part without reading data
+        let content_length = self.headers.as_ref().and_then(|headers| headers.get_content_length());
+        if let (Some(max_size), Some(content_length)) = (self.content_size_max, content_length)
+            {
+                if content_length > max_size
+                    {
+                        let name = match self.process_content
+                            {
+                                Some(ref process_content) => process_content.borrow().get_process_params().name.clone(),
+                                None => String::new()
+                            };
+                        if self.fail_fast
+                            {
+                                self.state = MultipartParserState::Finished;
+                                return Err(MultipartParser::<T>::fail_fast_error(format!("Size limit {} exceeded for field '{}'", max_size, name)));
+                            }
+                        let on_error = self.fire_error(MultipartParseError::SizeLimit(name, max_size))?;
+                        if MultipartParser::<T>::on_error_before_open(on_error) == OnError::Skip
+                            {
+                                self.on_error = OnError::Skip;
+                                self.text_field = None;
+                                return Ok(());
+                            }
+                        // handler continues - part is read, size is not checked more (as after `SizeLimit` in `write`)
+                        self.content_size_max = None;
+                    }
+            }
+
+        self.processor_open();
+
+        // processor rejected field by its headers (ex.: derive `extensions`)
+        let open_error = match self.process_content
+            {
+                Some(ref process_content) => process_content.borrow_mut().hooks().and_then(|hooks| hooks.take_open_error()),
+                None => None
+            };
+        if let Some(error) = open_error
+            {
+                if self.fail_fast
+                    {
+                        let name = self.process_content.as_ref().map(|process_content| process_content.borrow().get_process_params().name.clone()).unwrap_or_default();
+                        self.state = MultipartParserState::Finished;
+                        return Err(MultipartParser::<T>::fail_fast_error(format!("Field '{}' rejected by headers", name)));
+                    }
+                match self.fire_error(error)?
+                    {
+                        OnError::Skip => self.on_error = OnError::Skip,
+                        OnError::SkipAndClose => self.abort_processor()?,
+                        _ => ()
+                    }
+            }
+        Ok(())
+    }
+
+    /// Create processor by first pattern of `content_type_routes`, matched `Content-Type` of part
+    fn route_content_type(&self, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        let mime_type = headers.get_mime_type()?.trim().to_lowercase();
+        let mime_type_prefix = mime_type.split('/').next().unwrap_or("").to_string() + "/*";
+        self.content_type_routes
+            .iter()
+            .find(|&&(ref pattern, _)| *pattern == mime_type || *pattern == mime_type_prefix || pattern == "*/*")
+            .map(|&(_, ref factory)| factory(headers))
+    }
+
+    /// Get action on `error` of current part: `default_on_error` or result of `MultipartParserTarget::error`.
+    /// `error` is stored for `MultipartParserTarget::on_errors`.
+    fn fire_error(&mut self, error: MultipartParseError<'static>) -> Result<OnError, Error>
+    {
+        let on_error = match self.default_on_error
+            {
+                OnError::ContinueWithError => self.target.borrow_mut().error( &error )?,
+                ref default_on_error => default_on_error.clone()
+            };
+        self.fired_errors.push(error);
+        Ok(on_error)
+    }
+
+    /// Processor is not opened yet - nothing to close, `SkipAndClose` is `Skip`
+    fn on_error_before_open(on_error: OnError) -> OnError
+    {
+        match on_error
+            {
+                OnError::SkipAndClose => OnError::Skip,
+                on_error => on_error
+            }
+    }
+
+    /// Skip rest of current field and call `ProcessContent::abort` of its processor (see `OnError::SkipAndClose`)
+    fn abort_processor(&mut self) -> Result<(), Error>
+    {
+        self.on_error = OnError::Skip;
+        if let (&Some(ref process_content), &Some(ref headers)) = (&self.process_content, &self.headers)
+            {
+                process_content.borrow_mut().abort(headers)?;
+            }
+        Ok(())
+    }
+
+    /// Stop parsing, if size of all data exceeds `max_total_size`
+    fn check_total_size(&mut self, size: usize) -> Result<(), Error>
+    {
+        let already_exceeded = self.max_total_size.map(|max_total_size| self.total_size > max_total_size).unwrap_or(false);
+        self.total_size = self.total_size.saturating_add(size);
+        let max_total_size = match self.max_total_size
+            {
+                Some(max_total_size) if self.total_size > max_total_size => max_total_size,
+                _ => return Ok(())
+            };
+
+        let description = format!("Total size limit {} exceeded", max_total_size);
+        if already_exceeded || self.fail_fast
+            {
+                return Err(self.invalid_data(description));
+            }
+        // parsing cannot be continued - `OnError` is ignored
+        if self.default_on_error == OnError::ContinueWithError
+            {
+                self.target.borrow_mut().error( &MultipartParseError::TotalSizeLimit(max_total_size) )?;
+            }
+        self.fired_errors.push(MultipartParseError::TotalSizeLimit(max_total_size));
+        self.text_field = None;
+        if self.state == MultipartParserState::Content && self.on_error != OnError::Skip
+            {
+                self.abort_processor()?;
+            }
+        Err(self.invalid_data(description))
+    }
+
+    /// Skip rest of current part, if it is processed longer than `max_part_duration`
+    fn check_part_duration(&mut self) -> Result<(), Error>
+    {
+        let expired = match self.part_deadline
+            {
+                Some(part_deadline) => self.state == MultipartParserState::Content && self.on_error != OnError::Skip && Instant::now() >= part_deadline,
+                None => false
+            };
+        if !expired
+            {
+                return Ok(());
+            }
+        self.part_deadline = None;
+
+        let name = self.headers.as_ref().and_then(|headers| headers.get_name().cloned()).unwrap_or_default();
+        if self.fail_fast
+            {
+                self.state = MultipartParserState::Finished;
+                return Err(MultipartParser::<T>::fail_fast_error(format!("Timeout of field '{}'", name)));
+            }
+        // part is skipped anyway - `OnError` can be ignored
+        if self.default_on_error == OnError::ContinueWithError
+            {
+                self.target.borrow_mut().error( &MultipartParseError::PartTimeout(name.clone()) )?;
+            }
+        self.fired_errors.push(MultipartParseError::PartTimeout(name));
+        self.text_field = None;
+        self.abort_processor()
+    }
+
+    /// Check order of current part (see `ordered`), return `(expected, found)` names if part is out of order
+    fn check_order(&mut self) -> Option<(String, String)>
+    {
+        if self.ordered.is_empty()
+            {
+                return None;
+            }
+        // declared name of processor, part name may differ by case
+        let name = match self.process_content
+            {
+                Some(ref process_content) => process_content.borrow().get_process_params().name.clone(),
+                None => self.headers.as_ref().and_then(|headers| headers.get_name().cloned())?
+            };
+        let pos = self.ordered.iter().position(|declared| *declared == name)?;
+
+        // repeated part of last received field is allowed
+        if pos + 1 >= self.ordered_pos
+            {
+                self.ordered_pos = pos + 1;
+                return None;
+            }
+        Some((self.ordered.get(self.ordered_pos).cloned().unwrap_or_default(), name))
+    }
+
+    /// Change internal state to `PostBoundary`
+    fn to_post_boundary(&mut self) -> ()
+    {
+        self.content_start = 0;
+        self.compare_pos = 0;
+        self.state = MultipartParserState::PostBoundary;
+    }
+
+    /// Change internal state to `Finished`
+    fn to_finished(&mut self) -> Result<(), Error>
+    {
+        if self.fail_fast && !self.unprocessed.is_empty()
+            {
+                self.state = MultipartParserState::Finished;
+                return Err(MultipartParser::<T>::fail_fast_error(format!("Required fields missing: {}", self.unprocessed.join(", "))));
+            }
+
+        if self.parts_seen < self.min_parts
+            {
+                let (expected, received) = (self.min_parts, self.parts_seen);
+                if self.fail_fast
+                    {
+                        self.state = MultipartParserState::Finished;
+                        return Err(MultipartParser::<T>::fail_fast_error(format!("Too few parts: {} expected, {} received", expected, received)));
+                    }
+                // All data processed - result of `error` can be ignored.
+                match self.target.borrow_mut().error( &MultipartParseError::TooFewParts { expected, received } )
+                    {
+                        Ok(_) => (),
+                        Err(_) => ()
+                    }
+                self.fired_errors.push(MultipartParseError::TooFewParts { expected, received });
+            }
+
+        if !self.unprocessed.is_empty()
+            {
+                // All data processed - result of `error` can be ignored.
+                match self.target.borrow_mut().error( &MultipartParseError::RequiredMissing(&self.unprocessed) )
+                    {
+                        Ok(_) => (),
+                        Err(_) => ()
+                    }
+            }
+        if !self.field_errors.is_empty()
+            {
+                // All data processed - result of `error` can be ignored.
+                match self.target.borrow_mut().error( &MultipartParseError::FieldErrors(&self.field_errors) )
+                    {
+                        Ok(_) => (),
+                        Err(_) => ()
+                    }
+            }
+
+        let mut errors: Vec<MultipartParseError> = self.fired_errors.clone();
+        if !self.unprocessed.is_empty()
+            {
+                errors.push(MultipartParseError::RequiredMissing(&self.unprocessed));
+            }
+        if !self.field_errors.is_empty()
+            {
+                errors.push(MultipartParseError::FieldErrors(&self.field_errors));
+            }
+        if !errors.is_empty()
+            {
+                self.target.borrow_mut().on_errors(&errors);
+            }
+        self.target.borrow_mut().finish();
+
+        self.state = MultipartParserState::Finished;
+        if self.parse_epilogue_headers
+            {
+                self.epilogue_data = Some(vec![]);
+            }
+        Ok(())
+    }
+
+    /// Finish parsing on part after `max_parts`, rest of data is ignored
+    fn too_many_parts(&mut self, max_parts: usize) -> Result<(), Error>
+    {
+        if self.fail_fast
+            {
+                self.state = MultipartParserState::Finished;
+                return Err(MultipartParser::<T>::fail_fast_error(format!("Too many parts, limit {}", max_parts)));
+            }
+        // part is not processed - `OnError` can be ignored
+        if self.default_on_error == OnError::ContinueWithError
+            {
+                self.target.borrow_mut().error( &MultipartParseError::TooManyParts(max_parts) )?;
+            }
+        self.fired_errors.push(MultipartParseError::TooManyParts(max_parts));
+        self.to_finished()?;
+        // rest of data is not epilogue
+        self.epilogue_data = None;
+        Ok(())
+    }
+
+    /// Collect data after final boundary (`--<boundary>--`) and parse header block of it.
+    /// Data is collected up to empty line (end of header block), but not more than `text_field_max_size`.
+    fn epilogue_write(&mut self, data: &[u8]) -> ()
+    {
+        let block = match self.epilogue_data
+            {
+                Some(ref mut epilogue_data) =>
+                    {
+                        let size = ::std::cmp::min(data.len(), self.text_field_max_size.saturating_sub(epilogue_data.len()));
+                        epilogue_data.extend(&data[..size]);
+
+                        // `\r\n` after `--` ends final boundary line
+                        let block: &[u8] = match epilogue_data.starts_with(b"\r\n")
+                            {
+                                true => &epilogue_data[2..],
+                                false => &epilogue_data[..]
+                            };
+                        match block.windows(4).position(|window| window == b"\r\n\r\n")
+                            {
+                                Some(end) => Some((block[..end + 2].to_vec(), true)),
+                                None => Some((block.to_vec(), false))
+                            }
+                    },
+                None => None
+            };
+
+        if let Some((block, complete)) = block
+            {
+                let headers = Headers::parse(&block);
+                self.epilogue_headers = match headers.headers.is_empty()
+                    {
+                        true => None,
+                        false => Some(headers)
+                    };
+                if complete
+                    {
+                        self.epilogue_data = None;
+                    }
+            }
+    }
+
+
+
+    /// Compare `boundary[compare_pos]` and `c`
+    /// Return (`boundary[compare_pos]==c`, `compare_pos+1 = boundary.len()`)
+    fn compare (&self, c: u8, boundary: &Rc<RefCell<Vec<u8>>>) -> (bool, bool)
+    {
+        self.compare_at(c, boundary, self.compare_pos)
+    }
+
+    /// Compare `boundary[pos]` and `c`
+    /// Return (`boundary[pos]==c`, `pos+1 = boundary.len()`)
+    fn compare_at (&self, c: u8, boundary: &Rc<RefCell<Vec<u8>>>, pos: usize) -> (bool, bool)
+    {
+        let vec = boundary.borrow();
+        if self.symbol_equal(c, vec[pos])
+            {
+                (true, pos +1 == vec.len())
+            }
+            else
+            {
+                (false, false)
+            }
+    }
+
+    /// Compare symbol `c` of data and symbol `expected` of boundary (ignoring case with `case_insensitive_boundary`)
+    fn symbol_equal(&self, c: u8, expected: u8) -> bool
+    {
+        match self.case_insensitive_boundary
+            {
+                true => c.to_ascii_lowercase() == expected.to_ascii_lowercase(),
+                false => c == expected
+            }
+    }
+
+    /// Read boundary from stream, switch to `Header` when boundary completed
+    ///
+    /// First boundary in multipart/form-data is different to other - without `\r\n` in head
+    fn process_boundary_first(&mut self, c: u8) -> Result<(), Error>
+    {
+        let (sym_equal, boundary_equal) = self.compare(c, &self.boundary_first);
+        if boundary_equal
+            {
+                self.to_header();
+                return Ok(());
+            }
+
+        if !sym_equal
+            {
+                // first boundary is at start of data - position in boundary is offset in data;
+                // parsing cannot be continued - `OnError` is ignored
+                if self.default_on_error == OnError::ContinueWithError
+                    {
+                        self.target.borrow_mut().error( &MultipartParseError::MalformedBoundary(self.compare_pos) )?;
+                    }
+                self.fired_errors.push(MultipartParseError::MalformedBoundary(self.compare_pos));
+                let description = format!("Cannot parse first boundary, invalid symbol '{}' at position {}", c, &self.compare_pos);
+                return Err(self.invalid_data(description));
+            }
+        self.compare_pos += 1;
+        Ok(())
+    }
+
+    /// Read header from stream, switch to `PostHeader` when `\r\n` readed
+    ///
+    /// Symbols of `\r\n` head, which turned out to be header data, are re-fed by loop (not recursion).
+    /// Re-fed symbols are shorter than matched head (2 iterations for `\r\n`), loop is guarded by `max_header_refeed`.
+    fn process_header(&mut self, c: u8) -> Result<(), Error>
+    {
+        let tmp = self.empty_string.clone();
+        let empty_string: &Vec<u8> = &tmp.borrow();
+
+        self.header_queue.clear();
+        self.header_queue.push(c);
+        for _ in 0..self.max_header_refeed
+            {
+                let c = match self.header_queue.pop()
+                    {
+                        Some(c) => c,
+                        None => return Ok(())
+                    };
+
+                let (sym_equal, boundary_equal) = self.compare(c, &self.empty_string);
+                if boundary_equal
+                    {
+                        self.to_post_header();
+                        return Ok(());
+                    }
+
+                if sym_equal
+                    {
+                        self.compare_pos+=1;
+                    }
+                else if self.compare_pos > 0
+                    {
+                        // matched head of `\r\n` is header data: write first symbol, re-feed rest and `c`
+                        self.headers_builder.write(empty_string[0]);
+                        self.header_queue.push(c);
+                        self.header_queue.extend(empty_string[1..self.compare_pos].iter().rev());
+                        self.compare_pos = 0;
+                    }
+                else
+                    {
+                        self.headers_builder.write(c);
+                    }
+            }
+        if self.header_queue.is_empty()
+            {
+                return Ok(());
+            }
+
+        // parsing cannot be continued - `OnError` is ignored
+        let max_refeed = self.max_header_refeed;
+        if !self.fail_fast
+            {
+                if self.default_on_error == OnError::ContinueWithError
+                    {
+                        self.target.borrow_mut().error( &MultipartParseError::HeaderRefeedLimit(max_refeed) )?;
+                    }
+                self.fired_errors.push(MultipartParseError::HeaderRefeedLimit(max_refeed));
+            }
+        let description = format!("Header re-feed limit {} exceeded", max_refeed);
+        Err(self.invalid_data(description))
+    }
+
+    /// Read post header from stream, switch to `Content` when `\r\n` readed (i.e. 2x empty string) or returns to `Header` state if other synbos readed
+    fn process_post_header(&mut self, c: u8) -> Result<(), Error>
+    {
+        let (sym_equal, boundary_equal) = self.compare(c, &self.empty_string);
+        if boundary_equal
+            {
+                return self.to_content();
+            }
+
+        if sym_equal
+            {
                 self.compare_pos+=1;
             }
-            else
+            else
+            {
+                // `\r\n` of previous header line already flushed - readed symbols are start of next header line
+                let tmp = &self.empty_string.clone();
+                let empty_string: &Vec<u8> = &tmp.borrow();
+
+                let compare_pos_last = self.compare_pos;
+                self.to_header_continue();
+                // `process_header` is not recursive - re-feed does not grow stack
+                for &symbol in empty_string[0..compare_pos_last].iter().chain(Some(c).iter())
+                    {
+                        self.process_header(symbol)?;
+                    }
+            }
+        Ok(())
+    }
+
+    /// Read content from stream, until `boundary_middle` sequence readed
+    ///
+    /// Content is written to processor by runs `buf[content_start..]`. Symbols, matched with `boundary_middle`,
+    /// are not written until mismatch (they may be part of boundary), only `compare_pos` (count of them) is stored.
+    fn process_content(&mut self, c: u8, buf: &[u8]) -> Result<(), Error>
+    {
+        let (sym_equal, boundary_equal) = self.compare(c, &self.boundary_middle);
+        if boundary_equal
+            {
+                self.processor_flush()?;
+                self.record_part_timing();
+                self.to_post_boundary();
+                return Ok(())
+            }
+
+        if sym_equal
+            {
+                if self.compare_pos == 0
+                    {
+                        // write content before possible boundary
+                        let from = self.content_start;
+                        let to = self.buf_pos;
+                        self.processor_write_from_to(buf, from, to)?;
+                    }
+                if self.case_insensitive_boundary
+                    {
+                        if self.compare_pos == 0
+                            {
+                                self.boundary_received.clear();
+                            }
+                        self.boundary_received.push(c);
+                    }
+                self.compare_pos += 1;
+            }
+            else
+            {
+                if self.compare_pos > 0
+                    {
+                        let matched = self.flow_content(c)?;
+                        self.content_start = if matched { self.buf_pos + 1 } else { self.buf_pos };
+                    }
+            }
+        Ok(())
+    }
+
+    /// Special case - part of `boundary_middle` readed from stream, but it is part of content body.
+    ///
+    /// `boundary_middle[0..compare_pos]` + `c` readed. Write to processor minimal head of them,
+    /// so rest is start of `boundary_middle` again, and update `compare_pos`.
+    /// Return `true` if `c` is part of (new) possible boundary, `false` if `c` is content.
+    ///
+    /// Next possible start is found by `boundary_failure` (KMP), so whole content is scanned in linear time.
+    fn flow_content(&mut self, c: u8) -> Result<bool, Error>
+    {
+        let clone = self.boundary_middle.clone();
+        let vec = clone.borrow();
+        let readed = self.compare_pos;
+
+        let mut matched = readed;
+        // `matched` strictly decreases - at most `readed` iterations
+        while matched > 0 && !self.symbol_equal(c, vec[matched])
+            {
+                matched = self.boundary_failure[matched - 1];
+            }
+        if self.symbol_equal(c, vec[matched])
+            {
+                matched += 1;
+            }
+
+        // with `case_insensitive_boundary` readed symbols may differ by case from boundary - write them as received
+        let mut received = ::std::mem::replace(&mut self.boundary_received, vec![]);
+        let from_received = self.case_insensitive_boundary && received.len() == readed;
+        if from_received
+            {
+                received.push(c);
+            }
+        let source: &[u8] = match from_received
+            {
+                true => received.as_ref(),
+                false => vec.as_ref()
+            };
+
+        if matched > 0
+            {
+                self.processor_write_from_to(source, 0, readed + 1 - matched)?;
+                if from_received
+                    {
+                        self.boundary_received = source[readed + 1 - matched..].to_vec();
+                    }
+                self.compare_pos = matched;
+                return Ok(true);
+            }
+
+        self.processor_write_from_to(source, 0, readed)?;
+        self.compare_pos = 0;
+        Ok(false)
+    }
+
+    /// Build prefix function (KMP failure table) of `boundary`
+    fn failure_table(boundary: &Vec<u8>) -> Vec<usize>
+    {
+        let mut result: Vec<usize> = vec![0; boundary.len()];
+        let mut matched: usize = 0;
+        for pos in 1..boundary.len()
+            {
+                while matched > 0 && boundary[pos] != boundary[matched]
+                    {
+                        matched = result[matched - 1];
+                    }
+                if boundary[pos] == boundary[matched]
+                    {
+                        matched += 1;
+                    }
+                result[pos] = matched;
+            }
+        result
+    }
+
+    /// `boundary_middle` successfully read - next may be `--` (end of data) or `\r\n` (header and content)
+    fn process_post_boundary(&mut self, c: u8) -> Result<(), Error>
+    {
+        let (divider_sym_equal, divider_boundary_equal) = self.compare(c, &self.divider);
+        let (epilogue_sym_equal, epilogue_boundary_equal) = self.compare(c, &self.epilogue);
+
+        if !divider_sym_equal && !epilogue_sym_equal
+            {
+                let description = format!("Cannot parse boundary end, invalid symbol '{}' at position {}", c, &self.compare_pos);
+                return Err(self.invalid_data(description));
+            }
+
+        if divider_boundary_equal
+            {
+                self.to_header();
+            }
+        if epilogue_boundary_equal
+            {
+                self.to_finished()?;
+            }
+        if !divider_boundary_equal && !epilogue_boundary_equal
+            {
+                self.compare_pos +=1;
+            }
+        Ok(())
+    }
+
+    /// Call `open` for current processor
+    fn processor_open(&mut self) -> ()
+    {
+        if let Some(ref mut process_content) = self.process_content
+            {
+                if let Some(ref headers ) = self.headers
+                    {
+                        process_content.borrow_mut().open(headers);
+                    }
+
+            }
+    }
+
+
+    /// Write `buf[from...to]` to current processor (subslice of `buf`, without copy)
+    fn processor_write_from_to(&mut self, buf: &[u8], from: usize, to: usize) -> Result<(), Error>
+    {
+        debug_assert!(from <= to && to <= buf.len(), "Invalid content range {}..{} of buffer with length {}", from, to, buf.len());
+
+        if self.on_error == OnError::Skip
+            {
+
+                return Ok(());
+            }
+
+        #[cfg(feature = "content-md5")]
+        {
+            if let Some((ref mut context, _)) = self.content_md5
+                {
+                    context.consume(&buf[from..to]);
+                }
+        }
+
+        if let Some(ref mut validated_data) = self.validated_data
+            {
+                match self.validated_data_max
+                    {
+                        _ if self.validated_data_exceeded => (),
+                        Some(max_size) if validated_data.len() + to - from > max_size =>
+                            {
+                                self.validated_data_exceeded = true;
+                                *validated_data = vec![];
+                            },
+                        _ => validated_data.extend_from_slice(&buf[from..to])
+                    }
+            }
+
+        if self.process_content.is_none()
+            {
+                self.text_field_write(&buf[from..to])?;
+                return self.check_in_flight_memory();
+            }
+
+        if let Some(ref mut process_content) = self.process_content
+            {
+                if let Some(max_size) = self.content_size_max
+                    {
+                        self.content_size +=  to - from;
+
+                        if self.content_size > max_size
+                            {
+                                match self.on_error
+                                    {
+                                        OnError::Skip | OnError::SkipAndClose => (),
+                                        OnError::ContinueWithoutError => (),
+                                        OnError::ContinueWithError =>
+                                            {
+                                                let tmp = process_content.borrow();
+                                                let process_params = tmp.get_process_params();
+                                                let name = &process_params.name;
+                                                if self.fail_fast
+                                                    {
+                                                        self.state = MultipartParserState::Finished;
+                                                        return Err(MultipartParser::<T>::fail_fast_error(format!("Size limit {} exceeded for field '{}'", max_size, name)));
+                                                    }
+                                                let on_error = match self.default_on_error
+                                                    {
+                                                        OnError::ContinueWithError => self.target.borrow_mut().error( &MultipartParseError::SizeLimit(name.clone(), max_size )),
+                                                        ref default_on_error => Ok(default_on_error.clone())
+                                                    };
+                                                self.fired_errors.push(MultipartParseError::SizeLimit(name.clone(), max_size));
+                                                match on_error
+                                                    {
+                                                        Ok(on_error) =>
+                                                            {
+                                                                self.on_error = on_error;
+
+                                                            },
+                                                        Err(e) => return Err(e)
+                                                    }
+                                                self.content_size_max = None;
+                                            }
+                                    }
+                            }
+                    }
+
+                if self.on_error == OnError::SkipAndClose
+                    {
+                        return self.abort_processor();
+                    }
+
+                if self.on_error == OnError::Skip
+                    {
+                        return Ok(());
+                    }
+
+                if let Some(ref headers ) = self.headers
+                    {
+                        process_content.borrow_mut().write(&headers, &buf[from..to])?;
+                        let write_control = process_content.borrow_mut().hooks().map(|hooks| hooks.write_control());
+                        if write_control == Some(WriteControl::StopPart)
+                            {
+                                self.on_error = OnError::Skip;
+                            }
+                    }
+
+            }
+        self.check_in_flight_memory()
+    }
+
+    /// Skip rest of current part, if data buffered in memory exceeds `max_in_flight_memory`
+    fn check_in_flight_memory(&mut self) -> Result<(), Error>
+    {
+        let max_memory = match self.max_in_flight_memory
+            {
+                Some(max_memory) if self.on_error != OnError::Skip => max_memory,
+                _ => return Ok(())
+            };
+        let buffered_size = match self.process_content
+            {
+                Some(ref process_content) => process_content.borrow().buffered_size(),
+                None => self.text_field.as_ref().map(|text_field| text_field.len()).unwrap_or(0)
+            } + self.validated_data.as_ref().map(|validated_data| validated_data.len()).unwrap_or(0);
+        if self.in_flight_memory + buffered_size <= max_memory
+            {
+                return Ok(());
+            }
+
+        let name = self.headers.as_ref().and_then(|headers| headers.get_name().cloned()).unwrap_or_default();
+        if self.fail_fast
+            {
+                self.state = MultipartParserState::Finished;
+                return Err(MultipartParser::<T>::fail_fast_error(format!("Memory limit {} exceeded by field '{}'", max_memory, name)));
+            }
+        // part is skipped anyway - `OnError` can be ignored
+        if self.default_on_error == OnError::ContinueWithError
+            {
+                self.target.borrow_mut().error( &MultipartParseError::MemoryLimit(max_memory) )?;
+            }
+        self.fired_errors.push(MultipartParseError::MemoryLimit(max_memory));
+        self.text_field = None;
+        self.abort_processor()
+    }
+
+
+    /// Append `data` to current text part without processor
+    fn text_field_write(&mut self, data: &[u8]) -> Result<(), Error>
+    {
+        let exceeded = match self.text_field
+            {
+                Some(ref text_field) if text_field.len() + data.len() > self.text_field_max_size => true,
+                Some(ref mut text_field) =>
+                    {
+                        text_field.extend(data);
+                        false
+                    },
+                None => false
+            };
+
+        if exceeded
+            {
+                self.text_field = None;
+                let max_size = self.text_field_max_size;
+                let name = self.headers.as_ref().and_then(|headers| headers.get_name().cloned()).unwrap_or_default();
+                if self.fail_fast
+                    {
+                        self.state = MultipartParserState::Finished;
+                        return Err(MultipartParser::<T>::fail_fast_error(format!("Size limit {} exceeded for field '{}'", max_size, name)));
+                    }
+                // part is dropped anyway - `OnError` can be ignored
+                if self.default_on_error == OnError::ContinueWithError
+                    {
+                        self.target.borrow_mut().error( &MultipartParseError::SizeLimit(name.clone(), max_size) )?;
+                    }
+                self.fired_errors.push(MultipartParseError::SizeLimit(name, max_size));
+            }
+        Ok(())
+    }
+
+    /// Store duration of current part, see `collect_timings`
+    fn record_part_timing(&mut self) -> ()
+    {
+        if let Some(part_started) = self.part_started.take()
+            {
+                let name = self.headers.as_ref().and_then(|headers| headers.get_name().cloned()).unwrap_or_default();
+                self.part_timings.push((name, part_started.elapsed()));
+            }
+    }
+
+    /// Compare MD5 of current part data with `Content-MD5` header, see `verify_content_md5`
+    #[cfg(feature = "content-md5")]
+    fn check_content_md5(&mut self) -> Result<(), Error>
+    {
+        let matched = match self.content_md5.take()
+            {
+                Some((context, expected)) => context.compute().0[..] == expected[..],
+                None => true
+            };
+        if matched || self.on_error == OnError::Skip
+            {
+                return Ok(());
+            }
+
+        let name = self.headers.as_ref().and_then(|headers| headers.get_name().cloned()).unwrap_or_default();
+        if self.fail_fast
+            {
+                self.state = MultipartParserState::Finished;
+                return Err(MultipartParser::<T>::fail_fast_error(format!("Content-MD5 mismatch for field '{}'", name)));
+            }
+        self.on_error = self.fire_error(MultipartParseError::ChecksumMismatch(name))?;
+        if self.on_error == OnError::SkipAndClose
+            {
+                self.abort_processor()?;
+            }
+        if self.on_error == OnError::Skip
+            {
+                self.text_field = None;
+            }
+        Ok(())
+    }
+
+    /// Validate data of current part, see `add_validator`
+    fn check_validator(&mut self) -> Result<(), Error>
+    {
+        let validated_data = match self.validated_data.take()
+            {
+                Some(validated_data) => validated_data,
+                None => return Ok(())
+            };
+        if self.on_error == OnError::Skip
+            {
+                return Ok(());
+            }
+
+        let name = self.headers.as_ref().and_then(|headers| headers.get_name().cloned()).unwrap_or_default();
+        let message = match (self.validated_data_exceeded, self.validated_data_max)
+            {
+                // data is not buffered completely - validator is not called
+                (true, Some(max_size)) => format!("Size limit {} exceeded", max_size),
+                _ => match self.validators.get(&name).map(|validator| validator(&validated_data))
+                    {
+                        Some(Err(message)) => message,
+                        _ => return Ok(())
+                    }
+            };
+        if self.fail_fast
+            {
+                self.state = MultipartParserState::Finished;
+                return Err(MultipartParser::<T>::fail_fast_error(format!("Validation failed for field '{}': {}", name, message)));
+            }
+        self.on_error = self.fire_error(MultipartParseError::ValidationFailed(name, message))?;
+        if self.on_error == OnError::SkipAndClose
+            {
+                self.abort_processor()?;
+            }
+        if self.on_error == OnError::Skip
+            {
+                self.text_field = None;
+            }
+        Ok(())
+    }
+
+    /// Call `flush` for current processor
+    fn processor_flush(&mut self) -> Result<(), Error>
+    {
+        #[cfg(feature = "content-md5")]
+        self.check_content_md5()?;
+        self.check_validator()?;
+
+        if let Some(text_field) = self.text_field.take()
+            {
+                self.in_flight_memory += text_field.len();
+                if let Some(name) = self.headers.as_ref().and_then(|headers| headers.get_name())
+                    {
+                        self.target.borrow_mut().on_text_field(name, &text_field);
+                    }
+            }
+
+        if let Some(process_content) = self.process_content.take()
+            {
+                // skipped field - no `flush` (see `OnError::Skip`)
+                if self.on_error == OnError::Skip
+                    {
+                        return Ok(());
+                    }
+                // data may be moved to target in `flush`
+                self.in_flight_memory += process_content.borrow().buffered_size();
+                if let Some(ref headers ) = self.headers
+                    {
+                        process_content.borrow_mut().flush(&headers)?;
+                    }
+                let (error_result, deferred_error) = match process_content.borrow_mut().hooks()
+                    {
+                        Some(hooks) => (hooks.take_error_result(), hooks.take_deferred_error()),
+                        None => (None, None)
+                    };
+                if let Some(Err(error)) = error_result
+                    {
+                        return Err(error);
+                    }
+                if let Some(field_error) = deferred_error
+                    {
+                        if self.fail_fast
+                            {
+                                self.state = MultipartParserState::Finished;
+                                return Err(MultipartParser::<T>::fail_fast_error(format!("Cannot convert field '{}': {}", field_error.name, field_error.message)));
+                            }
+                        match self.defer_field_errors
+                            {
+                                true => self.field_errors.push(field_error),
+                                // already passed to `error` by processor (or default action applied) - only for `on_errors`
+                                false => self.fired_errors.push(MultipartParseError::FieldError(field_error))
+                            }
+                    }
+            }
+        Ok(())
+    }
+}
+
+impl MultipartParser<EventTarget>
+{
+    /// Create `MultipartParser` for event-based API with known string `boundary`, see `events`
+    pub fn new_events(boundary: &str) -> Self
+    {
+        MultipartParser::new_owned(boundary, EventTarget::new())
+    }
+
+    /// Take events of data, written since previous call
+    pub fn events(&mut self) -> vec::IntoIter<MultipartEvent>
+    {
+        self.target.borrow_mut().take_events().into_iter()
+    }
+}
+
+
+#[cfg(test)]
+pub mod tests
+{
+    use super::{MultipartParser, MultipartParserState, Routing, MAX_HEADER_PARAMS, boundary_from_content_type};
+    use std::cell::{RefCell};
+    use std::env;
+    use std::fs;
+    use std::io::{Write, Error, ErrorKind};
+    use std::rc::{Rc};
+    use std::thread;
+    use std::time::{Duration};
+    use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, MultipartParseError, OnError, WriteControl, ProcessContent, ProcessContentHooks, ProcessParams, Headers, FieldError};
+    use ::capturing_target::{CapturingTarget};
+
+    /// Store headers (as `Display` strings) and data of every part
+    struct Recorder
+    {
+        params: ProcessParams,
+        parts: Rc<RefCell<Vec<(Vec<String>, Vec<u8>)>>>
+    }
+
+    impl ProcessContent for Recorder
+    {
+        fn open(&mut self, headers: &Headers) -> ()
+        {
+            let mut lines: Vec<String> = headers.headers.values().map(|header| header.to_string()).collect();
+            lines.sort();
+            self.parts.borrow_mut().push((lines, vec![]));
+        }
+
+        fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), Error>
+        {
+            self.parts.borrow_mut().last_mut().unwrap().1.extend(data);
+            Ok(())
+        }
+
+        fn flush(&mut self, _headers: &Headers) -> Result<(), Error>
+        {
+            if self.params.name == "flush_error"
+                {
+                    return Err(Error::new(ErrorKind::Other, "flush error"));
+                }
+            Ok(())
+        }
+
+        fn get_process_params(&self) -> &ProcessParams
+        {
+            &self.params
+        }
+    }
+
+    struct Target
+    {
+        parts: Rc<RefCell<Vec<(Vec<String>, Vec<u8>)>>>
+    }
+
+    impl MultipartParserTarget for Target {}
+
+    impl MultipartParserTargetGenerated for Target
+    {
+        fn get_all_required(&self) -> Vec<String> { vec![] }
+
+        fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+        {
+            let name = headers.get_name().cloned().unwrap_or_default();
+            Some(Rc::new(RefCell::new(Recorder { params: ProcessParams::new(name, None), parts: self.parts.clone() })))
+        }
+    }
+
+    /// Parse `body` (boundary `boundary`), written by chunks of `chunk` bytes
+    fn parse(body: &[u8], chunk: usize) -> Vec<(Vec<String>, Vec<u8>)>
+    {
+        parse_with("boundary", body, chunk)
+    }
+
+    /// Parse `body` with `boundary`, written by chunks of `chunk` bytes.
+    /// Return list of `(sorted header lines, data)` for each part.
+    pub fn parse_with(boundary: &str, body: &[u8], chunk: usize) -> Vec<(Vec<String>, Vec<u8>)>
+    {
+        let target = Rc::new(RefCell::new(Target { parts: Rc::new(RefCell::new(vec![])) }));
+        {
+            let mut multipart_parser: MultipartParser<Target> = MultipartParser::new_from_str(boundary, &target);
+            for data in body.chunks(chunk)
+                {
+                    multipart_parser.write_all(data).unwrap();
+                }
+            multipart_parser.flush().unwrap();
+        }
+        let parts = target.borrow().parts.borrow().clone();
+        parts
+    }
+
+    /// Short text of `error`: name of variant and its names or numbers, `other` for variants not used in tests
+    fn describe(error: &MultipartParseError) -> String
+    {
+        match error
+            {
+                &MultipartParseError::SizeLimit(ref name, max_size) => format!("SizeLimit {} {}", name, max_size),
+                &MultipartParseError::DuplicateContentType(ref name) => format!("DuplicateContentType {}", name),
+                &MultipartParseError::InvalidParameter(ref name, ref reason) => format!("InvalidParameter {} {}", name, reason),
+                &MultipartParseError::RequiredMissing(names) => format!("RequiredMissing {}", names.join(",")),
+                &MultipartParseError::TooFewParts { expected, received } => format!("TooFewParts {} {}", expected, received),
+                &MultipartParseError::TooManyParts(max_parts) => format!("TooManyParts {}", max_parts),
+                &MultipartParseError::TotalSizeLimit(max_size) => format!("TotalSizeLimit {}", max_size),
+                &MultipartParseError::MemoryLimit(max_memory) => format!("MemoryLimit {}", max_memory),
+                &MultipartParseError::MalformedBoundary(offset) => format!("MalformedBoundary {}", offset),
+                &MultipartParseError::HeaderRefeedLimit(max_refeed) => format!("HeaderRefeedLimit {}", max_refeed),
+                &MultipartParseError::FieldError(ref field_error) => format!("FieldError {}", field_error.name),
+                &MultipartParseError::FieldErrors(field_errors) => format!("FieldErrors {}", field_errors.len()),
+                &MultipartParseError::ChecksumMismatch(ref name) => format!("ChecksumMismatch {}", name),
+                &MultipartParseError::ValidationFailed(ref name, ref message) => format!("ValidationFailed {} {}", name, message),
+                &MultipartParseError::PartTimeout(ref name) => format!("PartTimeout {}", name),
+                _ => "other".to_string()
+            }
+    }
+
+    /// Collect text fields by `on_text_field`, errors of `error` and `on_errors` calls (as `describe` text),
+    /// headers of parts and names of nested multipart parts. Change fields of `Recording::new()` to set
+    /// result of `error` and `on_nested_multipart`, required fields and processor of parts.
+    struct Recording
+    {
+        fields: Vec<(String, Vec<u8>)>,
+        errors: Vec<String>,
+        on_errors: Vec<String>,
+        /// Headers of parts, passed to `content_parser`
+        opened: Vec<Headers>,
+        /// Names of nested multipart parts
+        nested: Vec<String>,
+        /// `truncated` and `finish` calls, shared with processors to record their calls in same order
+        calls: Rc<RefCell<Vec<String>>>,
+        /// Result of `error`
+        on_error: OnError,
+        /// Result of `on_nested_multipart`
+        on_nested: OnError,
+        required: Vec<String>,
+        /// Processor of part, `None` - text field
+        processor: Option<Box<Fn(&Headers) -> Option<Rc<RefCell<ProcessContent>>>>>
+    }
+
+    impl Recording
+    {
+        fn new() -> Recording
+        {
+            Recording
+                {
+                    fields: vec![],
+                    errors: vec![],
+                    on_errors: vec![],
+                    opened: vec![],
+                    nested: vec![],
+                    calls: Rc::new(RefCell::new(vec![])),
+                    on_error: OnError::ContinueWithoutError,
+                    on_nested: OnError::ContinueWithoutError,
+                    required: vec![],
+                    processor: None
+                }
+        }
+
+        /// Names of received text fields
+        fn names(&self) -> Vec<String>
+        {
+            self.fields.iter().map(|&(ref name, _)| name.clone()).collect()
+        }
+
+        /// `finish` called
+        fn finished(&self) -> bool
+        {
+            self.calls.borrow().iter().any(|call| call == "finish")
+        }
+    }
+
+    impl MultipartParserTarget for Recording
+    {
+        fn content_parser(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+        {
+            self.opened.push(headers.clone());
+            self.processor.as_ref().and_then(|processor| processor(headers))
+        }
+
+        fn on_text_field(&mut self, name: &str, value: &[u8]) -> ()
+        {
+            self.fields.push((name.to_string(), value.to_vec()));
+        }
+
+        fn on_nested_multipart(&mut self, headers: &Headers) -> OnError
+        {
+            self.nested.push(headers.get_name().cloned().unwrap_or_default());
+            self.on_nested.clone()
+        }
+
+        fn error(&mut self, error: &MultipartParseError) -> Result<OnError, Error>
+        {
+            self.errors.push(describe(error));
+            Ok(self.on_error.clone())
+        }
+
+        fn on_errors(&mut self, errors: &[MultipartParseError]) -> ()
+        {
+            self.on_errors.extend(errors.iter().map(describe));
+        }
+
+        fn truncated(&mut self) -> ()
+        {
+            self.calls.borrow_mut().push("truncated".to_string());
+        }
+
+        fn finish(&mut self) -> ()
+        {
+            self.calls.borrow_mut().push("finish".to_string());
+        }
+    }
+
+    impl MultipartParserTargetGenerated for Recording
+    {
+        fn get_all_required(&self) -> Vec<String> { self.required.clone() }
+
+        fn content_parser_generated(&mut self, self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+        {
+            self.content_parser(self_, headers)
+        }
+    }
+
+    #[test]
+    fn multiline_headers() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"\r\nContent-Type: text/plain\r\n\r\nabc\r\n--boundary--\r\n";
+        for chunk in 1..body.len() + 1
+            {
+                let parts = parse(body, chunk);
+                assert_eq!(1, parts.len());
+                assert_eq!(vec!["Content-Disposition: form-data; name=\"file1\"".to_string(), "Content-Type: text/plain".to_string()], parts[0].0);
+            }
+        assert_eq!(b"abc".to_vec(), parse(body, body.len())[0].1);
+    }
+
+    #[test]
+    fn processor_flush_error() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"flush_error\"\r\n\r\nabc\r\n--boundary--\r\n";
+        let target = Rc::new(RefCell::new(Target { parts: Rc::new(RefCell::new(vec![])) }));
+        let mut multipart_parser: MultipartParser<Target> = MultipartParser::new_from_str("boundary", &target);
+        let result = multipart_parser.write(body);
+        assert_eq!(ErrorKind::Other, result.unwrap_err().kind());
+
+        // last part without final boundary is flushed by `flush` of parser
+        let mut multipart_parser: MultipartParser<Target> = MultipartParser::new_from_str("boundary", &target);
+        multipart_parser.flush_incomplete_final_part(true);
+        multipart_parser.write_all(&body[..body.len() - 16]).unwrap();
+        assert_eq!(ErrorKind::Other, multipart_parser.flush().unwrap_err().kind());
+    }
+
+    #[test]
+    fn new_owned() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"text2\"\r\n\r\ndef\r\n--boundary--\r\n";
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Target { parts: Rc::new(RefCell::new(vec![])) });
+        multipart_parser.write_all(body).unwrap();
+        multipart_parser.flush().unwrap();
+
+        let target: Target = multipart_parser.into_target();
+        let parts = target.parts.borrow();
+        assert_eq!(2, parts.len());
+        assert_eq!(b"abc".to_vec(), parts[0].1);
+        assert_eq!(b"def".to_vec(), parts[1].1);
+    }
+
+    #[test]
+    fn on_text_field() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\n\r\nfile\r\n--boundary\r\nContent-Disposition: form-data; name=\"long\"\r\n\r\n0123456789\r\n--boundary\r\nContent-Disposition: form-data; name=\"text2\"\r\n\r\n\r\n--boundary--\r\n";
+        for chunk in 1..body.len() + 1
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                multipart_parser.text_field_max_size(5);
+                for data in body.chunks(chunk)
+                    {
+                        multipart_parser.write_all(data).unwrap();
+                    }
+
+                let target = multipart_parser.into_target();
+                assert_eq!(vec![("text1".to_string(), b"abc".to_vec()), ("text2".to_string(), vec![])], target.fields);
+                assert_eq!(vec!["SizeLimit long 5"], target.errors);
+            }
+
+        // disabled: parts are dropped without error
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.buffer_text_fields(false).text_field_max_size(5);
+        multipart_parser.write_all(body).unwrap();
+        let target = multipart_parser.into_target();
+        assert!(target.fields.is_empty());
+        assert!(target.errors.is_empty());
+    }
+
+    #[test]
+    fn content_split_at_any_offsets() -> ()
+    {
+        let contents: Vec<&[u8]> = vec![b"\r\n--boundar", b"\r\r\n-\r\n--", b"--boundary", b"", b"\r\n\r\n--b\r\n--bo"];
+        let mut body: Vec<u8> = vec![];
+        for (i, content) in contents.iter().enumerate()
+            {
+                body.extend(format!("--boundary\r\nContent-Disposition: form-data; name=\"f{}\"\r\n\r\n", i).as_bytes());
+                body.extend(*content);
+                body.extend(b"\r\n");
+            }
+        body.extend(b"--boundary--\r\n");
+
+        // body written by 3 writes: `[0..first]`, `[first..second]`, `[second..]`
+        for first in 0..body.len() + 1
+            {
+                for second in first..body.len() + 1
+                    {
+                        let target = Rc::new(RefCell::new(Target { parts: Rc::new(RefCell::new(vec![])) }));
+                        {
+                            let mut multipart_parser: MultipartParser<Target> = MultipartParser::new_from_str("boundary", &target);
+                            multipart_parser.write_all(&body[..first]).unwrap();
+                            multipart_parser.write_all(&body[first..second]).unwrap();
+                            multipart_parser.write_all(&body[second..]).unwrap();
+                        }
+                        let parts = target.borrow().parts.borrow().clone();
+                        let data: Vec<Vec<u8>> = parts.into_iter().map(|part| part.1).collect();
+                        let expected: Vec<Vec<u8>> = contents.iter().map(|content| content.to_vec()).collect();
+                        assert_eq!(expected, data, "split at {} and {}", first, second);
+                    }
+            }
+    }
+
+    #[test]
+    fn self_overlapping_boundary() -> ()
+    {
+        // `boundary_middle` is `\r\n----a--`
+        let contents: Vec<&[u8]> = vec![b"\r\n---", b"\r\n----a-\r\n----a", b"-\r\n-----a--", b"\r\n--\r\n----"];
+        let mut body: Vec<u8> = vec![];
+        for content in &contents
+            {
+                body.extend(b"----a--\r\nContent-Disposition: form-data; name=\"f\"\r\n\r\n");
+                body.extend(*content);
+                body.extend(b"\r\n");
+            }
+        body.extend(b"----a----\r\n");
+
+        for chunk in 1..body.len() + 1
+            {
+                let data: Vec<Vec<u8>> = parse_with("--a--", &body, chunk).into_iter().map(|part| part.1).collect();
+                let expected: Vec<Vec<u8>> = contents.iter().map(|content| content.to_vec()).collect();
+                assert_eq!(expected, data, "chunk {}", chunk);
+            }
+    }
+
+    #[test]
+    fn malformed_input_no_panic() -> ()
+    {
+        let bodies: Vec<&[u8]> = vec![
+            b"",
+            b"garbage",
+            b"--boundaryXY\r\n",
+            b"--boundary\r",
+            b"--boundary--\r\n",
+            b"--boundary\r\nno colon\r\n\r\ndata\r\n--boundary--\r\n",
+            b"--boundary\r\nContent-Disposition: form-data\r\n\r\nno name\r\n--boundary--\r\n",
+            b"--boundary\r\nContent-Disposition: form-data; name; =\r\n\r\nx\r\n--boundaryZZ",
+            b"--boundary\r\n\r\n\r\n--boundary-\r\n",
+            b"--boundary\r\n\xff\xfe: \xff\r\n\r\n\r\n--boundary--",
+            b"--boundary\r\nContent-Disposition: form-data; name=\"a\r\n\r\n\r\n--boundary\r\n--boundary--",
+            b"--boundary\r\n:\r\n\r\n\r\n--boundary--\r\n--boundary\r\n",
+        ];
+
+        for body in bodies
+            {
+                for chunk in 1..body.len() + 2
+                    {
+                        let target = Rc::new(RefCell::new(Target { parts: Rc::new(RefCell::new(vec![])) }));
+                        let mut multipart_parser: MultipartParser<Target> = MultipartParser::new_from_str("boundary", &target);
+                        for data in body.chunks(chunk)
+                            {
+                                if let Err(error) = multipart_parser.write(data)
+                                    {
+                                        assert_eq!(ErrorKind::InvalidData, error.kind());
+                                        break;
+                                    }
+                            }
+                    }
+            }
+    }
+
+    #[test]
+    fn state_transitions() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nab\r\n--boundary--\r\n";
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { required: vec!["text1".to_string(), "text2".to_string()], ..Recording::new() });
+        assert_eq!(&MultipartParserState::BoundaryFirst, multipart_parser.state());
+        assert_eq!(&vec!["text1".to_string(), "text2".to_string()], multipart_parser.pending_required());
+
+        // `(offset of last written byte, state after it)` for every state change
+        let mut transitions: Vec<(usize, MultipartParserState)> = vec![];
+        for (pos, c) in body.iter().enumerate()
+            {
+                let before = multipart_parser.state().clone();
+                multipart_parser.write_all(&[*c]).unwrap();
+                if *multipart_parser.state() != before
+                    {
+                        transitions.push((pos, multipart_parser.state().clone()));
+                    }
+                if pos == 11
+                    {
+                        assert_eq!(0, multipart_parser.parts_seen());
+                    }
+            }
+
+        assert_eq!(vec![
+            (11, MultipartParserState::Header),
+            (57, MultipartParserState::PostHeader),
+            (59, MultipartParserState::Content),
+            (73, MultipartParserState::PostBoundary),
+            (75, MultipartParserState::Finished),
+        ], transitions);
+        assert_eq!(1, multipart_parser.parts_seen());
+        assert_eq!(&vec!["text2".to_string()], multipart_parser.pending_required());
+    }
+
+    /// Record calls of `flush` and `cleanup`
+    struct Cleanup
+    {
+        params: ProcessParams,
+        calls: Rc<RefCell<Vec<String>>>
+    }
+
+    impl ProcessContent for Cleanup
+    {
+        fn open(&mut self, _headers: &Headers) -> () {}
+
+        fn write(&mut self, _headers: &Headers, _data: &[u8]) -> Result<(), Error>
+        {
+            Ok(())
+        }
+
+        fn flush(&mut self, headers: &Headers) -> Result<(), Error>
+        {
+            self.calls.borrow_mut().push(format!("flush {}", headers.get_name().unwrap()));
+            Ok(())
+        }
+
+        fn get_process_params(&self) -> &ProcessParams
+        {
+            &self.params
+        }
+
+        fn cleanup(&mut self, headers: &Headers) -> ()
+        {
+            self.calls.borrow_mut().push(format!("cleanup {}", headers.get_name().unwrap()));
+        }
+    }
+
+    /// `Recording` with `Cleanup` processor for each part, `text2` required
+    fn cleanup_target() -> Recording
+    {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let processor_calls = calls.clone();
+        let processor = move |headers: &Headers| -> Option<Rc<RefCell<ProcessContent>>>
+            {
+                let name = headers.get_name().cloned().unwrap_or_default();
+                Some(Rc::new(RefCell::new(Cleanup { params: ProcessParams::new(name, None), calls: processor_calls.clone() })))
+            };
+        Recording { calls, required: vec!["text2".to_string()], processor: Some(Box::new(processor)), ..Recording::new() }
+    }
+
+    #[test]
+    fn cleanup_on_drop() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\n\r\npartial";
+        for &cleanup in &[false, true]
+            {
+                let target = Rc::new(RefCell::new(cleanup_target()));
+                {
+                    let mut multipart_parser: MultipartParser<Recording> = MultipartParser::new_from_str("boundary", &target);
+                    multipart_parser.cleanup_on_drop(cleanup);
+                    multipart_parser.write_all(body).unwrap();
+                }
+                let (expected, expected_errors): (Vec<&str>, Vec<&str>) = match cleanup
+                    {
+                        true => (vec!["flush text1", "cleanup file1", "truncated", "finish"], vec!["RequiredMissing text2"]),
+                        false => (vec!["flush text1"], vec![])
+                    };
+                assert_eq!(expected, *target.borrow().calls.borrow());
+                assert_eq!(expected_errors, target.borrow().errors);
+
+                // same calls, when target is taken back
+                let mut multipart_parser = MultipartParser::new_owned("boundary", cleanup_target());
+                multipart_parser.cleanup_on_drop(cleanup);
+                multipart_parser.write_all(body).unwrap();
+                let target = multipart_parser.into_target();
+                assert_eq!(expected, *target.calls.borrow());
+                assert_eq!(expected_errors, target.errors);
+            }
+
+        // complete data - nothing called on drop
+        let target = cleanup_target();
+        let calls = target.calls.clone();
+        {
+            let mut multipart_parser = MultipartParser::new_owned("boundary", target);
+            multipart_parser.cleanup_on_drop(true);
+            multipart_parser.write_all(b"--boundary\r\nContent-Disposition: form-data; name=\"text2\"\r\n\r\nabc\r\n--boundary--\r\n").unwrap();
+        }
+        assert_eq!(vec!["flush text2", "finish"], *calls.borrow());
+    }
+
+    /// `Recording` with `Recorder` processor, `max_size` 3, skip fields on errors
+    fn limited(parts: &Rc<RefCell<Vec<(Vec<String>, Vec<u8>)>>>) -> Recording
+    {
+        let parts = parts.clone();
+        let processor = move |headers: &Headers| -> Option<Rc<RefCell<ProcessContent>>>
+            {
+                let name = headers.get_name().cloned().unwrap_or_default();
+                Some(Rc::new(RefCell::new(Recorder { params: ProcessParams::new(name, Some(3)), parts: parts.clone() })))
+            };
+        Recording { on_error: OnError::Skip, processor: Some(Box::new(processor)), ..Recording::new() }
+    }
+
+    #[test]
+    fn default_on_error() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"long\"\r\n\r\n0123456789\r\n--boundary\r\nContent-Disposition: form-data; name=\"short\"\r\n\r\nabc\r\n--boundary--\r\n";
+        for &default_on_error in &[false, true]
+            {
+                for chunk in 1..body.len() + 1
+                    {
+                        let parts = Rc::new(RefCell::new(vec![]));
+                        let mut multipart_parser = MultipartParser::new_owned("boundary", limited(&parts));
+                        if default_on_error
+                            {
+                                multipart_parser.set_default_on_error(OnError::Skip);
+                            }
+                        for data in body.chunks(chunk)
+                            {
+                                multipart_parser.write_all(data).unwrap();
+                            }
+
+                        // data of field is written by pieces, bounded by chunks, piece exceeding limit is not written
+                        let start = body.windows(10).position(|window| window == b"0123456789").unwrap();
+                        let (mut pos, mut written) = (start, 0);
+                        while (pos / chunk + 1) * chunk - start <= 3
+                            {
+                                pos = (pos / chunk + 1) * chunk;
+                                written = pos - start;
+                            }
+
+                        let target = multipart_parser.into_target();
+                        assert_eq!(if default_on_error { vec![] } else { vec!["SizeLimit long 3"] }, target.errors);
+                        // `open` called for both fields, data of skipped field is not greater than limit
+                        let parts = parts.borrow();
+                        assert_eq!(2, parts.len());
+                        assert_eq!(b"0123456789"[..written].to_vec(), parts[0].1, "chunk {}", chunk);
+                        assert_eq!(b"abc".to_vec(), parts[1].1);
+                    }
+            }
+    }
+
+    #[test]
+    fn min_parts() -> ()
+    {
+        let one_part: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\n\r\nabc\r\n--boundary--\r\n";
+        for &(body, min_parts, ref expected) in &[(&b""[..], 1, vec!["TooFewParts 1 0"]), (one_part, 2, vec!["TooFewParts 2 1"]), (one_part, 1, vec![]), (&b""[..], 0, vec![])]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                multipart_parser.set_min_parts(min_parts).flush_incomplete_final_part(true);
+                multipart_parser.write_all(body).unwrap();
+                multipart_parser.flush().unwrap();
+                multipart_parser.flush().unwrap();
+                assert_eq!(*expected, multipart_parser.into_target().errors);
+            }
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.set_min_parts(1).flush_incomplete_final_part(true).fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.flush().unwrap_err().kind());
+
+        // `flush` in middle of data does not check count of parts, error is collected for `on_errors`
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.set_min_parts(2);
+        let (head, tail) = one_part.split_at(20);
+        multipart_parser.write_all(head).unwrap();
+        multipart_parser.flush().unwrap();
+        assert!(multipart_parser.target.borrow().errors.is_empty());
+        multipart_parser.write_all(tail).unwrap();
+        let target = multipart_parser.into_target();
+        assert_eq!(vec!["TooFewParts 2 1"], target.errors);
+        assert_eq!(vec!["TooFewParts 2 1"], target.on_errors);
+    }
+
+    #[test]
+    fn duplicate_content_type() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\ncontent-type: image/png\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"file2\"; filename=\"b.txt\"\r\nContent-Type: text/html\r\n\r\ndef\r\n--boundary--\r\n";
+        for &strict in &[false, true]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+                multipart_parser.strict(strict);
+                multipart_parser.write_all(body).unwrap();
+
+                let target = multipart_parser.into_target();
+                let content_types: Vec<(&str, &str)> = target.opened.iter().map(|headers| (headers.get_name().unwrap().as_str(), headers.get_mime_type().unwrap())).collect();
+                assert_eq!(vec![("file1", "text/plain"), ("file2", "text/html")], content_types);
+                assert_eq!(if strict { vec!["DuplicateContentType file1"] } else { vec![] }, target.errors);
+            }
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+        multipart_parser.strict(true).fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(body).unwrap_err().kind());
+    }
+
+    #[test]
+    fn routing() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Type: application/json\r\nContent-ID: <root>\r\n\r\n{\"image\": \"cid:img1\"}\r\n--boundary\r\nContent-Type: image/png\r\nContent-ID: <img1>\r\n\r\nPNG\r\n--boundary\r\nContent-Type: text/plain\r\n\r\nnote\r\n--boundary--\r\n";
+        for &(ref routing, ref names) in &[(Routing::ContentId, ["root", "img1", ""]), (Routing::Index, ["0", "1", "2"])]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Target { parts: Rc::new(RefCell::new(vec![])) });
+                multipart_parser.routing(routing.clone());
+                multipart_parser.write_all(body).unwrap();
+
+                let target = multipart_parser.into_target();
+                let parts = target.parts.borrow();
+                assert_eq!(3, parts.len());
+                for (part, name) in parts.iter().zip(names.iter())
+                    {
+                        let expected = format!("Content-Disposition: form-data; name=\"{}\"", name);
+                        assert_eq!(!name.is_empty(), part.0.contains(&expected));
+                    }
+                assert_eq!(b"{\"image\": \"cid:img1\"}".to_vec(), parts[0].1);
+                assert_eq!(b"PNG".to_vec(), parts[1].1);
+                assert_eq!(b"note".to_vec(), parts[2].1);
+            }
+    }
+
+    #[test]
+    fn header_partial_crlf() -> ()
+    {
+        let junk: Vec<u8> = b"a\rb\r\rc\r\r\r".iter().cycle().take(60000).cloned().collect();
+        let mut body: Vec<u8> = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"\r\nX-Junk: ".to_vec();
+        body.extend(&junk);
+        body.extend(b"\r\n\r\r\nX-Next: 1\r\n\r\nabc\r\n--boundary--\r\n".iter());
+        for &chunk in &[1, 7, body.len()]
+            {
+                let parts = parse(&body, chunk);
+                assert_eq!(1, parts.len());
+                assert_eq!(b"abc".to_vec(), parts[0].1);
+
+                let junk_header = format!("X-Junk: {}", String::from_utf8(junk.clone()).unwrap().trim());
+                assert!(parts[0].0.contains(&junk_header));
+                assert!(parts[0].0.contains(&"X-Next: 1".to_string()));
+            }
+
+        // `\r\r` needs 2 iterations: limit 1 stops parsing
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.max_header_refeed(1);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(&body).unwrap_err().kind());
+        // data after error is ignored
+        multipart_parser.write_all(b"--boundary\r\n").unwrap();
+        assert_eq!(vec!["HeaderRefeedLimit 1"], multipart_parser.into_target().errors);
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.max_header_refeed(1).fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(&body).unwrap_err().kind());
+        assert!(multipart_parser.into_target().errors.is_empty());
+    }
+
+    #[test]
+    fn epilogue_headers() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"\r\n\r\nabc\r\n--boundary--\r\nX-Signature: sha256=abc\r\nX-Count: 1\r\n\r\nignored: 1\r\n";
+        for &chunk in &[1, 5, body.len()]
+            {
+                let target = Rc::new(RefCell::new(Target { parts: Rc::new(RefCell::new(vec![])) }));
+                let mut multipart_parser = MultipartParser::new_from_str("boundary", &target);
+                multipart_parser.parse_epilogue_headers(true);
+                for data in body.chunks(chunk)
+                    {
+                        multipart_parser.write_all(data).unwrap();
+                    }
+
+                let headers = multipart_parser.epilogue_headers().unwrap();
+                assert_eq!(2, headers.headers.len());
+                assert_eq!("sha256=abc", headers.headers["X-Signature"].value);
+                assert_eq!("1", headers.headers["X-Count"].value);
+                assert_eq!(b"abc".to_vec(), target.borrow().parts.borrow()[0].1);
+            }
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Target { parts: Rc::new(RefCell::new(vec![])) });
+        multipart_parser.write_all(body).unwrap();
+        assert!(multipart_parser.epilogue_headers().is_none());
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Target { parts: Rc::new(RefCell::new(vec![])) });
+        multipart_parser.parse_epilogue_headers(true);
+        multipart_parser.write_all(b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"\r\n\r\nabc\r\n--boundary--\r\n").unwrap();
+        assert!(multipart_parser.epilogue_headers().is_none());
+    }
+
+    #[test]
+    fn first_content_byte() -> ()
+    {
+        // `\r` and `\n` - possible leakage of header-terminating `\r\n` or head of boundary
+        for &byte in &[b'x', 0x00, 0xFF, b'\r', b'\n', b'-']
+            {
+                let mut body: Vec<u8> = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\n".to_vec();
+                body.push(byte);
+                body.extend(b"\r\n--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.bin\"\r\nContent-Type: application/octet-stream\r\n\r\n".iter());
+                body.push(byte);
+                body.extend(b"\r\n--boundary--\r\n".iter());
+                for chunk in 1..body.len() + 1
+                    {
+                        let parts = parse(&body, chunk);
+                        assert_eq!(2, parts.len());
+                        assert_eq!(vec![byte], parts[0].1, "text part, byte {}, chunk {}", byte, chunk);
+                        assert_eq!(vec![byte], parts[1].1, "binary part, byte {}, chunk {}", byte, chunk);
+                    }
+            }
+    }
+
+    #[test]
+    fn last_content_bytes() -> ()
+    {
+        // only `\r\n` before `--boundary` is part of delimiter, content ends with any bytes (including head of delimiter)
+        for &data in &[&b"abc"[..], b"ab\r", b"ab\n", b"ab\r\n", b"ab\r\n\r", b"\r\n--boundar", b"-"]
+            {
+                let mut body: Vec<u8> = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.bin\"\r\n\r\n".to_vec();
+                body.extend(data);
+                body.extend(b"\r\n--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\n".iter());
+                body.extend(data);
+                body.extend(b"\r\n--boundary--".iter());
+                for chunk in 1..body.len() + 1
+                    {
+                        let parts = parse(&body, chunk);
+                        assert_eq!(2, parts.len());
+                        assert_eq!(data.to_vec(), parts[0].1, "chunk {}", chunk);
+                        assert_eq!(data.to_vec(), parts[1].1, "chunk {}", chunk);
+                    }
+            }
+    }
+
+    #[cfg(feature = "content-md5")]
+    #[test]
+    fn content_md5() -> ()
+    {
+        // MD5 of `abc` is `kAFQmDzST7DWlj99KOF/cg==`
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"good\"\r\nContent-MD5: kAFQmDzST7DWlj99KOF/cg==\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"bad\"\r\nContent-MD5: kAFQmDzST7DWlj99KOF/cg==\r\n\r\nabd\r\n--boundary\r\nContent-Disposition: form-data; name=\"none\"\r\n\r\nxyz\r\n--boundary--\r\n";
+        for &(verify, ref fields, ref mismatches) in &[(true, vec!["good", "none"], vec!["ChecksumMismatch bad"]), (false, vec!["good", "bad", "none"], vec![])]
+            {
+                for &chunk in &[1, 3, body.len()]
+                    {
+                        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+                        multipart_parser.verify_content_md5(verify);
+                        for data in body.chunks(chunk)
+                            {
+                                multipart_parser.write_all(data).unwrap();
+                            }
+
+                        let target = multipart_parser.into_target();
+                        assert_eq!(*fields, target.names());
+                        assert_eq!(b"abc".to_vec(), target.fields[0].1);
+                        assert_eq!(*mismatches, target.errors);
+                    }
+            }
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+        multipart_parser.verify_content_md5(true).fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(body).unwrap_err().kind());
+    }
+
+    #[test]
+    fn nested_multipart() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"bundle\"\r\nContent-Type: Multipart/Mixed; boundary=inner\r\n\r\n--inner\r\n\r\nx\r\n--inner--\r\n--boundary\r\nContent-Disposition: form-data; name=\"note\"\r\nContent-Type: text/plain\r\n\r\nabc\r\n--boundary--\r\n";
+        for &skip in &[true, false]
+            {
+                let on_nested = if skip { OnError::Skip } else { OnError::ContinueWithoutError };
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_nested, ..Recording::new() });
+                multipart_parser.write_all(body).unwrap();
+
+                let target = multipart_parser.into_target();
+                assert_eq!(vec!["bundle".to_string()], target.nested);
+                assert_eq!(if skip { vec!["note"] } else { vec!["bundle", "note"] }, target.names());
+                assert_eq!(b"abc".to_vec(), target.fields.last().unwrap().1);
+            }
+    }
+
+    #[test]
+    fn part_timings() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\ndef\r\n--boundary--\r\n";
+        for &collect in &[true, false]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Target { parts: Rc::new(RefCell::new(vec![])) });
+                multipart_parser.collect_timings(collect);
+                for data in body.chunks(5)
+                    {
+                        multipart_parser.write_all(data).unwrap();
+                    }
+
+                let timings = multipart_parser.part_timings();
+                let names: Vec<&str> = timings.iter().map(|timing| timing.0.as_str()).collect();
+                assert_eq!(if collect { vec!["file1", "text1"] } else { vec![] }, names);
+                assert!(timings.iter().all(|timing| timing.1 >= Duration::from_secs(0)));
+            }
+    }
+
+    /// Processor, which write data to "file" (buffer), opened in `open` and closed in `flush`
+    struct FileLike
+    {
+        params: ProcessParams,
+        file: Option<Vec<u8>>,
+        closed: Rc<RefCell<Vec<Vec<u8>>>>
+    }
+
+    impl ProcessContent for FileLike
+    {
+        fn open(&mut self, _headers: &Headers) -> ()
+        {
+            self.file = Some(vec![]);
+        }
+
+        fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), Error>
+        {
+            self.file.as_mut().unwrap().extend(data);
+            Ok(())
+        }
+
+        fn flush(&mut self, _headers: &Headers) -> Result<(), Error>
+        {
+            self.closed.borrow_mut().push(self.file.take().unwrap());
+            Ok(())
+        }
+
+        fn get_process_params(&self) -> &ProcessParams
+        {
+            &self.params
+        }
+    }
+
+    /// `Recording` with `FileLike` processor, `max_size` 4, return `on_error` on errors
+    fn file_like(on_error: OnError, closed: &Rc<RefCell<Vec<Vec<u8>>>>) -> Recording
+    {
+        let closed = closed.clone();
+        let processor = move |headers: &Headers| -> Option<Rc<RefCell<ProcessContent>>>
+            {
+                let name = headers.get_name().cloned().unwrap_or_default();
+                Some(Rc::new(RefCell::new(FileLike { params: ProcessParams::new(name, Some(4)), file: None, closed: closed.clone() })))
+            };
+        Recording { on_error, processor: Some(Box::new(processor)), ..Recording::new() }
+    }
+
+    #[test]
+    fn skip_and_close() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\n\r\nabcdefgh\r\n--boundary\r\nContent-Disposition: form-data; name=\"file2\"; filename=\"b.txt\"\r\n\r\nxyz\r\n--boundary--\r\n";
+        for &(ref on_error, ref expected) in &[(OnError::SkipAndClose, vec![&b"abcd"[..], b"xyz"]), (OnError::Skip, vec![&b"xyz"[..]])]
+            {
+                let closed = Rc::new(RefCell::new(vec![]));
+                let mut multipart_parser = MultipartParser::new_owned("boundary", file_like(on_error.clone(), &closed));
+                for data in body.chunks(2)
+                    {
+                        multipart_parser.write_all(data).unwrap();
+                    }
+                assert_eq!(*expected, closed.borrow().iter().map(|file| file.as_slice()).collect::<Vec<&[u8]>>());
+            }
+    }
+
+    #[test]
+    fn max_header_params() -> ()
+    {
+        let params: Vec<String> = (0..1000).map(|i| format!("p{}=\"{}\"", i, i)).collect();
+        let body = format!("--boundary\r\nContent-Disposition: form-data; name=\"file1\"; {}; filename=\"a.txt\"\r\n\r\nabc\r\n--boundary--\r\n", params.join("; "));
+        for &(max_params, expected) in &[(None, MAX_HEADER_PARAMS), (Some(3), 3), (Some(2000), 1002)]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Target { parts: Rc::new(RefCell::new(vec![])) });
+                if let Some(max_params) = max_params
+                    {
+                        multipart_parser.max_header_params(max_params);
+                    }
+                multipart_parser.write_all(body.as_bytes()).unwrap();
+
+                let target = multipart_parser.into_target();
+                let parts = target.parts.borrow();
+                assert_eq!(1, parts.len());
+                assert_eq!(expected, parts[0].0[0].matches("; ").count());
+                assert!(parts[0].0[0].contains("name=\"file1\""));
+                assert_eq!(expected == 1002, parts[0].0[0].contains("filename=\"a.txt\""));
+                assert_eq!(b"abc".to_vec(), parts[0].1);
+            }
+    }
+
+    #[test]
+    fn export_import_state() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Type: text/plain\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\n\r\nabc\r\n--boun\r\ndef\r\n--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nxyz\r\n--boundary--\r\n";
+        // header lines of current part - in order of part
+        let file1_headers: Vec<String> = vec!["Content-Type: text/plain".to_string(), "Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"".to_string()];
+        for split in 1..body.len()
+            {
+                let parts = Rc::new(RefCell::new(vec![]));
+                let state =
+                    {
+                        let mut multipart_parser = MultipartParser::new_owned("boundary", Target { parts: parts.clone() });
+                        multipart_parser.write_all(&body[..split]).unwrap();
+                        multipart_parser.export_state()
+                    };
+                if state.state == MultipartParserState::Content && state.parts_seen == 1
+                    {
+                        assert_eq!(file1_headers, state.headers);
+                    }
+
+                // processor of current part is saved separately, `Recorder` continues to append to last part
+                let process_content: Option<Rc<RefCell<ProcessContent>>> = match state.state
+                    {
+                        MultipartParserState::Content => Some(Rc::new(RefCell::new(Recorder { params: ProcessParams::new(Headers::new(&state.headers).get_name().unwrap().as_str(), None), parts: parts.clone() }))),
+                        _ => None
+                    };
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Target { parts: parts.clone() });
+                multipart_parser.import_state(state, process_content);
+                multipart_parser.write_all(&body[split..]).unwrap();
+                assert_eq!(MultipartParserState::Finished, multipart_parser.export_state().state);
+
+                let parts = parts.borrow();
+                assert_eq!(2, parts.len());
+                assert_eq!(file1_headers.iter().rev().cloned().collect::<Vec<String>>(), parts[0].0);
+                assert_eq!(b"abc\r\n--boun\r\ndef".to_vec(), parts[0].1);
+                assert_eq!(b"xyz".to_vec(), parts[1].1);
+            }
+
+        // possible boundary in other case (`case_insensitive_boundary`), split by snapshot, is written as received
+        let body: &[u8] = b"--abcd\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nx\r\n--ABcX\r\n--aBc\r\n--ABCD--\r\n";
+        for split in 1..body.len()
             {
-                if self.compare_pos > 0
+                let parts = Rc::new(RefCell::new(vec![]));
+                let state =
                     {
-                        let tmp = &self.empty_string.clone();
-                        let empty_string: &Vec<u8> = &tmp.borrow();
-                        self.headers_builder.write(empty_string[0]);
-
-                        let compare_pos_last = self.compare_pos;
-                        self.compare_pos = 0;
-                        for i in 1..compare_pos_last
-                            {
-                                self.process_header(empty_string[i]);
-                            }
-                    }
-                    else
+                        let mut multipart_parser = MultipartParser::new_owned("AbCd", Target { parts: parts.clone() });
+                        multipart_parser.case_insensitive_boundary(true);
+                        multipart_parser.write_all(&body[..split]).unwrap();
+                        multipart_parser.export_state()
+                    };
+                let process_content: Option<Rc<RefCell<ProcessContent>>> = match state.state
                     {
-                        self.headers_builder.write(c);
-                    }
+                        MultipartParserState::Content => Some(Rc::new(RefCell::new(Recorder { params: ProcessParams::new("a", None), parts: parts.clone() }))),
+                        _ => None
+                    };
+                let mut multipart_parser = MultipartParser::new_owned("AbCd", Target { parts: parts.clone() });
+                multipart_parser.case_insensitive_boundary(true);
+                multipart_parser.import_state(state, process_content);
+                multipart_parser.write_all(&body[split..]).unwrap();
+
+                let parts = parts.borrow();
+                assert_eq!(1, parts.len(), "split {}", split);
+                assert_eq!(b"x\r\n--ABcX\r\n--aBc".to_vec(), parts[0].1, "split {}", split);
             }
     }
 
-    /// Read post header from stream, switch to `Content` when `\r\n` readed (i.e. 2x empty string) or returns to `Header` state if other synbos readed
-    fn process_post_header(&mut self, c: u8) ->()
+    #[test]
+    fn nul_in_parameter() -> ()
     {
-        let (sym_equal, boundary_equal) = self.compare(c, &self.empty_string);
-        if boundary_equal
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a\0b.txt\"\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"file2\"; filename=\"c.txt\"\r\n\r\ndef\r\n--boundary--\r\n";
+        for &strict in &[false, true]
             {
-                self.to_content();
-                return;
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                multipart_parser.strict(strict);
+                multipart_parser.write_all(body).unwrap();
+
+                let target = multipart_parser.into_target();
+                let filenames: Vec<&str> = target.opened.iter().map(|headers| headers.get_filename().unwrap().as_str()).collect();
+                assert_eq!(vec!["ab.txt", "c.txt"], filenames);
+                assert_eq!(if strict { vec!["InvalidParameter filename NUL byte"] } else { vec![] }, target.errors);
             }
 
-        if sym_equal
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.strict(true).fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(body).unwrap_err().kind());
+
+        // percent-encoded NUL, decoded by `decode_param_names`
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a%00b.txt\"\r\n\r\nabc\r\n--boundary--\r\n";
+        for &strict in &[false, true]
             {
-                self.compare_pos+=1;
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                multipart_parser.strict(strict).decode_param_names(true);
+                multipart_parser.write_all(body).unwrap();
+
+                let target = multipart_parser.into_target();
+                assert_eq!("ab.txt", target.opened[0].get_filename().unwrap());
+                assert_eq!(if strict { vec!["InvalidParameter filename NUL byte"] } else { vec![] }, target.errors);
             }
-            else
-            {
-                let tmp = &self.empty_string.clone();
-                let empty_string: &Vec<u8> = &tmp.borrow();
-                self.headers_builder.write(empty_string[0]);
+    }
 
-                let compare_pos_last = self.compare_pos;
-                self.compare_pos = 0;
-                for i in 1..compare_pos_last
+    /// Convert data of part to `i32`, conversion error is returned by `ProcessContentHooks::take_deferred_error` (as derive does)
+    struct Converted
+    {
+        params: ProcessParams,
+        data: Vec<u8>,
+        error: Option<FieldError>
+    }
+
+    impl ProcessContent for Converted
+    {
+        fn open(&mut self, _headers: &Headers) -> () {}
+
+        fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), Error>
+        {
+            self.data.extend(data);
+            Ok(())
+        }
+
+        fn flush(&mut self, _headers: &Headers) -> Result<(), Error>
+        {
+            if let Err(error) = String::from_utf8_lossy(&self.data).parse::<i32>()
+                {
+                    self.error = Some(FieldError::new(self.params.name.clone(), self.data.clone(), error.to_string()));
+                }
+            Ok(())
+        }
+
+        fn get_process_params(&self) -> &ProcessParams
+        {
+            &self.params
+        }
+
+        fn hooks(&mut self) -> Option<&mut ProcessContentHooks>
+        {
+            Some(self)
+        }
+    }
+
+    impl ProcessContentHooks for Converted
+    {
+        fn take_deferred_error(&mut self) -> Option<FieldError>
+        {
+            self.error.take()
+        }
+    }
+
+    /// `Recording` with `required1` required, `Converted` processor for field `number`
+    fn all_errors() -> Recording
+    {
+        let processor = |headers: &Headers| -> Option<Rc<RefCell<ProcessContent>>>
+            {
+                match headers.get_name().map(|name| name.as_str())
                     {
-                        self.process_header(empty_string[i]);
+                        Some("number") => Some(Rc::new(RefCell::new(Converted { params: ProcessParams::new("number", None), data: vec![], error: None }))),
+                        _ => None
                     }
-                self.to_header_continue();
+            };
+        Recording { required: vec!["required1".to_string()], processor: Some(Box::new(processor)), ..Recording::new() }
+    }
+
+    #[test]
+    fn on_errors() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabcdef\r\n--boundary\r\nContent-Disposition: form-data; name=\"text2\"\r\nContent-Type: text/plain\r\nContent-Type: text/html\r\n\r\nx\r\n--boundary\r\nContent-Disposition: form-data; name=\"text3\"; filename=\"a\0b.txt\"\r\n\r\ny\r\n--boundary--\r\n";
+        let mut multipart_parser = MultipartParser::new_owned("boundary", all_errors());
+        multipart_parser.strict(true).text_field_max_size(4).set_min_parts(4).set_default_on_error(OnError::ContinueWithoutError);
+        multipart_parser.write_all(body).unwrap();
+        multipart_parser.flush().unwrap();
+
+        let target = multipart_parser.into_target();
+        let expected: Vec<String> = vec!["SizeLimit text1 4", "DuplicateContentType text2", "InvalidParameter filename NUL byte", "TooFewParts 4 3", "RequiredMissing required1"]
+            .iter()
+            .map(|error| error.to_string())
+            .collect();
+        assert_eq!(expected, target.on_errors);
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", all_errors());
+        multipart_parser.write_all(b"--boundary\r\nContent-Disposition: form-data; name=\"required1\"\r\n\r\nabc\r\n--boundary--\r\n").unwrap();
+        assert!(multipart_parser.into_target().on_errors.is_empty());
+
+        // conversion error with default settings, deferred - as `FieldErrors`
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"number\"\r\n\r\nx\r\n--boundary\r\nContent-Disposition: form-data; name=\"required1\"\r\n\r\nabc\r\n--boundary--\r\n";
+        for &(defer, expected) in &[(false, "FieldError number"), (true, "FieldErrors 1")]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", all_errors());
+                multipart_parser.defer_field_errors(defer);
+                multipart_parser.write_all(body).unwrap();
+                assert_eq!(vec![expected.to_string()], multipart_parser.into_target().on_errors);
             }
     }
 
-    /// Read content from stream, until `boundary_middle` sequence readed
-    fn process_content(&mut self, c: u8, buf: &[u8]) -> Result<(), Error>
+    /// Processor, which sleep in every `write`
+    struct Slow
     {
-        let (sym_equal, boundary_equal) = self.compare(c, &self.boundary_middle);
-        if boundary_equal
+        params: ProcessParams,
+        parts: Rc<RefCell<Vec<(String, Vec<u8>, bool)>>>
+    }
+
+    impl ProcessContent for Slow
+    {
+        fn open(&mut self, _headers: &Headers) -> ()
+        {
+            self.parts.borrow_mut().push((self.params.name.clone(), vec![], false));
+        }
+
+        fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), Error>
+        {
+            thread::sleep(Duration::from_millis(20));
+            self.parts.borrow_mut().last_mut().unwrap().1.extend(data);
+            Ok(())
+        }
+
+        fn flush(&mut self, _headers: &Headers) -> Result<(), Error> { Ok(()) }
+
+        fn abort(&mut self, _headers: &Headers) -> Result<(), Error>
+        {
+            self.parts.borrow_mut().last_mut().unwrap().2 = true;
+            Ok(())
+        }
+
+        fn get_process_params(&self) -> &ProcessParams
+        {
+            &self.params
+        }
+    }
+
+    #[test]
+    fn max_part_duration() -> ()
+    {
+        let head: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"slow\"\r\n\r\nab";
+        let tail: &[u8] = b"cdef\r\n--boundary\r\nContent-Disposition: form-data; name=\"slow\"\r\n\r\nxy\r\n--boundary--\r\n";
+        for &(max_part_duration, ref expected_timeouts) in &[(None, vec![]), (Some(Duration::from_millis(5)), vec!["PartTimeout slow"])]
             {
-                self.processor_flush();
-                self.to_post_boundary();
-                return Ok(())
+                let parts = Rc::new(RefCell::new(vec![]));
+                let slow_parts = parts.clone();
+                let processor = move |headers: &Headers| -> Option<Rc<RefCell<ProcessContent>>>
+                    {
+                        let name = headers.get_name().cloned().unwrap_or_default();
+                        Some(Rc::new(RefCell::new(Slow { params: ProcessParams::new(name, None), parts: slow_parts.clone() })))
+                    };
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { processor: Some(Box::new(processor)), ..Recording::new() });
+                multipart_parser.max_part_duration(max_part_duration);
+                // processor sleeps in `write` of `ab`, timeout is checked in next `write`
+                multipart_parser.write_all(head).unwrap();
+                multipart_parser.write_all(tail).unwrap();
+
+                let target = multipart_parser.into_target();
+                assert_eq!(*expected_timeouts, target.errors);
+                let parts = parts.borrow();
+                let timeout = max_part_duration.is_some();
+                let expected = vec![("slow".to_string(), if timeout { b"ab".to_vec() } else { b"abcdef".to_vec() }, timeout), ("slow".to_string(), b"xy".to_vec(), false)];
+                assert_eq!(expected, *parts);
             }
+    }
 
-        if sym_equal
+    #[test]
+    fn flush_incomplete_final_part() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"text2\"\r\n\r\ndef\r\n";
+        for &flush in &[false, true]
             {
-                if self.compare_pos == 0
+                let mut multipart_parser = MultipartParser::new_owned("boundary", CapturingTarget::new());
+                multipart_parser.flush_incomplete_final_part(flush);
+                multipart_parser.write_all(body).unwrap();
+                multipart_parser.flush().unwrap();
+                assert_eq!(flush, multipart_parser.export_state().state == MultipartParserState::Finished);
+
+                let fields = multipart_parser.into_target().into_fields();
+                let fields: Vec<(&str, &[u8])> = fields.iter().map(|field| (field.name.as_str(), field.raw.as_slice())).collect();
+                if flush
                     {
-                        self.content_end = self.buf_pos;
-                        self.processor_write(buf)?;
+                        assert_eq!(vec![("text1", &b"abc"[..]), ("text2", &b"def"[..])], fields);
+                    }
+                    else
+                    {
+                        assert_eq!(vec![("text1", &b"abc"[..])], fields);
                     }
-                self.compare_pos += 1;
             }
-            else
+    }
+
+    /// Accept decimal prime number only
+    fn prime(data: &[u8]) -> Result<(), String>
+    {
+        let value: u64 = ::std::str::from_utf8(data).ok().and_then(|value| value.parse().ok()).ok_or("not a number".to_string())?;
+        match value >= 2 && (2..value).take_while(|divisor| divisor * divisor <= value).all(|divisor| value % divisor != 0)
             {
-                if self.compare_pos > 0
+                true => Ok(()),
+                false => Err(format!("{} is not prime", value))
+            }
+    }
+
+    #[test]
+    fn add_validator() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"n\"\r\n\r\n13\r\n--boundary\r\nContent-Disposition: form-data; name=\"n\"\r\n\r\n15\r\n--boundary\r\nContent-Disposition: form-data; name=\"m\"\r\n\r\n15\r\n--boundary--\r\n";
+        for &chunk in &[1, 3, body.len()]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+                multipart_parser.add_validator("n", Box::new(prime));
+                for data in body.chunks(chunk)
                     {
-                        let clone = self.boundary_middle.clone();
-                        let vec = clone.borrow();
+                        multipart_parser.write_all(data).unwrap();
+                    }
 
-                        self.processor_write_sym(vec[0])?;
+                let target = multipart_parser.into_target();
+                assert_eq!(vec![("n".to_string(), b"13".to_vec()), ("m".to_string(), b"15".to_vec())], target.fields, "chunk {}", chunk);
+                assert_eq!(vec!["ValidationFailed n 15 is not prime"], target.errors, "chunk {}", chunk);
+            }
 
-                        if self.compare_pos > 1
-                            {
-                                let to = self.compare_pos;
-                                match  self.flow_content(1, to)
-                                    {
-                                        Ok(pos) => self.compare_pos = pos,
-                                        Err(e) => return Err(e)
-                                    };
-                            }
-                        else
-                        {
-                            self.compare_pos = 0;
-                        }
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+        multipart_parser.add_validator("n", Box::new(prime)).fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(body).unwrap_err().kind());
+
+        // data over `text_field_max_size` is not buffered, validation fails without call of validator
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"n\"\r\n\r\n1013\r\n--boundary--\r\n";
+        for &chunk in &[1, 3, body.len()]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                multipart_parser.text_field_max_size(3).add_validator("n", Box::new(prime));
+                for data in body.chunks(chunk)
+                    {
+                        multipart_parser.write_all(data).unwrap();
                     }
+
+                let target = multipart_parser.into_target();
+                assert_eq!(vec!["SizeLimit n 3", "ValidationFailed n Size limit 3 exceeded"], target.errors, "chunk {}", chunk);
             }
-        Ok(())
     }
 
-    /// Special case - part of `boundary_middle` readed from stream, but it is part of content body
-    fn flow_content(&mut self, from: usize, to: usize) -> Result<usize, Error>
+    #[test]
+    fn max_in_flight_memory() -> ()
     {
-        let clone = self.boundary_middle.clone();
-        let vec = clone.borrow();
+        // 8 fields by 100 bytes, 500 bytes in memory allowed: 5 fields received, rest skipped
+        let mut body: Vec<u8> = vec![];
+        for i in 0..8
+            {
+                body.extend(format!("--boundary\r\nContent-Disposition: form-data; name=\"field{}\"\r\n\r\n", i).as_bytes());
+                body.extend(&[b'x'; 100][..]);
+                body.extend(b"\r\n");
+            }
+        body.extend(b"--boundary--\r\n");
+        let received: Vec<String> = (0..5).map(|i| format!("field{}", i)).collect();
 
-        let mut pos: usize = from;
-        while pos < to
+        for &chunk in &[1, 7, body.len()]
             {
-                let c = vec[pos];
-                let (sym_equal, _boundary_equal) = self.compare_at(c, &self.boundary_middle, pos);
-                if sym_equal
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                multipart_parser.set_max_in_flight_memory(Some(500));
+                for data in body.chunks(chunk)
                     {
-                        if pos > from
-                            {
-                                match self.processor_write_from_to(vec.as_ref(), from, pos)
-                                    {
-                                        Ok(_) => (),
-                                        Err(e) => return Err(e)
-                                    }
-                            }
-                        break
+                        multipart_parser.write_all(data).unwrap();
+                    }
+                let target = multipart_parser.into_target();
+                assert_eq!(received, target.names(), "chunk {}", chunk);
+                assert_eq!(vec!["MemoryLimit 500"; 3], target.errors, "chunk {}", chunk);
+
+                // processors (`ProcessContent::buffered_size`)
+                let mut multipart_parser = MultipartParser::new_owned("boundary", CapturingTarget::new());
+                multipart_parser.set_max_in_flight_memory(Some(500)).set_default_on_error(OnError::Skip);
+                for data in body.chunks(chunk)
+                    {
+                        multipart_parser.write_all(data).unwrap();
                     }
-                pos+=1;
+                let fields = multipart_parser.into_target().into_fields();
+                assert_eq!(received, fields.iter().map(|field| field.name.clone()).collect::<Vec<String>>(), "chunk {}", chunk);
             }
 
-        Ok(to - pos)
+        let mut multipart_parser = MultipartParser::new_owned("boundary", CapturingTarget::new());
+        multipart_parser.set_max_in_flight_memory(Some(500)).fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(&body).unwrap_err().kind());
     }
 
-    /// `boundary_middle` successfully read - next may be `--` (end of data) or `\r\n` (header and content)
-    fn process_post_boundary(&mut self, c: u8) ->()
+    #[test]
+    fn content_length_exceeds_max_size() -> ()
     {
-        let (_divider_sym_equal, divider_boundary_equal) = self.compare(c, &self.divider);
-        let (_epilogue_sym_equal, epilogue_boundary_equal) = self.compare(c, &self.epilogue);
-
-        if divider_boundary_equal
+        // `max_size` of `FileLike` is 4: `file1` is rejected by `Content-Length` before data (if handler skips it),
+        // invalid `Content-Length` of `file3` is ignored
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\nContent-Length: 8\r\n\r\nabcdefgh\r\n--boundary\r\nContent-Disposition: form-data; name=\"file2\"; filename=\"b.txt\"\r\nContent-Length: 3\r\n\r\nxyz\r\n--boundary\r\nContent-Disposition: form-data; name=\"file3\"; filename=\"c.txt\"\r\nContent-Length: x\r\n\r\nuvw\r\n--boundary--\r\n";
+        for on_error in &[OnError::Skip, OnError::SkipAndClose]
             {
-                self.to_header();
+                for &chunk in &[1, 5, body.len()]
+                    {
+                        let closed = Rc::new(RefCell::new(vec![]));
+                        let mut multipart_parser = MultipartParser::new_owned("boundary", file_like(on_error.clone(), &closed));
+                        for data in body.chunks(chunk)
+                            {
+                                multipart_parser.write_all(data).unwrap();
+                            }
+                        // `FileLike::write` without `open` panics - no data of `file1` passed to processor
+                        assert_eq!(vec![&b"xyz"[..], b"uvw"], closed.borrow().iter().map(|file| file.as_slice()).collect::<Vec<&[u8]>>());
+                    }
             }
-        if epilogue_boundary_equal
+
+        // handler continues - `file1` is read as part without `Content-Length`, error is fired once
+        for on_error in &[OnError::ContinueWithoutError, OnError::ContinueWithError]
             {
-                self.to_finished();
+                let closed = Rc::new(RefCell::new(vec![]));
+                let mut multipart_parser = MultipartParser::new_owned("boundary", file_like(on_error.clone(), &closed));
+                multipart_parser.write_all(body).unwrap();
+                assert_eq!(vec![&b"abcdefgh"[..], b"xyz", b"uvw"], closed.borrow().iter().map(|file| file.as_slice()).collect::<Vec<&[u8]>>());
+                assert_eq!(1, multipart_parser.fired_errors.len());
             }
-        if !divider_boundary_equal && !epilogue_boundary_equal
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", file_like(OnError::ContinueWithoutError, &Rc::new(RefCell::new(vec![]))));
+        multipart_parser.fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(body).unwrap_err().kind());
+    }
+
+    #[test]
+    fn route_by_content_type() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"a.png\"\r\nContent-Type: image/png\r\n\r\nPNG\r\n--boundary\r\nContent-Disposition: form-data; name=\"notes\"; filename=\"a.txt\"\r\nContent-Type: Text/Plain; charset=utf-8\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"icon\"; filename=\"b.gif\"\r\nContent-Type: image/gif\r\n\r\nGIF\r\n--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\nContent-Type: application/json\r\n\r\n{}\r\n--boundary--\r\n";
+        let images: Rc<RefCell<Vec<Vec<u8>>>> = Rc::new(RefCell::new(vec![]));
+        let texts: Rc<RefCell<Vec<Vec<u8>>>> = Rc::new(RefCell::new(vec![]));
+        let factory = |closed: &Rc<RefCell<Vec<Vec<u8>>>>| -> Box<Fn(&Headers) -> Rc<RefCell<ProcessContent>>>
             {
-                self.compare_pos +=1;
-            }
+                let closed = closed.clone();
+                Box::new(move |headers: &Headers| -> Rc<RefCell<ProcessContent>>
+                    {
+                        Rc::new(RefCell::new(FileLike { params: ProcessParams::new(headers.get_name().cloned().unwrap_or_default(), None), file: None, closed: closed.clone() }))
+                    })
+            };
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+        multipart_parser.route_by_content_type("image/*", factory(&images)).route_by_content_type("text/plain", factory(&texts));
+        multipart_parser.write_all(body).unwrap();
+
+        assert_eq!(vec![b"PNG".to_vec(), b"GIF".to_vec()], *images.borrow());
+        assert_eq!(vec![b"abc".to_vec()], *texts.borrow());
+        // not matched part - text part without processor
+        assert_eq!(vec![("text1".to_string(), b"{}".to_vec())], multipart_parser.into_target().fields);
     }
 
-    /// Call `open` for current processor
-    fn processor_open(&mut self) -> ()
+    #[test]
+    fn spill_by_default() -> ()
     {
-        if let Some(ref mut process_content) = self.process_content
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"small\"; filename=\"a.txt\"\r\n\r\nabc\r\n--boundary\r\nContent-Disposition: form-data; name=\"large\"; filename=\"b.txt\"\r\n\r\nlarge data spilled\r\n--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nxyz\r\n--boundary--\r\n";
+        for &chunk in &[1, 5, body.len()]
             {
-                if let Some(ref headers ) = self.headers
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+                multipart_parser.spill_by_default(Some(env::temp_dir())).spill_threshold(8);
+                for data in body.chunks(chunk)
                     {
-                        process_content.borrow_mut().open(headers);
+                        multipart_parser.write_all(data).unwrap();
                     }
 
+                let names: Vec<&str> = multipart_parser.default_parts().iter().map(|&(ref name, _)| name.as_str()).collect();
+                assert_eq!(vec!["small", "large"], names);
+
+                let small = multipart_parser.default_parts()[0].1.borrow();
+                assert_eq!(Some(&b"abc"[..]), small.bytes());
+                assert!(small.path().is_none());
+
+                let large = multipart_parser.default_parts()[1].1.borrow();
+                assert!(large.bytes().is_none());
+                let path = large.path().unwrap().to_path_buf();
+                assert_eq!(b"large data spilled".to_vec(), fs::read(&path).unwrap());
+                fs::remove_file(path).unwrap();
             }
+
+        // text part is buffered for `on_text_field`, not spilled
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+        multipart_parser.spill_by_default(Some(env::temp_dir())).spill_threshold(1024);
+        multipart_parser.write_all(body).unwrap();
+        assert_eq!(2, multipart_parser.default_parts().len());
+        assert_eq!(vec![("text1".to_string(), b"xyz".to_vec())], multipart_parser.into_target().fields);
+
+        // default - parts without processor are skipped
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { on_error: OnError::Skip, ..Recording::new() });
+        multipart_parser.write_all(body).unwrap();
+        assert!(multipart_parser.default_parts().is_empty());
     }
 
+    #[test]
+    fn case_insensitive_boundary() -> ()
+    {
+        // declared boundary `AbCd`, proxy changed case of boundaries; content contains possible boundary in other case
+        let body: &[u8] = b"--abcd\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nx\r\n--ABcX\r\n--aBc\r\n--ABCD\r\nContent-Disposition: form-data; name=\"b\"\r\n\r\nyz\r\n--abCD--\r\n";
+        for &chunk in &[1, 3, body.len()]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("AbCd", CapturingTarget::new());
+                multipart_parser.case_insensitive_boundary(true);
+                for data in body.chunks(chunk)
+                    {
+                        multipart_parser.write_all(data).unwrap();
+                    }
+                let fields = multipart_parser.into_target().into_fields();
+                let received: Vec<(String, Vec<u8>)> = fields.into_iter().map(|field| (field.name, field.raw)).collect();
+                assert_eq!(vec![("a".to_string(), b"x\r\n--ABcX\r\n--aBc".to_vec()), ("b".to_string(), b"yz".to_vec())], received, "chunk {}", chunk);
+            }
+
+        // strict by default
+        let mut multipart_parser = MultipartParser::new_owned("AbCd", CapturingTarget::new());
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(body).unwrap_err().kind());
+    }
 
-    /// Write `buf` to current processor
-    fn processor_write(&mut self, buf: &[u8]) -> Result<(), Error>
+    /// Keep first `limit` bytes of each part, then stop part
+    struct Prefix
     {
-        let from = self.content_start;
-        let to = self.content_end;
-        self.processor_write_from_to(buf, from, to)
+        params: ProcessParams,
+        limit: usize,
+        data: Vec<u8>,
+        stopped: bool,
+        parts: Rc<RefCell<Vec<Vec<u8>>>>
     }
 
-    /// Write `buf[from...to]` to current processor
-    fn processor_write_from_to(&mut self, buf: &[u8], from: usize, to: usize) -> Result<(), Error>
+    impl ProcessContent for Prefix
     {
-        if self.on_error == OnError::Skip
-            {
+        fn open(&mut self, _headers: &Headers) -> ()
+        {
+        }
 
-                return Ok(());
-            }
+        fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), Error>
+        {
+            assert!(!self.stopped, "write after WriteControl::StopPart");
+            self.data.extend(data);
+            Ok(())
+        }
 
-        if let Some(ref mut process_content) = self.process_content
+        fn flush(&mut self, _headers: &Headers) -> Result<(), Error>
+        {
+            self.parts.borrow_mut().push(self.data.clone());
+            Ok(())
+        }
+
+        fn get_process_params(&self) -> &ProcessParams
+        {
+            &self.params
+        }
+
+        fn hooks(&mut self) -> Option<&mut ProcessContentHooks>
+        {
+            Some(self)
+        }
+    }
+
+    impl ProcessContentHooks for Prefix
+    {
+        fn write_control(&mut self) -> WriteControl
+        {
+            if self.data.len() < self.limit
+                {
+                    return WriteControl::Continue;
+                }
+            self.stopped = true;
+            self.parts.borrow_mut().push(self.data.clone());
+            WriteControl::StopPart
+        }
+    }
+
+    #[test]
+    fn write_control_stop_part() -> ()
+    {
+        // `a` stopped after 4 bytes (no more `write` and `flush`, see `Prefix::write`), `b` is shorter - flushed
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nabcdefgh\r\n--boundary\r\nContent-Disposition: form-data; name=\"b\"\r\n\r\nxy\r\n--boundary--\r\n";
+        for &chunk in &[1, 3, body.len()]
             {
-                if let Some(max_size) = self.content_size_max
+                let parts = Rc::new(RefCell::new(vec![]));
+                let prefix_parts = parts.clone();
+                let processor = move |headers: &Headers| -> Option<Rc<RefCell<ProcessContent>>>
                     {
-                        self.content_size +=  to - from;
+                        let params = ProcessParams::new(headers.get_name().cloned().unwrap_or_default(), None);
+                        Some(Rc::new(RefCell::new(Prefix { params, limit: 4, data: vec![], stopped: false, parts: prefix_parts.clone() })))
+                    };
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording { processor: Some(Box::new(processor)), ..Recording::new() });
+                for data in body.chunks(chunk)
+                    {
+                        multipart_parser.write_all(data).unwrap();
+                    }
+                // `a` is stopped after write, which reached limit
+                let parts = parts.borrow();
+                assert_eq!(2, parts.len(), "chunk {}", chunk);
+                assert!(parts[0].starts_with(b"abcd") && b"abcdefgh".starts_with(&parts[0]), "chunk {}", chunk);
+                assert_eq!(b"xy".to_vec(), parts[1], "chunk {}", chunk);
+                assert!(multipart_parser.into_target().errors.is_empty(), "chunk {}", chunk);
+            }
+    }
 
-                        if self.content_size > max_size
-                            {
-                                match self.on_error
-                                    {
-                                        OnError::Skip => (),
-                                        OnError::ContinueWithoutError => (),
-                                        OnError::ContinueWithError =>
-                                            {
-                                                let tmp = process_content.borrow();
-                                                let process_params = tmp.get_process_params();
-                                                let name = &process_params.name;
-                                                let on_error = self.target.borrow_mut().error( &MultipartParseError::SizeLimit(name.clone(), max_size ));
-                                                match on_error
-                                                    {
-                                                        Ok(on_error) =>
-                                                            {
-                                                                self.on_error = on_error;
+    #[test]
+    fn binary_round_trip() -> ()
+    {
+        // binary content salted with partial matches of `\r\n--boundary` (at start, in middle and at end of part);
+        // whole `\r\n--boundary` never occurs in content (RFC 2046)
+        let salts: [&[u8]; 10] = [b"\r", b"\n", b"\r\n", b"\r\n-", b"\r\n--", b"\r\n--b", b"\r\n--boundar", b"\r\r\n--bo", b"\r\n-\r\n--", b"\r\n--boundar\r\n--bou"];
+        let mut content: Vec<u8> = vec![];
+        let mut seed: u32 = 12345;
+        for i in 0..200
+            {
+                content.extend_from_slice(salts[i % salts.len()]);
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                // `y` (or `Y` with `case_insensitive_boundary`) may complete `\r\n--boundar`
+                content.push(match (seed >> 16) as u8 { b'y' | b'Y' => 0, byte => byte });
+            }
+        for salt in salts.iter()
+            {
+                let mut file = salt.to_vec();
+                file.extend(&content);
+                file.extend_from_slice(salt);
 
-                                                            },
-                                                        Err(e) => return Err(e)
-                                                    }
-                                                self.content_size_max = None;
-                                            }
-                                    }
-                            }
-                    }
+                let mut body: Vec<u8> = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.bin\"\r\n\r\n".to_vec();
+                body.extend(&file);
+                body.extend_from_slice(b"\r\n--boundary\r\nContent-Disposition: form-data; name=\"file2\"; filename=\"b.bin\"\r\n\r\n");
+                body.extend_from_slice(salt);
+                body.extend_from_slice(b"\r\n--boundary--\r\n");
 
-                if let Some(ref headers ) = self.headers
+                for &(chunk, case_insensitive) in (1..8).chain(Some(body.len())).flat_map(|chunk| vec![(chunk, false), (chunk, true)]).collect::<Vec<(usize, bool)>>().iter()
                     {
-                        let data: Vec<u8> = buf[from..to].to_vec();
-                        process_content.borrow_mut().write(&headers, &data);
+                        let mut multipart_parser = MultipartParser::new_owned("boundary", CapturingTarget::new());
+                        multipart_parser.case_insensitive_boundary(case_insensitive);
+                        for data in body.chunks(chunk)
+                            {
+                                multipart_parser.write_all(data).unwrap();
+                            }
+                        let fields = multipart_parser.into_target().into_fields();
+                        assert_eq!(2, fields.len(), "salt {:?}, chunk {}, case_insensitive {}", salt, chunk, case_insensitive);
+                        assert!(file == fields[0].raw, "salt {:?}, chunk {}, case_insensitive {}", salt, chunk, case_insensitive);
+                        assert_eq!(salt.to_vec(), fields[1].raw, "salt {:?}, chunk {}, case_insensitive {}", salt, chunk, case_insensitive);
                     }
-
             }
-        Ok(())
     }
 
+    #[test]
+    fn malformed_first_boundary() -> ()
+    {
+        let body: &[u8] = b"--bounXary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc\r\n--boundary--\r\n";
+        for &chunk in &[1, 4, body.len()]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                let error = body.chunks(chunk).map(|data| multipart_parser.write(data)).find(|result| result.is_err()).unwrap().unwrap_err();
+                assert_eq!(ErrorKind::InvalidData, error.kind(), "chunk {}", chunk);
+                // data after error is ignored
+                multipart_parser.write_all(b"--boundary\r\n").unwrap();
+                assert_eq!(vec!["MalformedBoundary 6"], multipart_parser.into_target().errors, "chunk {}", chunk);
+            }
+    }
 
-    /// Write one symbol `c` to current processor
-    fn processor_write_sym(&mut self, c: u8) -> Result<(), Error>
+    #[test]
+    fn max_total_size() -> ()
     {
-        let a = [c];
-        self.processor_write_from_to(&a, 0, 1)
+        // 8 small fields, limit is just after start of 4th field: 3 fields received, parsing stopped
+        let mut body: Vec<u8> = vec![];
+        let mut max_size = 0;
+        for i in 0..8
+            {
+                body.extend(format!("--boundary\r\nContent-Disposition: form-data; name=\"field{}\"\r\n\r\n", i).as_bytes());
+                body.extend(&[b'x'; 10][..]);
+                body.extend(b"\r\n");
+                if i == 2
+                    {
+                        max_size = body.len() + 20;
+                    }
+            }
+        body.extend(b"--boundary--\r\n");
+        let received: Vec<String> = (0..3).map(|i| format!("field{}", i)).collect();
+
+        for &chunk in &[1, 7, body.len()]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                multipart_parser.set_max_total_size(Some(max_size));
+                let error = body.chunks(chunk).map(|data| multipart_parser.write(data)).find(|result| result.is_err()).unwrap().unwrap_err();
+                assert_eq!(ErrorKind::InvalidData, error.kind(), "chunk {}", chunk);
+                // next data is rejected, error is fired once
+                assert!(multipart_parser.write(b"--boundary\r\n").is_err());
+                let target = multipart_parser.into_target();
+                match chunk == body.len()
+                    {
+                        true => assert!(target.fields.is_empty()),
+                        false => assert_eq!(received, target.names(), "chunk {}", chunk)
+                    }
+                assert_eq!(vec![format!("TotalSizeLimit {}", max_size)], target.errors, "chunk {}", chunk);
+            }
+
+        // unlimited by default
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.write_all(&body).unwrap();
+        assert_eq!(8, multipart_parser.into_target().fields.len());
     }
 
-    /// Call `flush` for current processor
-    fn processor_flush(&mut self) -> ()
+    #[test]
+    fn max_parts() -> ()
     {
-        if let Some(ref mut process_content) = self.process_content
+        // 4 parts, 3 allowed: 4th part is not processed, parsing finished
+        let mut body: Vec<u8> = vec![];
+        for i in 0..4
             {
-                if let Some(ref headers ) = self.headers
+                body.extend(format!("--boundary\r\nContent-Disposition: form-data; name=\"field{}\"\r\n\r\nvalue\r\n", i).as_bytes());
+            }
+        body.extend(b"--boundary--\r\n");
+        let received: Vec<String> = (0..3).map(|i| format!("field{}", i)).collect();
+
+        for &chunk in &[1, 7, body.len()]
+            {
+                let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+                multipart_parser.set_max_parts(Some(3));
+                for data in body.chunks(chunk)
                     {
-                        process_content.borrow_mut().flush(&headers);
+                        multipart_parser.write_all(data).unwrap();
                     }
+                let target = multipart_parser.into_target();
+                assert_eq!(received, target.names(), "chunk {}", chunk);
+                assert_eq!(vec!["TooManyParts 3"], target.errors, "chunk {}", chunk);
+                assert!(target.finished(), "chunk {}", chunk);
             }
-        self.process_content = None;
+
+        // limit is not exceeded
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.set_max_parts(Some(4));
+        multipart_parser.write_all(&body).unwrap();
+        let target = multipart_parser.into_target();
+        assert_eq!(4, target.fields.len());
+        assert!(target.errors.is_empty());
+
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Recording::new());
+        multipart_parser.set_max_parts(Some(3)).fail_fast(true);
+        assert_eq!(ErrorKind::InvalidData, multipart_parser.write(&body).unwrap_err().kind());
     }
-}
+
+    #[test]
+    fn boundary() -> ()
+    {
+        assert_eq!(Some("abc".to_string()), boundary_from_content_type("multipart/form-data; boundary=abc"));
+        assert_eq!(Some("a b".to_string()), boundary_from_content_type("Multipart/Form-Data;charset=utf-8; BOUNDARY=\"a b\""));
+        assert_eq!(None, boundary_from_content_type("multipart/form-data"));
+        assert_eq!(None, boundary_from_content_type("multipart/form-data; boundary="));
+        assert_eq!(None, boundary_from_content_type("text/plain; boundary=abc"));
+    }
+
+    #[test]
+    fn boundary_with_spaces() -> ()
+    {
+        let boundary = boundary_from_content_type("multipart/form-data; boundary=\"my boundary value\"; charset=utf-8").unwrap();
+        assert_eq!("my boundary value", boundary);
+
+        let body: &[u8] = b"--my boundary value\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc\r\n--my boundary\r\n--my boundary value--\r\n";
+        let parts = parse_with(&boundary, body, body.len());
+        assert_eq!(1, parts.len());
+        assert_eq!(b"abc\r\n--my boundary".to_vec(), parts[0].1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,300 @@
+//! Build `multipart/form-data` (RFC 7578) body - reference encoder for `MultipartParser`
+//!
+//! ```rust,ignore
+//! let mut multipart_writer = MultipartWriter::new("boundary");
+//! multipart_writer
+//!     .add_field("text1", b"text default")
+//!     .add_file("file1", "a.txt", "text/plain", b"Content of a.txt.");
+//! let body: Vec<u8> = multipart_writer.finish();
+//!
+//! // or stream body to any `std::io::Write`
+//! let mut multipart_writer = MultipartWriter::from_writer("boundary", socket);
+//! multipart_writer.add_field("text1", b"text default");
+//! let socket = multipart_writer.close()?;
+//! ```
+
+use std::io::{Write, Error as IOError};
+use boundary_builder::{BoundaryBuilder};
+use ::gnitive_multipart::{Header};
+
+
+/// Build `multipart/form-data` body part by part.
+///
+/// Body is written to inner `std::io::Write` (`Vec<u8>` by default, see `new`) while parts are added, without buffering,
+/// ex.: to socket of transcoding proxy (see `from_writer`). First error of inner writer is stored (see `error`),
+/// no data written after error.
+pub struct MultipartWriter<W: Write = Vec<u8>>
+{
+    /// Boundary without `--` prelude
+    boundary: Vec<u8>,
+
+    /// Target of body
+    writer: W,
+
+    /// `true` if at least one part added
+    has_parts: bool,
+
+    /// First error of inner writer
+    error: Option<IOError>
+}
+
+impl MultipartWriter<Vec<u8>>
+{
+    /// Build body in memory, see `finish`
+    pub fn new(boundary: &str) -> MultipartWriter<Vec<u8>>
+    {
+        MultipartWriter::from_writer(boundary, vec![])
+    }
+
+    /// Append closing boundary and return whole body
+    pub fn finish(self) -> Vec<u8>
+    {
+        match self.close()
+            {
+                Ok(body) => body,
+                // write to `Vec` never fails
+                Err(_) => vec![]
+            }
+    }
+}
+
+impl <W: Write>MultipartWriter<W>
+{
+    /// Write body to `writer` part by part, see `close`
+    pub fn from_writer(boundary: &str, writer: W) -> MultipartWriter<W>
+    {
+        MultipartWriter
+            {
+                boundary: boundary.as_bytes().to_vec(),
+                writer,
+                has_parts: false,
+                error: None
+            }
+    }
+
+    /// Boundary, to use in `Content-Type: multipart/form-data; boundary=<boundary>` and `MultipartParser`
+    pub fn boundary(&self) -> String
+    {
+        String::from_utf8_lossy(&self.boundary).to_string()
+    }
+
+    /// Add text field
+    ///
+    /// ```text
+    /// Content-Disposition: form-data; name="<name>"
+    /// ```
+    pub fn add_field(&mut self, name: &str, value: &[u8]) -> &mut Self
+    {
+        let headers = vec![
+            format!("Content-Disposition: form-data; name=\"{}\"", Header::quote(name))
+        ];
+        self.add_part(&headers, value)
+    }
+
+    /// Add file, `\r` and `\n` are removed from `content_type` (see `begin_part`)
+    ///
+    /// ```text
+    /// Content-Disposition: form-data; name="<name>"; filename="<filename>"
+    /// Content-Type: <content_type>
+    /// ```
+    pub fn add_file(&mut self, name: &str, filename: &str, content_type: &str, bytes: &[u8]) -> &mut Self
+    {
+        let headers = vec![
+            format!("Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"", Header::quote(name), Header::quote(filename)),
+            format!("Content-Type: {}", content_type)
+        ];
+        self.add_part(&headers, bytes)
+    }
+
+    /// Add part with any `headers` (lines, see `begin_part`)
+    pub fn add_part(&mut self, headers: &Vec<String>, bytes: &[u8]) -> &mut Self
+    {
+        self.begin_part(headers).append(bytes)
+    }
+
+    /// Start new part with any `headers` (lines), content of part added by `append`.
+    /// `\r` and `\n` are removed from header lines - values cannot add header lines or boundary.
+    pub fn begin_part(&mut self, headers: &Vec<String>) -> &mut Self
+    {
+        let mut data = self.next_boundary();
+        for header in headers
+            {
+                data.extend(header.bytes().filter(|&c| c != b'\r' && c != b'\n'));
+                data.append_crlf();
+            }
+        data.append_crlf();
+        self.write_data(&data);
+        self
+    }
+
+    /// Append `bytes` to content of current part
+    pub fn append(&mut self, bytes: &[u8]) -> &mut Self
+    {
+        self.write_data(bytes);
+        self
+    }
+
+    /// Return first error of inner writer, if any
+    pub fn error(&self) -> Option<&IOError>
+    {
+        self.error.as_ref()
+    }
+
+    /// Append closing boundary, flush and return inner writer, or first error of inner writer
+    pub fn close(mut self) -> Result<W, IOError>
+    {
+        let mut data: Vec<u8> = vec![];
+        if self.has_parts
+            {
+                data.append_crlf();
+            }
+        data
+            .append_prelude()
+            .append_boundary(&self.boundary)
+            .append_prelude()
+            .append_crlf();
+        self.write_data(&data);
+        if self.error.is_none()
+            {
+                if let Err(error) = self.writer.flush()
+                    {
+                        self.error = Some(error);
+                    }
+            }
+        match self.error
+            {
+                Some(error) => Err(error),
+                None => Ok(self.writer)
+            }
+    }
+
+    /// Boundary before next part
+    ///
+    /// ```text
+    /// --<boundary>\r\n         - first part
+    /// \r\n--<boundary>\r\n     - all next parts
+    /// ```
+    fn next_boundary(&mut self) -> Vec<u8>
+    {
+        let mut data: Vec<u8> = vec![];
+        if self.has_parts
+            {
+                data.append_crlf();
+            }
+        data
+            .append_prelude()
+            .append_boundary(&self.boundary)
+            .append_crlf();
+        self.has_parts = true;
+        data
+    }
+
+    /// Write `data` to inner writer, store first error
+    fn write_data(&mut self, data: &[u8]) -> ()
+    {
+        if self.error.is_none()
+            {
+                if let Err(error) = self.writer.write_all(data)
+                    {
+                        self.error = Some(error);
+                    }
+            }
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::{MultipartWriter};
+    use std::cell::{RefCell};
+    use std::io::{Write, Error as IOError, ErrorKind};
+    use std::rc::{Rc};
+    use ::multipart_parser::tests::{parse_with};
+
+    /// Store data in shared buffer, fail to write more than `limit` bytes
+    struct Limited
+    {
+        data: Rc<RefCell<Vec<u8>>>,
+        limit: usize
+    }
+
+    impl Write for Limited
+    {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, IOError>
+        {
+            if self.data.borrow().len() + buf.len() > self.limit
+                {
+                    return Err(IOError::new(ErrorKind::Other, "limit"));
+                }
+            self.data.borrow_mut().extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), IOError> { Ok(()) }
+    }
+
+    #[test]
+    fn multipart_writer() -> ()
+    {
+        let mut multipart_writer = MultipartWriter::new("735323031399963166993862150");
+        multipart_writer
+            .add_field("text1", b"text default")
+            .add_file("file1", "a.txt", "text/plain", b"Content of a.txt.\r\n")
+            .add_field("empty", b"");
+        let boundary = multipart_writer.boundary();
+        let body = multipart_writer.finish();
+
+        for chunk in 1..body.len() + 1
+            {
+                let parts = parse_with(&boundary, &body, chunk);
+                assert_eq!(3, parts.len());
+
+                assert_eq!(vec!["Content-Disposition: form-data; name=\"text1\"".to_string()], parts[0].0);
+                assert_eq!(b"text default".to_vec(), parts[0].1);
+
+                assert_eq!(b"Content of a.txt.\r\n".to_vec(), parts[1].1);
+                assert_eq!("Content-Type: text/plain", parts[1].0[1]);
+
+                assert_eq!(b"".to_vec(), parts[2].1);
+            }
+    }
+
+    #[test]
+    fn header_injection() -> ()
+    {
+        let mut multipart_writer = MultipartWriter::new("boundary");
+        multipart_writer.add_file("file1", "a.txt", "text/plain\r\nX-Injected: 1\r\n\r\n--boundary--", b"abc");
+        let body = multipart_writer.finish();
+
+        let parts = parse_with("boundary", &body, body.len());
+        assert_eq!(1, parts.len());
+        assert_eq!(vec!["Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"", "Content-Type: text/plainX-Injected: 1--boundary--"], parts[0].0);
+        assert_eq!(b"abc".to_vec(), parts[0].1);
+    }
+
+    #[test]
+    fn from_writer() -> ()
+    {
+        let data = Rc::new(RefCell::new(vec![]));
+        let mut multipart_writer = MultipartWriter::from_writer("boundary", Limited { data: data.clone(), limit: 1000 });
+        multipart_writer.add_field("text1", b"text default");
+        // written while part is added
+        assert_eq!(b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\ntext default".to_vec(), *data.borrow());
+        multipart_writer.begin_part(&vec!["Content-Disposition: form-data; name=\"text2\"".to_string()]).append(b"a").append(b"b");
+        assert!(multipart_writer.close().is_ok());
+        let body = data.borrow().clone();
+        let parts = parse_with("boundary", &body, body.len());
+        assert_eq!(b"ab".to_vec(), parts[1].1);
+
+        // first error is stored, nothing written after it
+        let data = Rc::new(RefCell::new(vec![]));
+        let mut multipart_writer = MultipartWriter::from_writer("boundary", Limited { data: data.clone(), limit: 60 });
+        multipart_writer.add_field("text1", b"a").add_field("text2", b"b");
+        assert_eq!(ErrorKind::Other, multipart_writer.error().unwrap().kind());
+        let written = data.borrow().len();
+        multipart_writer.append(b"c");
+        assert_eq!(written, data.borrow().len());
+        assert!(multipart_writer.close().is_err());
+    }
+}
\ No newline at end of file
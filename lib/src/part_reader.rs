@@ -0,0 +1,487 @@
+//! Pull API for `multipart/form-data` (RFC 7578): read headers of next part, then (optionally) its body.
+//!
+//! Unlike `MultipartParser`, no `ProcessContent` required - caller decides what to do with body
+//! after headers read, body is not buffered. Preamble before first boundary is skipped.
+//!
+//! ```rust,ignore
+//! let mut part_reader = PartReader::new_from_str("boundary", reader);
+//! while let Some(headers) = part_reader.next_part_headers()
+//!     {
+//!         let headers = headers?;
+//!         if headers.get_name().map(|name| name == "file1").unwrap_or(false)
+//!             {
+//!                 io::copy(&mut part_reader.body(), &mut file)?;
+//!             }
+//!         // body of other parts skipped
+//!     }
+//! ```
+
+use std::cmp::{min, max};
+use std::io::{Read, Error as IOError, ErrorKind};
+use memchr::{memchr};
+use boundary_builder::{BoundaryBuilder};
+use ::gnitive_multipart::{Headers};
+
+
+/// Size of chunk for reading from inner reader
+const CHUNK_SIZE: usize = 4096;
+
+/// Default maximum size of headers block of part (see `PartReader::max_headers_size`)
+pub const HEADERS_MAX_SIZE: usize = 16 * 1024;
+
+
+#[derive(PartialEq)]
+enum PartReaderState
+{
+    /// Unread data starts inside preamble (before first boundary)
+    Preamble,
+
+    /// Unread data starts with boundary
+    Boundary,
+
+    /// Unread data starts inside body of current part
+    Body,
+
+    /// Closing boundary read or error occurred
+    Finished
+}
+
+
+/// Read parts of `multipart/form-data` from inner `std::io::Read` one by one
+pub struct PartReader<R: Read>
+{
+    /// Inner reader
+    reader: R,
+
+    /// `\r\n--<boundary>`
+    boundary: Vec<u8>,
+
+    /// Data read from `reader`, `buf[pos..]` is not processed yet
+    buf: Vec<u8>,
+
+    /// Start of not processed data in `buf`
+    pos: usize,
+
+    /// No boundary starts in `buf[pos..scanned]` (body of part): data is not searched again
+    scanned: usize,
+
+    /// Maximum size of headers block of part, including `\r\n` of lines
+    max_headers_size: usize,
+
+    state: PartReaderState
+}
+
+impl <R: Read>PartReader<R>
+{
+    /// Create `PartReader` with known string `boundary`
+    pub fn new_from_str(boundary: &str, reader: R) -> PartReader<R>
+    {
+        PartReader::new_from_vec(boundary.as_bytes().to_vec(), reader)
+    }
+
+    /// Create `PartReader` with known vector `boundary`
+    pub fn new_from_vec(boundary: Vec<u8>, reader: R) -> PartReader<R>
+    {
+        let mut boundary_middle: Vec<u8> = Vec::new();
+        boundary_middle
+            .append_crlf()
+            .append_prelude()
+            .append_boundary(&boundary);
+
+        // first boundary has no `\r\n` in head - add it, so all boundaries are equal
+        let mut buf: Vec<u8> = Vec::new();
+        buf.append_crlf();
+
+        PartReader
+            {
+                reader,
+                boundary: boundary_middle,
+                buf,
+                pos: 0,
+                scanned: 0,
+                max_headers_size: HEADERS_MAX_SIZE,
+                state: PartReaderState::Preamble
+            }
+    }
+
+    /// Maximum size of headers block of part (all header lines with `\r\n`), default `HEADERS_MAX_SIZE`.
+    /// `next_part_headers` returns `Err` with `ErrorKind::InvalidData` for larger headers, headers are not buffered more.
+    pub fn max_headers_size(&mut self, max_size: usize) -> &mut Self
+    {
+        self.max_headers_size = max_size;
+        self
+    }
+
+    /// Advance to next part and return its headers, body of part left unread (see `body`).
+    /// Unread rest of body of previous part is skipped.
+    ///
+    /// Return `None` after closing boundary or error.
+    pub fn next_part_headers(&mut self) -> Option<Result<Headers, IOError>>
+    {
+        match self.read_headers()
+            {
+                Ok(Some(headers)) => Some(Ok(headers)),
+                Ok(None) => None,
+                Err(error) =>
+                    {
+                        self.state = PartReaderState::Finished;
+                        Some(Err(error))
+                    }
+            }
+    }
+
+    /// Body of current part, `std::io::Read` returns `Ok(0)` at end of body
+    pub fn body<'a>(&'a mut self) -> PartBody<'a, R>
+    {
+        PartBody
+            {
+                part_reader: self
+            }
+    }
+
+    /// Return inner reader
+    pub fn into_inner(self) -> R
+    {
+        self.reader
+    }
+
+    fn read_headers(&mut self) -> Result<Option<Headers>, IOError>
+    {
+        if self.state == PartReaderState::Body || self.state == PartReaderState::Preamble
+            {
+                let mut sink = [0u8; CHUNK_SIZE];
+                while self.read_body(&mut sink)? > 0 {}
+            }
+        if self.state == PartReaderState::Finished
+            {
+                return Ok(None);
+            }
+
+        // boundary and `\r\n` (next part) or `--` (end of data)
+        let boundary_len = self.boundary.len();
+        self.fill_to(boundary_len + 2)?;
+        if self.buf[self.pos..self.pos + boundary_len] != self.boundary[..]
+            {
+                return Err(IOError::new(ErrorKind::InvalidData, "Cannot parse boundary"));
+            }
+        match &self.buf[self.pos + boundary_len..self.pos + boundary_len + 2]
+            {
+                b"\r\n" => (),
+                b"--" =>
+                    {
+                        self.state = PartReaderState::Finished;
+                        return Ok(None);
+                    },
+                _ => return Err(IOError::new(ErrorKind::InvalidData, "Cannot parse boundary end"))
+            }
+        self.pos += boundary_len + 2;
+
+        let mut lines: Vec<String> = vec![];
+        let mut headers_size: usize = 0;
+        // offset from `pos`, before which no `\r\n` found
+        let mut scanned: usize = 0;
+        loop
+            {
+                let pos = match PartReader::<R>::find(&self.buf[self.pos + scanned..], b"\r\n")
+                    {
+                        Some(offset) => scanned + offset,
+                        None =>
+                            {
+                                let unread = self.buf.len() - self.pos;
+                                if headers_size + unread > self.max_headers_size
+                                    {
+                                        return Err(self.headers_too_large());
+                                    }
+                                // last byte may be `\r` of `\r\n`
+                                scanned = unread.saturating_sub(1);
+                                if !self.fill()?
+                                    {
+                                        return Err(IOError::new(ErrorKind::UnexpectedEof, "Multipart headers truncated"));
+                                    }
+                                continue;
+                            }
+                    };
+                headers_size += pos + 2;
+                if headers_size > self.max_headers_size
+                    {
+                        return Err(self.headers_too_large());
+                    }
+                if pos == 0
+                    {
+                        self.pos += 2;
+                        break;
+                    }
+                lines.push(String::from_utf8_lossy(&self.buf[self.pos..self.pos + pos]).to_string());
+                self.pos += pos + 2;
+                scanned = 0;
+            }
+
+        self.state = PartReaderState::Body;
+        self.scanned = self.pos;
+        Ok(Some(Headers::new(&lines)))
+    }
+
+    fn headers_too_large(&self) -> IOError
+    {
+        IOError::new(ErrorKind::InvalidData, format!("Multipart headers exceed {} bytes", self.max_headers_size))
+    }
+
+    /// Read body of current part (or preamble) until boundary.
+    ///
+    /// Boundary is searched in data after `scanned` only, so every byte is searched once (not for every `read`).
+    fn read_body(&mut self, out: &mut [u8]) -> Result<usize, IOError>
+    {
+        if (self.state != PartReaderState::Body && self.state != PartReaderState::Preamble) || out.is_empty()
+            {
+                return Ok(0);
+            }
+
+        loop
+            {
+                // bytes before boundary, or all bytes except tail, which may be start of boundary
+                let found = match PartReader::<R>::find(&self.buf[self.scanned..], &self.boundary)
+                    {
+                        Some(offset) =>
+                            {
+                                self.scanned += offset;
+                                true
+                            },
+                        None =>
+                            {
+                                self.scanned = max(self.scanned, self.buf.len().saturating_sub(self.boundary.len() - 1));
+                                false
+                            }
+                    };
+
+                let available = self.scanned - self.pos;
+                if available > 0
+                    {
+                        let size = min(available, out.len());
+                        out[..size].copy_from_slice(&self.buf[self.pos..self.pos + size]);
+                        self.pos += size;
+                        return Ok(size);
+                    }
+                if found
+                    {
+                        self.state = PartReaderState::Boundary;
+                        return Ok(0);
+                    }
+                if !self.fill()?
+                    {
+                        return Err(IOError::new(ErrorKind::UnexpectedEof, "Multipart body truncated"));
+                    }
+            }
+    }
+
+    /// Read next chunk from inner reader to `buf`, return `false` at end of data.
+    /// Processed data is removed from `buf` before.
+    fn fill(&mut self) -> Result<bool, IOError>
+    {
+        if self.pos > 0
+            {
+                self.buf.drain(..self.pos);
+                self.scanned = self.scanned.saturating_sub(self.pos);
+                self.pos = 0;
+            }
+
+        let mut chunk = [0u8; CHUNK_SIZE];
+        loop
+            {
+                match self.reader.read(&mut chunk)
+                    {
+                        Ok(0) => return Ok(false),
+                        Ok(size) =>
+                            {
+                                self.buf.extend(&chunk[..size]);
+                                return Ok(true);
+                            },
+                        Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                        Err(e) => return Err(e)
+                    }
+            }
+    }
+
+    /// Read from inner reader until `buf` contains at least `len` not processed bytes
+    fn fill_to(&mut self, len: usize) -> Result<(), IOError>
+    {
+        while self.buf.len() - self.pos < len
+            {
+                if !self.fill()?
+                    {
+                        return Err(IOError::new(ErrorKind::UnexpectedEof, "Multipart boundary truncated"));
+                    }
+            }
+        Ok(())
+    }
+
+    /// Position of first `needle` in `haystack`: candidates are found by first byte of `needle` (`memchr`)
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize>
+    {
+        let mut from: usize = 0;
+        while let Some(offset) = memchr(needle[0], &haystack[from..])
+            {
+                let start = from + offset;
+                if haystack.len() - start < needle.len()
+                    {
+                        return None;
+                    }
+                if &haystack[start..start + needle.len()] == needle
+                    {
+                        return Some(start);
+                    }
+                from = start + 1;
+            }
+        None
+    }
+}
+
+
+/// Body of current part of `PartReader`
+pub struct PartBody<'a, R: Read + 'a>
+{
+    part_reader: &'a mut PartReader<R>
+}
+
+impl <'a, R: Read>Read for PartBody<'a, R>
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError>
+    {
+        if self.part_reader.state == PartReaderState::Preamble
+            {
+                return Ok(0);
+            }
+        let result = self.part_reader.read_body(buf);
+        if result.is_err()
+            {
+                self.part_reader.state = PartReaderState::Finished;
+            }
+        result
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::{PartReader, CHUNK_SIZE};
+    use std::io::{Read, Error as IOError, ErrorKind};
+
+    /// Return data from `data` by `chunk` bytes
+    struct Chunked<'a>
+    {
+        data: &'a [u8],
+        chunk: usize
+    }
+
+    impl <'a>Read for Chunked<'a>
+    {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError>
+        {
+            let size = [self.chunk, buf.len(), self.data.len()].iter().cloned().min().unwrap();
+            buf[..size].copy_from_slice(&self.data[..size]);
+            self.data = &self.data[size..];
+            Ok(size)
+        }
+    }
+
+    #[test]
+    fn part_reader() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"skipped\"\r\n\r\n--boundar\r\n--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\nContent of a.txt.\r\n\r\n--boundary--\r\n";
+        for chunk in 1..body.len() + 1
+            {
+                let mut part_reader = PartReader::new_from_str("boundary", Chunked { data: body, chunk });
+
+                let headers = part_reader.next_part_headers().unwrap().unwrap();
+                assert_eq!("skipped", headers.get_name().unwrap());
+
+                let headers = part_reader.next_part_headers().unwrap().unwrap();
+                assert_eq!("file1", headers.get_name().unwrap());
+                assert_eq!("a.txt", headers.get_filename().unwrap());
+
+                let mut content: Vec<u8> = vec![];
+                part_reader.body().read_to_end(&mut content).unwrap();
+                assert_eq!(b"Content of a.txt.\r\n".to_vec(), content);
+
+                assert!(part_reader.next_part_headers().is_none());
+            }
+    }
+
+    #[test]
+    fn part_reader_preamble() -> ()
+    {
+        let body: &[u8] = b"This is preamble.\r\n--boundar\r\n--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc\r\n--boundary--\r\n";
+        for chunk in 1..body.len() + 1
+            {
+                let mut part_reader = PartReader::new_from_str("boundary", Chunked { data: body, chunk });
+                // no body before first part
+                let mut content: Vec<u8> = vec![];
+                part_reader.body().read_to_end(&mut content).unwrap();
+                assert!(content.is_empty());
+
+                assert_eq!("text1", part_reader.next_part_headers().unwrap().unwrap().get_name().unwrap());
+                part_reader.body().read_to_end(&mut content).unwrap();
+                assert_eq!(b"abc".to_vec(), content);
+                assert!(part_reader.next_part_headers().is_none());
+            }
+    }
+
+    #[test]
+    fn part_reader_small_reads() -> ()
+    {
+        // body with many starts of boundary, read by 1 byte: data is searched once, not for every `read`
+        let mut data: Vec<u8> = vec![];
+        for i in 0..10000
+            {
+                data.extend(format!("\r\n--bound{}", i % 10).as_bytes());
+            }
+        let mut body: Vec<u8> = b"--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\n\r\n".to_vec();
+        body.extend(&data);
+        body.extend(b"\r\n--boundary--\r\n");
+
+        let mut part_reader = PartReader::new_from_str("boundary", Chunked { data: &body, chunk: CHUNK_SIZE });
+        assert_eq!("file1", part_reader.next_part_headers().unwrap().unwrap().get_name().unwrap());
+        let mut content: Vec<u8> = vec![];
+        let mut byte = [0u8; 1];
+        while part_reader.body().read(&mut byte).unwrap() > 0
+            {
+                content.push(byte[0]);
+            }
+        assert_eq!(data, content);
+        assert!(part_reader.buf.len() <= 2 * CHUNK_SIZE);
+        assert!(part_reader.next_part_headers().is_none());
+    }
+
+    #[test]
+    fn part_reader_truncated() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc";
+        let mut part_reader = PartReader::new_from_str("boundary", body);
+        assert!(part_reader.next_part_headers().unwrap().is_ok());
+
+        let mut content: Vec<u8> = vec![];
+        assert!(part_reader.body().read_to_end(&mut content).is_err());
+        assert!(part_reader.next_part_headers().is_none());
+    }
+
+    #[test]
+    fn part_reader_max_headers_size() -> ()
+    {
+        // 48 bytes of headers of first part: long line without `\r\n` is not buffered up to end of data
+        let mut body: Vec<u8> = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\nabc\r\n--boundary\r\nX-Long: ".to_vec();
+        body.extend(&[b'x'; 100000][..]);
+        for &chunk in &[1, 7, body.len()]
+            {
+                let mut part_reader = PartReader::new_from_str("boundary", Chunked { data: &body, chunk });
+                part_reader.max_headers_size(48);
+                assert_eq!("text1", part_reader.next_part_headers().unwrap().unwrap().get_name().unwrap());
+                assert_eq!(ErrorKind::InvalidData, part_reader.next_part_headers().unwrap().err().unwrap().kind());
+                assert!(part_reader.next_part_headers().is_none());
+                assert!(part_reader.buf.len() <= 48 + CHUNK_SIZE);
+
+                let mut part_reader = PartReader::new_from_str("boundary", Chunked { data: &body, chunk });
+                part_reader.max_headers_size(47);
+                assert_eq!(ErrorKind::InvalidData, part_reader.next_part_headers().unwrap().err().unwrap().kind());
+            }
+    }
+}
\ No newline at end of file
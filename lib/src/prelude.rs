@@ -0,0 +1,9 @@
+//! Commonly used items in one import.
+//!
+//! ```rust,ignore
+//! use gnitive_multipart::prelude::*;
+//! ```
+
+pub use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessContentHooks, ProcessParams, Headers, OnError, WriteControl, MultipartParseError, ToMultipartParseError};
+pub use ::multipart_parser::{MultipartParser};
+pub use ::process_content::{DefaultProcessor, NullProcessor};
\ No newline at end of file
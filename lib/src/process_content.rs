@@ -3,121 +3,1007 @@
 //! * `NullProcessor` - Empty processor,
 //!
 //! * `DefaultProcessor` - buferize all incoming data, convert data to any simple type
+//!
+//! * `WriterProcessor` - forward all incoming data to any `std::io::Write`
+//!
+//! * `TranscodeProcessor` - copy part (headers and data) to `MultipartWriter`
+//!
+//! * `FileProcessor` - stream data of part to new file with unique name
+//!
+//! * `HybridProcessor` - store small part in memory, spill large part to file
+//!
+//! * `RecodeProcessor` - decode `Content-Transfer-Encoding` of part and encode data to another one (ex.: binary to base64)
+//!
+//! * `Utf8ValidatingProcessor` - store data of part, validate UTF-8 while data received, report offset of first invalid byte
+//!
+//! * `decode_quoted_printable` - decode `quoted-printable` data, as `DefaultProcessor` does
+
+
+use std::cell::{RefCell};
+use std::convert::{TryFrom};
+use std::fs::{self, File, OpenOptions};
+use std::rc::{Rc};
+use std::io::{Write, Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::str::{FromStr, ParseBoolError};
+use std::num::{ParseIntError, ParseFloatError};
+use std::string::{FromUtf8Error};
+use ::gnitive_multipart::{ProcessContent, ProcessContentHooks, ProcessParams, Headers, MultipartParseError};
+use ::multipart_writer::{MultipartWriter};
+use ::transfer_encoding::{Cte, Decoder, Encoder};
+
+/// Empty processor - dont process any data
+pub struct NullProcessor
+{
+    /// Only for trait `ProcessContent`
+    params: ProcessParams,
+
+    /// Error for `ProcessContentHooks::take_open_error`, see `rejecting`
+    open_error: Option<MultipartParseError<'static>>
+}
+
+impl NullProcessor
+{
+    pub fn new() -> NullProcessor
+    {
+        NullProcessor
+            {
+                params: ProcessParams::new("", None ),
+                open_error: None
+            }
+    }
+
+    /// Processor, which rejects part by `error`: `error` is returned by `ProcessContentHooks::take_open_error` and handled by `MultipartParser`
+    /// as other errors of field (ex.: derive `require_name` fires `MultipartParseError::MissingName` by it)
+    pub fn rejecting(error: MultipartParseError<'static>) -> NullProcessor
+    {
+        NullProcessor
+            {
+                params: ProcessParams::new("", None ),
+                open_error: Some(error)
+            }
+    }
+}
+
+impl ProcessContent for NullProcessor
+{
+    fn open(&mut self, _headers: &Headers) -> () {}
+
+    fn write(&mut self, _headers: &Headers, _data: &[u8]) -> Result<(), IOError> { Ok(()) }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError> { Ok(()) }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+
+    fn hooks(&mut self) -> Option<&mut ProcessContentHooks>
+    {
+        Some(self)
+    }
+}
+
+impl ProcessContentHooks for NullProcessor
+{
+    fn take_open_error(&mut self) -> Option<MultipartParseError<'static>>
+    {
+        self.open_error.take()
+    }
+}
+
+
+/// Store all data in `raw_data`, can convert to any simple type (see `impl TryFrom` bellow)
+///
+/// Data of part with `Content-Transfer-Encoding: base64` or `quoted-printable` is decoded, `7bit`, `8bit`
+/// and `binary` are stored verbatim. Data with unknown encoding is stored verbatim too, see `unsupported_encoding`.
+/// Data, invalid for encoding (ex.: not base64 symbol), does not fail `write`: rest of part is dropped, see `invalid_encoding`.
+pub struct DefaultProcessor
+{
+    /// Processor parameters, used in `ProcessContent` trait.
+    params: ProcessParams,
+
+    /// Buffer to store data in `write` function
+    raw_data: Vec<u8>,
+
+    /// Decoder of `Content-Transfer-Encoding` of current part, `None` for identity encodings
+    decoder: Option<Decoder>,
+
+    /// Unknown `Content-Transfer-Encoding` of current part
+    unsupported_encoding: Option<String>,
+
+    /// Error of decoding of current part, rest of data is dropped
+    invalid_encoding: Option<String>,
+
+    /// `true` after `flush`, `false` otherwise
+    is_done: bool,
+
+    /// Data of current part exceeds `max_size` of `params`, rest of data is dropped
+    exceeded_limit: bool,
+
+    /// Release unused capacity of `raw_data` in `flush` and whole buffer in next `open`
+    shrink_after_flush: bool
+}
+
+
+impl DefaultProcessor
+{
+    pub fn new(params: ProcessParams) -> DefaultProcessor
+    {
+        DefaultProcessor
+            {
+                params,
+                raw_data: vec![],
+                decoder: None,
+                unsupported_encoding: None,
+                invalid_encoding: None,
+                is_done: false,
+                exceeded_limit: false,
+                shrink_after_flush: false
+            }
+    }
+
+    /// Processor with already collected data (`is_done` is `true`), ex.: to convert stored raw value by `TryFrom`
+    pub fn with_raw_data(params: ProcessParams, raw_data: Vec<u8>) -> DefaultProcessor
+    {
+        DefaultProcessor
+            {
+                params,
+                raw_data,
+                decoder: None,
+                unsupported_encoding: None,
+                invalid_encoding: None,
+                is_done: true,
+                exceeded_limit: false,
+                shrink_after_flush: false
+            }
+    }
+
+    /// Release memory of internal buffer after each part: unused capacity in `flush`,
+    /// whole buffer in next `open`. Useful for long-lived processors, which may receive a huge part once.
+    pub fn shrink_after_flush(&mut self, shrink: bool) -> &mut Self
+    {
+        self.shrink_after_flush = shrink;
+        self
+    }
+
+    /// Return `true` if all data collected (i.e. `flush` called)
+    pub fn is_done(&self) -> bool
+    {
+        self.is_done
+    }
+
+    /// Get access to internal buffer
+    pub fn raw_data(&self) -> &Vec<u8>
+    {
+        &self.raw_data
+    }
+
+    /// Size of data (decoded, if part has `Content-Transfer-Encoding`), written since last `open`
+    pub fn len(&self) -> usize
+    {
+        self.raw_data.len()
+    }
+
+    /// Return `true` if no data written since last `open`
+    pub fn is_empty(&self) -> bool
+    {
+        self.raw_data.is_empty()
+    }
+
+    /// Return `true` if data of current part exceeds `max_size` of `ProcessParams`: only first `max_size` bytes are kept.
+    /// `MultipartParser` fires `MultipartParseError::SizeLimit` anyway, this limit protects processor, used without parser.
+    pub fn exceeded_limit(&self) -> bool
+    {
+        self.exceeded_limit
+    }
+
+    /// Value of `Content-Transfer-Encoding` header of current part, if encoding is unknown (data is not decoded)
+    pub fn unsupported_encoding(&self) -> Option<&String>
+    {
+        self.unsupported_encoding.as_ref()
+    }
+
+    /// Error of decoding of `Content-Transfer-Encoding` of current part (ex.: `Invalid base64 symbol 0x21`),
+    /// data is decoded up to invalid symbol only
+    pub fn invalid_encoding(&self) -> Option<&String>
+    {
+        self.invalid_encoding.as_ref()
+    }
+
+    /// Keep error of decoding, drop decoder - rest of data is ignored
+    fn set_invalid_encoding(&mut self, error: IOError) -> ()
+    {
+        self.decoder = None;
+        self.invalid_encoding = Some(error.to_string());
+    }
+
+    /// Drop data after `max_size`
+    fn check_limit(&mut self) -> ()
+    {
+        if let Some(max_size) = self.params.max_size
+            {
+                if self.raw_data.len() > max_size
+                    {
+                        self.raw_data.truncate(max_size);
+                        self.exceeded_limit = true;
+                    }
+            }
+    }
+}
+
+
+impl ProcessContent for DefaultProcessor
+{
+    fn open(&mut self, headers: &Headers) -> ()
+    {
+        // data of previous part (ex.: incomplete part, skipped without `flush`) is dropped too
+        self.raw_data.clear();
+        if self.is_done
+            {
+                if self.shrink_after_flush
+                    {
+                        self.raw_data.shrink_to_fit();
+                    }
+                self.is_done = false;
+            }
+
+        self.decoder = None;
+        self.unsupported_encoding = None;
+        self.invalid_encoding = None;
+        self.exceeded_limit = false;
+        if let Some(header) = headers.get_header("Content-Transfer-Encoding")
+            {
+                match Cte::parse(&header.value)
+                    {
+                        Some(Cte::Binary) => (),
+                        Some(cte) => self.decoder = Some(Decoder::new(cte)),
+                        None => self.unsupported_encoding = Some(header.value.trim().to_string())
+                    }
+            }
+    }
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        if !self.is_done
+            {
+                if self.exceeded_limit || self.invalid_encoding.is_some()
+                    {
+                        return Ok(());
+                    }
+                let result = match self.decoder
+                    {
+                        Some(ref mut decoder) => decoder.update(data, &mut self.raw_data),
+                        None =>
+                            {
+                                self.raw_data.extend(data);
+                                Ok(())
+                            }
+                    };
+                if let Err(error) = result
+                    {
+                        self.set_invalid_encoding(error);
+                    }
+                self.check_limit();
+            }
+        else
+            {
+                return Err(IOError::new(ErrorKind::Other, format!("'write' called after 'flush' for field '{}'", self.params.name)));
+            }
+        Ok(())
+    }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
+    {
+        if let Some(mut decoder) = self.decoder.take()
+            {
+                if let Err(error) = decoder.finish(&mut self.raw_data)
+                    {
+                        self.set_invalid_encoding(error);
+                    }
+                self.check_limit();
+            }
+        if self.shrink_after_flush
+            {
+                self.raw_data.shrink_to_fit();
+            }
+        self.is_done = true;
+        Ok(())
+    }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+
+    fn buffered_size(&self) -> usize
+    {
+        self.raw_data.len()
+    }
+}
+
+
+/// Forward all data to inner `std::io::Write` (file, socket, compressor etc).
+///
+/// Errors of inner writer are returned to `MultipartParser`, first of them is stored too
+/// and can be obtained after parsing by `into_result`. No data written after error.
+pub struct WriterProcessor<W: Write>
+{
+    /// Processor parameters, used in `ProcessContent` trait.
+    params: ProcessParams,
+
+    /// Inner writer
+    writer: W,
+
+    /// First error of inner writer
+    error: Option<IOError>
+}
+
+
+impl <W: Write>WriterProcessor<W>
+{
+    pub fn new(params: ProcessParams, writer: W) -> WriterProcessor<W>
+    {
+        WriterProcessor
+            {
+                params,
+                writer,
+                error: None
+            }
+    }
+
+    /// Get access to inner writer
+    pub fn get_ref(&self) -> &W
+    {
+        &self.writer
+    }
+
+    /// Return first error of inner writer, if any
+    pub fn error(&self) -> Option<&IOError>
+    {
+        self.error.as_ref()
+    }
+
+    /// Copy of stored error for `ProcessContent` result
+    fn result(&self) -> Result<(), IOError>
+    {
+        match self.error
+            {
+                Some(ref error) => Err(IOError::new(error.kind(), error.to_string())),
+                None => Ok(())
+            }
+    }
+
+    /// Return inner writer, or first error of inner writer
+    pub fn into_result(self) -> Result<W, IOError>
+    {
+        match self.error
+            {
+                Some(error) => Err(error),
+                None => Ok(self.writer)
+            }
+    }
+}
+
+
+impl <W: Write>ProcessContent for WriterProcessor<W>
+{
+    fn open(&mut self, _headers: &Headers) -> () {}
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        if self.error.is_none()
+            {
+                if let Err(error) = self.writer.write_all(data)
+                    {
+                        self.error = Some(error);
+                    }
+            }
+        self.result()
+    }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
+    {
+        if self.error.is_none()
+            {
+                if let Err(error) = self.writer.flush()
+                    {
+                        self.error = Some(error);
+                    }
+            }
+        self.result()
+    }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+}
+
+
+/// Copy part to `MultipartWriter`: start new part with same headers in `open`, append data in `write`.
+///
+/// One writer may be shared by processors of all parts, to re-emit multipart data with another boundary.
+/// Data is not buffered by processor, writer streams it to its inner `std::io::Write` (see `MultipartWriter::from_writer`).
+/// Errors of writer are returned to `MultipartParser` by `write` and `flush`.
+pub struct TranscodeProcessor<W: Write = Vec<u8>>
+{
+    /// Processor parameters, used in `ProcessContent` trait.
+    params: ProcessParams,
+
+    /// Target writer
+    writer: Rc<RefCell<MultipartWriter<W>>>
+}
+
+impl <W: Write>TranscodeProcessor<W>
+{
+    pub fn new(params: ProcessParams, writer: Rc<RefCell<MultipartWriter<W>>>) -> TranscodeProcessor<W>
+    {
+        TranscodeProcessor
+            {
+                params,
+                writer
+            }
+    }
+
+    /// Copy of first error of writer for `ProcessContent` result
+    fn result(&self) -> Result<(), IOError>
+    {
+        match self.writer.borrow().error()
+            {
+                Some(error) => Err(IOError::new(error.kind(), error.to_string())),
+                None => Ok(())
+            }
+    }
+}
+
+impl <W: Write>ProcessContent for TranscodeProcessor<W>
+{
+    fn open(&mut self, headers: &Headers) -> ()
+    {
+        // error of writer is returned by next `write` or `flush`
+        self.writer.borrow_mut().begin_part(&headers.to_lines());
+    }
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        self.writer.borrow_mut().append(data);
+        self.result()
+    }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
+    {
+        self.result()
+    }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+}
+
+
+/// Counter for unique file names of `FileProcessor`
+static FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Stream data of part to new file `<dir>/multipart-<pid>-<time>-<counter>`, created in `open`.
+///
+/// After successful `flush` file is closed and `path` returns its path. On error file is removed,
+/// error is returned from `write`/`flush`.
+pub struct FileProcessor
+{
+    /// Processor parameters, used in `ProcessContent` trait.
+    params: ProcessParams,
+
+    /// Directory for new files
+    dir: PathBuf,
+
+    /// File of current part, `None` after `flush`
+    file: Option<File>,
+
+    /// Path of file of current part
+    path: Option<PathBuf>,
+
+    /// Error of file creation in `open`, returned from next `write`/`flush`
+    error: Option<IOError>
+}
+
+impl FileProcessor
+{
+    pub fn new(params: ProcessParams, dir: PathBuf) -> FileProcessor
+    {
+        FileProcessor
+            {
+                params,
+                dir,
+                file: None,
+                path: None,
+                error: None
+            }
+    }
+
+    /// Path of file with data of part, `None` before successful `flush`
+    pub fn path(&self) -> Option<&PathBuf>
+    {
+        match self.file
+            {
+                None if self.error.is_none() => self.path.as_ref(),
+                _ => None
+            }
+    }
+
+    /// New unique path in `dir`
+    fn unique_path(&self) -> PathBuf
+    {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0);
+        let counter = FILE_COUNTER.fetch_add(1, Ordering::SeqCst);
+        self.dir.join(format!("multipart-{}-{}-{}", process::id(), time, counter))
+    }
+
+    /// Store `error`, remove file of current part and return copy of `error`
+    fn fail(&mut self, error: IOError) -> IOError
+    {
+        self.file = None;
+        if let Some(ref path) = self.path
+            {
+                let _unused = fs::remove_file(path);
+            }
+        let result = IOError::new(error.kind(), error.to_string());
+        self.error = Some(error);
+        result
+    }
+
+    /// Copy of stored error
+    fn result(&self) -> Result<(), IOError>
+    {
+        match self.error
+            {
+                Some(ref error) => Err(IOError::new(error.kind(), error.to_string())),
+                None => Ok(())
+            }
+    }
+}
+
+impl ProcessContent for FileProcessor
+{
+    fn open(&mut self, _headers: &Headers) -> ()
+    {
+        let path = self.unique_path();
+        self.error = None;
+        // name is predictable - never follow symlink or truncate existing file (ex.: in shared temp directory)
+        match OpenOptions::new().write(true).create_new(true).open(&path)
+            {
+                Ok(file) =>
+                    {
+                        self.file = Some(file);
+                        self.path = Some(path);
+                    },
+                Err(error) =>
+                    {
+                        // existing file is not ours - never removed
+                        self.path = None;
+                        self.error = Some(error);
+                    }
+            }
+    }
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        let result = match self.file
+            {
+                Some(ref mut file) => file.write_all(data),
+                None => return self.result()
+            };
+        result.map_err(|error| self.fail(error))
+    }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
+    {
+        let result = match self.file
+            {
+                Some(ref mut file) => file.flush(),
+                None => return self.result()
+            };
+        match result
+            {
+                Ok(()) =>
+                    {
+                        self.file = None;
+                        Ok(())
+                    },
+                Err(error) => Err(self.fail(error))
+            }
+    }
 
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
 
-use std::convert::{TryFrom};
-use std::str::{FromStr, ParseBoolError};
-use std::num::{ParseIntError, ParseFloatError};
-use std::string::{FromUtf8Error};
-use ::gnitive_multipart::{ProcessContent, ProcessParams, Headers};
+    fn cleanup(&mut self, _headers: &Headers) -> ()
+    {
+        if self.file.take().is_some()
+            {
+                if let Some(path) = self.path.take()
+                    {
+                        let _unused = fs::remove_file(path);
+                    }
+            }
+    }
 
-/// Empty processor - dont process any data
-pub struct NullProcessor
+    /// Close and remove partial file
+    fn abort(&mut self, headers: &Headers) -> Result<(), IOError>
+    {
+        self.cleanup(headers);
+        Ok(())
+    }
+}
+
+
+/// Default `HybridProcessor::spill_threshold`
+pub const SPILL_THRESHOLD: usize = 1024 * 1024;
+
+/// Store small part in memory (like `DefaultProcessor`), part greater than `spill_threshold` - in file
+/// (like `FileProcessor`): data, received before threshold exceeded, is moved to new file in `dir`.
+///
+/// After `flush` data is available by `bytes` (in memory) or `path` (in file). Removing file is up to user.
+pub struct HybridProcessor
 {
-    /// Only for trait `ProcessContent`
-    params: ProcessParams
+    /// Processor parameters, used in `ProcessContent` trait.
+    params: ProcessParams,
+
+    /// Directory for spilled parts
+    dir: PathBuf,
+
+    /// Maximum size of part in memory
+    spill_threshold: usize,
+
+    /// Data of current part, while not spilled
+    data: Vec<u8>,
+
+    /// Processor of spilled part
+    file: Option<FileProcessor>
 }
 
-impl NullProcessor
+impl HybridProcessor
 {
-    pub fn new() -> NullProcessor
+    pub fn new(params: ProcessParams, dir: PathBuf) -> HybridProcessor
     {
-        NullProcessor
+        HybridProcessor
+            {
+                params,
+                dir,
+                spill_threshold: SPILL_THRESHOLD,
+                data: vec![],
+                file: None
+            }
+    }
+
+    /// Maximum size of part in memory, default `SPILL_THRESHOLD`
+    pub fn spill_threshold(&mut self, spill_threshold: usize) -> &mut Self
+    {
+        self.spill_threshold = spill_threshold;
+        self
+    }
+
+    /// Data of part, stored in memory, `None` if part spilled to file
+    pub fn bytes(&self) -> Option<&[u8]>
+    {
+        match self.file
             {
-                params: ProcessParams::new("", None )
+                Some(_) => None,
+                None => Some(&self.data)
             }
     }
+
+    /// Path of file with data of spilled part, `None` if part is in memory or before successful `flush`
+    pub fn path(&self) -> Option<&Path>
+    {
+        self.file.as_ref().and_then(|file| file.path()).map(|path| path.as_path())
+    }
 }
 
-impl ProcessContent for NullProcessor
+impl ProcessContent for HybridProcessor
 {
-    fn open(&mut self, _headers: &Headers) -> () {}
+    fn open(&mut self, _headers: &Headers) -> ()
+    {
+        self.data.clear();
+        self.file = None;
+    }
+
+    fn write(&mut self, headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        if let Some(ref mut file) = self.file
+            {
+                return file.write(headers, data);
+            }
 
-    fn write(&mut self, _headers: &Headers, _data: &Vec<u8>) -> (){}
+        self.data.extend(data);
+        if self.data.len() > self.spill_threshold
+            {
+                let mut file = FileProcessor::new(ProcessParams::new(self.params.name.clone(), self.params.max_size), self.dir.clone());
+                file.open(headers);
+                let data = ::std::mem::replace(&mut self.data, vec![]);
+                let result = file.write(headers, &data);
+                self.file = Some(file);
+                return result;
+            }
+        Ok(())
+    }
 
-    fn flush(&mut self, _headers: &Headers) -> (){}
+    fn flush(&mut self, headers: &Headers) -> Result<(), IOError>
+    {
+        match self.file
+            {
+                Some(ref mut file) => file.flush(headers),
+                None => Ok(())
+            }
+    }
 
     fn get_process_params(&self) -> &ProcessParams
     {
         &self.params
     }
+
+    /// Spilled part is not in memory (`data` is empty)
+    fn buffered_size(&self) -> usize
+    {
+        self.data.len()
+    }
+
+    fn cleanup(&mut self, headers: &Headers) -> ()
+    {
+        if let Some(ref mut file) = self.file
+            {
+                file.cleanup(headers);
+            }
+    }
+
+    /// Release data in memory or close and remove partial file
+    fn abort(&mut self, headers: &Headers) -> Result<(), IOError>
+    {
+        self.data.clear();
+        self.cleanup(headers);
+        Ok(())
+    }
 }
 
 
-/// Store all data in `raw_data`, can convert to any simple type (see `impl TryFrom` bellow)
-pub struct DefaultProcessor
+/// Decode data of part from `from` encoding and encode to `to`, streaming (data split at any byte).
+///
+/// To decode encoding from part headers use `Cte::from_headers` in `MultipartParserTarget::content_parser`.
+pub struct RecodeProcessor
 {
     /// Processor parameters, used in `ProcessContent` trait.
     params: ProcessParams,
 
-    /// Buffer to store data in `write` function
-    raw_data: Vec<u8>,
+    /// Encoding of incoming data
+    from: Cte,
 
-    /// `true` after `flush`, `false` otherwise
-    is_done: bool
-}
+    /// Encoding of result
+    to: Cte,
 
+    decoder: Decoder,
+    encoder: Encoder,
 
-impl DefaultProcessor
+    /// Decoded data of current `write`
+    decoded: Vec<u8>,
+
+    /// Encoded data of part
+    data: Vec<u8>
+}
+
+impl RecodeProcessor
 {
-    pub fn new(params: ProcessParams) -> DefaultProcessor
+    pub fn new(params: ProcessParams, from: Cte, to: Cte) -> RecodeProcessor
     {
-        DefaultProcessor
+        RecodeProcessor
             {
                 params,
-                raw_data: vec![],
-                is_done: false
+                from,
+                to,
+                decoder: Decoder::new(from),
+                encoder: Encoder::new(to),
+                decoded: vec![],
+                data: vec![]
             }
     }
 
-    /// Return `true` if all data collected (i.e. `flush` called)
-    pub fn is_done(&self) -> bool
+    /// Encoded data of part, complete after `flush`
+    pub fn data(&self) -> &Vec<u8>
     {
-        self.is_done
+        &self.data
     }
 
-    /// Get access to internal buffer
-    pub fn raw_data(&self) -> &Vec<u8>
+    /// Encoded data of part as string, `None` if not valid UTF-8 (possible only for `Cte::Binary` result)
+    pub fn as_str(&self) -> Option<&str>
     {
-        &self.raw_data
+        ::std::str::from_utf8(&self.data).ok()
     }
 }
 
-
-impl ProcessContent for DefaultProcessor
+impl ProcessContent for RecodeProcessor
 {
     fn open(&mut self, _headers: &Headers) -> ()
     {
-        if self.is_done
+        self.decoder = Decoder::new(self.from);
+        self.encoder = Encoder::new(self.to);
+        self.data.clear();
+    }
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        self.decoded.clear();
+        self.decoder.update(data, &mut self.decoded)?;
+        self.encoder.update(&self.decoded, &mut self.data);
+        Ok(())
+    }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
+    {
+        self.decoded.clear();
+        self.decoder.finish(&mut self.decoded)?;
+        self.encoder.update(&self.decoded, &mut self.data);
+        self.encoder.finish(&mut self.data);
+        Ok(())
+    }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+
+    fn buffered_size(&self) -> usize
+    {
+        self.data.len()
+    }
+}
+
+/// Store data of part (like `DefaultProcessor`) and validate UTF-8 while data received: multi-byte sequence
+/// may be split between `write` calls. Offset of first invalid byte (from start of part) is available by `first_error_offset`,
+/// ex.: to point user to broken place of text upload. Data is stored anyway, validation stops after first error.
+pub struct Utf8ValidatingProcessor
+{
+    /// Processor parameters, used in `ProcessContent` trait.
+    params: ProcessParams,
+
+    /// Data of part
+    data: Vec<u8>,
+
+    /// Incomplete sequence at end of validated data (up to 4 bytes)
+    pending: Vec<u8>,
+
+    /// Count of validated bytes before `pending`
+    offset: usize,
+
+    /// Offset of first invalid byte
+    first_error_offset: Option<usize>
+}
+
+impl Utf8ValidatingProcessor
+{
+    pub fn new(params: ProcessParams) -> Utf8ValidatingProcessor
+    {
+        Utf8ValidatingProcessor
             {
-                self.raw_data.clear();
-                self.is_done = false;
+                params,
+                data: vec![],
+                pending: vec![],
+                offset: 0,
+                first_error_offset: None
             }
     }
 
-    fn write(&mut self, _headers: &Headers, data: &Vec<u8>) -> ()
+    /// Data of part, complete after `flush`
+    pub fn data(&self) -> &Vec<u8>
     {
-        if !self.is_done
+        &self.data
+    }
+
+    /// Data of part as string, `None` if not valid UTF-8
+    pub fn as_str(&self) -> Option<&str>
+    {
+        match self.first_error_offset
             {
-                self.raw_data.extend(data);
+                Some(_) => None,
+                None => ::std::str::from_utf8(&self.data).ok()
             }
-        else
+    }
+
+    /// Offset of first invalid byte from start of part, `None` if all data received is valid.
+    /// Sequence, truncated by end of part, is reported in `flush` by offset of its first byte.
+    pub fn first_error_offset(&self) -> Option<usize>
+    {
+        self.first_error_offset
+    }
+
+    /// Validate next `data`, continue incomplete sequence in `pending`
+    fn validate(&mut self, data: &[u8]) -> ()
+    {
+        let mut data = data;
+        if !self.pending.is_empty()
+            {
+                // complete pending sequence by first bytes of `data`
+                let pending_len = self.pending.len();
+                let size = ::std::cmp::min(4 - pending_len, data.len());
+                self.pending.extend_from_slice(&data[..size]);
+                let consumed = match ::std::str::from_utf8(&self.pending)
+                    {
+                        Ok(_) => self.pending.len(),
+                        Err(error) => match error.error_len()
+                            {
+                                Some(_) =>
+                                    {
+                                        self.first_error_offset = Some(self.offset + error.valid_up_to());
+                                        return;
+                                    },
+                                None if error.valid_up_to() == 0 => return,
+                                None => error.valid_up_to()
+                            }
+                    };
+                self.pending.clear();
+                self.offset += consumed;
+                data = &data[consumed - pending_len..];
+            }
+
+        match ::std::str::from_utf8(data)
+            {
+                Ok(_) => self.offset += data.len(),
+                Err(error) => match error.error_len()
+                    {
+                        Some(_) => self.first_error_offset = Some(self.offset + error.valid_up_to()),
+                        None =>
+                            {
+                                self.offset += error.valid_up_to();
+                                self.pending.extend_from_slice(&data[error.valid_up_to()..]);
+                            }
+                    }
+            }
+    }
+}
+
+impl ProcessContent for Utf8ValidatingProcessor
+{
+    fn open(&mut self, _headers: &Headers) -> ()
+    {
+        self.data.clear();
+        self.pending.clear();
+        self.offset = 0;
+        self.first_error_offset = None;
+    }
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        if self.first_error_offset.is_none()
             {
-                panic!("'write' called after 'flush' for field '{}'", self.params.name);
+                self.validate(data);
             }
+        self.data.extend(data);
+        Ok(())
     }
 
-    fn flush(&mut self, _headers: &Headers) -> ()
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
     {
-        self.is_done = true;
+        if self.first_error_offset.is_none() && !self.pending.is_empty()
+            {
+                self.first_error_offset = Some(self.offset);
+            }
+        Ok(())
     }
 
     fn get_process_params(&self) -> &ProcessParams
     {
         &self.params
     }
-}
 
+    fn buffered_size(&self) -> usize
+    {
+        self.data.len()
+    }
+}
 
 /* -------- Vec<u8>  -------- */
 impl <'a>TryFrom<&'a DefaultProcessor> for Vec<u8>
@@ -219,8 +1105,37 @@ impl TryFrom<DefaultProcessor> for Option<String>
 }
 
 
+/* -------- quoted-printable  -------- */
+
+/// Decode quoted-printable `data`: `=XX` escapes (lowercase hex digits allowed) and soft line breaks (`=\r\n`),
+/// as `DefaultProcessor` does for `Content-Transfer-Encoding: quoted-printable`.
+/// `=` at end of data is soft line break, `=` not followed by two hex digits is kept as is (ex.: `=ZZ`).
+pub fn decode_quoted_printable(data: &[u8]) -> Vec<u8>
+{
+    let mut result: Vec<u8> = vec![];
+    let mut decoder = Decoder::new(Cte::QuotedPrintable);
+    // quoted-printable decoding never fails (see `Decoder`)
+    let _ = decoder.update(data, &mut result);
+    let _ = decoder.finish(&mut result);
+    result
+}
+
+
 /* -------- bool  -------- */
 
+/// Parse `bool` from common form values (ignoring case and whitespaces): `true`, `1`, `yes`, `y`, `on` and
+/// `false`, `0`, `no`, `n`, `off` (ex.: HTML checkbox), used by derive `bool_format="lenient"`.
+/// Other values - error of `bool::from_str`.
+pub fn parse_bool_lenient(s: &str) -> Result<bool, ParseBoolError>
+{
+    match s.trim().to_lowercase().as_str()
+        {
+            "true" | "1" | "yes" | "y" | "on" => Ok(true),
+            "false" | "0" | "no" | "n" | "off" => Ok(false),
+            _ => bool::from_str(s)
+        }
+}
+
 impl <'a>TryFrom<&'a DefaultProcessor> for bool
 {
     type Error = ParseBoolError;
@@ -1133,3 +2048,364 @@ impl TryFrom<DefaultProcessor> for Option<f64>
             }
     }
 }
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::{DefaultProcessor, WriterProcessor, TranscodeProcessor, FileProcessor, HybridProcessor, RecodeProcessor, Utf8ValidatingProcessor};
+    use ::transfer_encoding::{Cte};
+    use std::cell::{RefCell};
+    use std::env;
+    use std::fs;
+    use std::io::{Write};
+    use std::rc::{Rc};
+    use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessParams, Headers};
+    use ::multipart_parser::{MultipartParser};
+    use ::multipart_parser::tests::{parse_with};
+    use ::multipart_writer::{MultipartWriter};
+
+    #[test]
+    fn writer_processor() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"".to_string()]);
+        let mut processor = WriterProcessor::new(ProcessParams::new("file1", None), Vec::new());
+
+        processor.open(&headers);
+        processor.write(&headers, &b"Hello, ".to_vec()).unwrap();
+        processor.write(&headers, &b"world".to_vec()).unwrap();
+        processor.flush(&headers).unwrap();
+
+        assert_eq!(b"Hello, world".to_vec(), processor.into_result().unwrap());
+    }
+
+    #[test]
+    fn default_processor_shrink_after_flush() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"".to_string()]);
+        let mut processor = DefaultProcessor::new(ProcessParams::new("file1", None));
+        processor.shrink_after_flush(true);
+
+        processor.open(&headers);
+        let chunk: Vec<u8> = vec![0u8; 1000];
+        for _ in 0..1025
+            {
+                processor.write(&headers, &chunk).unwrap();
+            }
+        let capacity = processor.raw_data.capacity();
+        processor.flush(&headers).unwrap();
+        assert_eq!(1025000, processor.raw_data().len());
+        assert!(processor.raw_data.capacity() < capacity);
+
+        processor.open(&headers);
+        processor.write(&headers, &b"abc".to_vec()).unwrap();
+        processor.flush(&headers).unwrap();
+        assert!(processor.raw_data.capacity() < 1024);
+        assert_eq!(b"abc".to_vec(), *processor.raw_data());
+    }
+
+    #[test]
+    fn default_processor_transfer_encoding() -> ()
+    {
+        let parse = |cte: &str, data: &[u8]| -> (Vec<u8>, Option<String>)
+            {
+                let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"".to_string(), format!("Content-Transfer-Encoding: {}", cte)]);
+                let mut processor = DefaultProcessor::new(ProcessParams::new("file1", None));
+                processor.open(&headers);
+                for chunk in data.chunks(3)
+                    {
+                        processor.write(&headers, &chunk.to_vec()).unwrap();
+                    }
+                processor.flush(&headers).unwrap();
+                (processor.raw_data().clone(), processor.unsupported_encoding().cloned())
+            };
+
+        // identity encodings - data as is, even not matched to encoding (ex.: 8-bit data in `7bit`)
+        for cte in &["7bit", "8bit", "binary", "BINARY"]
+            {
+                assert_eq!((b"a=3D\xff\r\nYWJj".to_vec(), None), parse(cte, b"a=3D\xff\r\nYWJj"), "{}", cte);
+            }
+        assert_eq!((b"abcd".to_vec(), None), parse("base64", b"YWJj\r\nZA=="));
+        assert_eq!((b"a=b".to_vec(), None), parse("quoted-printable", b"a=3Db"));
+        assert_eq!((b"a=ZZb".to_vec(), None), parse("quoted-printable", b"a=ZZ=\r\nb="));
+        assert_eq!((b"YWJj".to_vec(), Some("x-uuencode".to_string())), parse("x-uuencode", b"YWJj"));
+
+        // invalid data - `write` and `flush` succeed, rest of part is dropped
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"".to_string(), "Content-Transfer-Encoding: base64".to_string()]);
+        let mut processor = DefaultProcessor::new(ProcessParams::new("file1", None));
+        for &(data, expected, error) in &[(&b"YWJj!ZA=="[..], &b"abc"[..], "Invalid base64 symbol 0x21"), (b"YWJjZ", b"abc", "Base64 data truncated")]
+            {
+                processor.open(&headers);
+                for chunk in data.chunks(3)
+                    {
+                        processor.write(&headers, chunk).unwrap();
+                    }
+                processor.flush(&headers).unwrap();
+                assert_eq!(expected.to_vec(), *processor.raw_data());
+                assert_eq!(Some(&error.to_string()), processor.invalid_encoding());
+            }
+        processor.open(&headers);
+        assert_eq!(None, processor.invalid_encoding());
+    }
+
+    #[test]
+    fn decode_quoted_printable() -> ()
+    {
+        assert_eq!(b"a=b c\r\nd".to_vec(), super::decode_quoted_printable(b"a=3Db=\r\n c=0D=0A=\nd"));
+        // lowercase hex digits
+        assert_eq!(b"\xab\xcd\xef".to_vec(), super::decode_quoted_printable(b"=ab=Cd=eF"));
+        // trailing `=` - soft line break
+        assert_eq!(b"abc".to_vec(), super::decode_quoted_printable(b"abc="));
+        // `=` without two hex digits - as is
+        assert_eq!(b"=ZZ =4 =\r= x=4".to_vec(), super::decode_quoted_printable(b"=ZZ =4 =\r= x=4"));
+        assert_eq!(Vec::<u8>::new(), super::decode_quoted_printable(b""));
+    }
+
+    #[test]
+    fn default_processor_write_after_flush() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"".to_string()]);
+        let mut processor = DefaultProcessor::new(ProcessParams::new("file1", None));
+        processor.open(&headers);
+        processor.write(&headers, &b"abc".to_vec()).unwrap();
+        processor.flush(&headers).unwrap();
+        assert!(processor.write(&headers, &b"def".to_vec()).is_err());
+    }
+
+    #[test]
+    fn default_processor_len() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"".to_string(), "Content-Transfer-Encoding: base64".to_string()]);
+        let mut processor = DefaultProcessor::new(ProcessParams::new("file1", None));
+        assert!(processor.is_empty());
+        processor.open(&headers);
+        processor.write(&headers, b"YWJj").unwrap();
+        assert_eq!(3, processor.len());
+        assert!(!processor.is_empty());
+
+        // incomplete part without `flush`: next `open` starts from empty data
+        processor.open(&headers);
+        assert!(processor.is_empty());
+        processor.write(&headers, b"ZA==").unwrap();
+        processor.flush(&headers).unwrap();
+        assert_eq!(1, processor.len());
+        assert_eq!(b"d".to_vec(), *processor.raw_data());
+    }
+
+    #[test]
+    fn default_processor_max_size() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"".to_string()]);
+        let mut processor = DefaultProcessor::new(ProcessParams::new("file1", Some(4)));
+        processor.open(&headers);
+        processor.write(&headers, b"abc").unwrap();
+        assert!(!processor.exceeded_limit());
+        processor.write(&headers, b"def").unwrap();
+        processor.write(&headers, b"ghi").unwrap();
+        processor.flush(&headers).unwrap();
+        assert!(processor.exceeded_limit());
+        assert_eq!(b"abcd".to_vec(), *processor.raw_data());
+
+        // decoded data is limited, flag is reset in `open`
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"".to_string(), "Content-Transfer-Encoding: base64".to_string()]);
+        processor.open(&headers);
+        assert!(!processor.exceeded_limit());
+        processor.write(&headers, b"YWJjZA==").unwrap();
+        processor.flush(&headers).unwrap();
+        assert!(!processor.exceeded_limit());
+        processor.open(&headers);
+        processor.write(&headers, b"YWJjZGVm").unwrap();
+        processor.flush(&headers).unwrap();
+        assert!(processor.exceeded_limit());
+        assert_eq!(b"abcd".to_vec(), *processor.raw_data());
+    }
+
+    /// Copy all parts to `writer`
+    struct Transcode
+    {
+        writer: Rc<RefCell<MultipartWriter>>
+    }
+
+    impl MultipartParserTarget for Transcode {}
+
+    impl MultipartParserTargetGenerated for Transcode
+    {
+        fn get_all_required(&self) -> Vec<String> { vec![] }
+
+        fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+        {
+            let name = headers.get_name().cloned().unwrap_or_default();
+            Some(Rc::new(RefCell::new(TranscodeProcessor::new(ProcessParams::new(name, None), self.writer.clone()))))
+        }
+    }
+
+    #[test]
+    fn transcode_processor() -> ()
+    {
+        let body: &[u8] = b"--boundary\r\nContent-Disposition: form-data; name=\"text1\"\r\n\r\ntext default\r\n--boundary\r\nContent-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\nContent of a.txt.\r\n\r\n--boundary--\r\n";
+        let writer = Rc::new(RefCell::new(MultipartWriter::new("other-boundary")));
+        let mut multipart_parser = MultipartParser::new_owned("boundary", Transcode { writer: writer.clone() });
+        for data in body.chunks(7)
+            {
+                multipart_parser.write_all(data).unwrap();
+            }
+        drop(multipart_parser);
+
+        let transcoded = match Rc::try_unwrap(writer)
+            {
+                Ok(writer) => writer.into_inner().finish(),
+                Err(_) => panic!("writer is still shared")
+            };
+
+        let parts = parse_with("other-boundary", &transcoded, transcoded.len());
+        assert_eq!(2, parts.len());
+        assert_eq!(vec!["Content-Disposition: form-data; name=\"text1\"".to_string()], parts[0].0);
+        assert_eq!(b"text default".to_vec(), parts[0].1);
+        assert_eq!("Content-Type: text/plain", parts[1].0[1]);
+        assert_eq!(b"Content of a.txt.\r\n".to_vec(), parts[1].1);
+        // headers in order of part
+        let file1_headers: &[u8] = b"Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\n";
+        assert!(transcoded.windows(file1_headers.len()).any(|window| window == file1_headers));
+    }
+
+    #[test]
+    fn file_processor() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"".to_string()]);
+        let mut processor = FileProcessor::new(ProcessParams::new("file1", None), env::temp_dir());
+
+        let mut paths = vec![];
+        for content in &[&b"first"[..], &b"second"[..]]
+            {
+                processor.open(&headers);
+                assert!(processor.path().is_none());
+                processor.write(&headers, &content.to_vec()).unwrap();
+                processor.flush(&headers).unwrap();
+
+                let path = processor.path().unwrap().clone();
+                assert_eq!(content.to_vec(), fs::read(&path).unwrap());
+                paths.push(path);
+            }
+        assert!(paths[0] != paths[1]);
+        for path in paths
+            {
+                fs::remove_file(path).unwrap();
+            }
+
+        processor.open(&headers);
+        processor.write(&headers, &b"partial".to_vec()).unwrap();
+        let path = processor.path.clone().unwrap();
+        assert!(path.exists());
+        processor.cleanup(&headers);
+        assert!(!path.exists());
+        assert!(processor.path().is_none());
+
+        let mut processor = FileProcessor::new(ProcessParams::new("file1", None), env::temp_dir().join("multipart-no-such-dir"));
+        processor.open(&headers);
+        assert!(processor.write(&headers, &b"data".to_vec()).is_err());
+        assert!(processor.flush(&headers).is_err());
+        assert!(processor.path().is_none());
+    }
+
+    #[test]
+    fn hybrid_processor() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"".to_string()]);
+        let mut processor = HybridProcessor::new(ProcessParams::new("file1", None), env::temp_dir());
+        processor.spill_threshold(8);
+
+        processor.open(&headers);
+        processor.write(&headers, &b"small".to_vec()).unwrap();
+        processor.write(&headers, &b"!".to_vec()).unwrap();
+        processor.flush(&headers).unwrap();
+        assert_eq!(Some(&b"small!"[..]), processor.bytes());
+        assert!(processor.path().is_none());
+
+        processor.open(&headers);
+        processor.write(&headers, &b"large".to_vec()).unwrap();
+        processor.write(&headers, &b" data".to_vec()).unwrap();
+        processor.write(&headers, &b" spilled".to_vec()).unwrap();
+        assert!(processor.bytes().is_none());
+        processor.flush(&headers).unwrap();
+        let path = processor.path().unwrap().to_path_buf();
+        assert_eq!(b"large data spilled".to_vec(), fs::read(&path).unwrap());
+        fs::remove_file(path).unwrap();
+
+        // next part in memory again
+        processor.open(&headers);
+        processor.write(&headers, &b"abc".to_vec()).unwrap();
+        processor.flush(&headers).unwrap();
+        assert_eq!(Some(&b"abc"[..]), processor.bytes());
+        assert!(processor.path().is_none());
+    }
+
+    #[test]
+    fn recode_processor() -> ()
+    {
+        let binary = Headers::new(&vec!["Content-Disposition: form-data; name=\"file1\"; filename=\"a.bin\"".to_string()]);
+        let data: Vec<u8> = vec![0x00, 0xFF, 0xFE, b'f', b'o', b'o', b'b'];
+
+        for chunk in 1..data.len() + 1
+            {
+                let mut processor = RecodeProcessor::new(ProcessParams::new("file1", None), Cte::Binary, Cte::Base64);
+                processor.open(&binary);
+                for data in data.chunks(chunk)
+                    {
+                        processor.write(&binary, &data.to_vec()).unwrap();
+                    }
+                processor.flush(&binary).unwrap();
+                assert_eq!(Some("AP/+Zm9vYg=="), processor.as_str());
+            }
+
+        let mut processor = RecodeProcessor::new(ProcessParams::new("file1", None), Cte::QuotedPrintable, Cte::Base64);
+        processor.open(&binary);
+        processor.write(&binary, &b"=00=FF=FEfo=\r\nob".to_vec()).unwrap();
+        processor.flush(&binary).unwrap();
+        assert_eq!(Some("AP/+Zm9vYg=="), processor.as_str());
+
+        // invalid quoted-printable escape is kept as is
+        processor.open(&binary);
+        processor.write(&binary, &b"=ZZ".to_vec()).unwrap();
+        processor.flush(&binary).unwrap();
+        assert_eq!(Some("PVpa"), processor.as_str());
+    }
+
+    #[test]
+    fn utf8_validating_processor() -> ()
+    {
+        let headers = Headers::new(&vec!["Content-Disposition: form-data; name=\"text\"; filename=\"a.txt\"".to_string()]);
+        let validate = |data: &[u8], chunk: usize| -> Utf8ValidatingProcessor
+            {
+                let mut processor = Utf8ValidatingProcessor::new(ProcessParams::new("text", None));
+                processor.open(&headers);
+                for data in data.chunks(chunk)
+                    {
+                        processor.write(&headers, &data.to_vec()).unwrap();
+                    }
+                processor.flush(&headers).unwrap();
+                processor
+            };
+
+        // 2, 3 and 4 byte sequences split at any byte
+        let valid = "añb€c😀d".as_bytes();
+        // `\xE2\x82` - truncated `€` before `x`
+        let invalid: &[u8] = b"a\xC3\xB1b\xE2\x82\xACc\xE2\x82xyz\xFF";
+        for chunk in 1..valid.len() + 1
+            {
+                let processor = validate(valid, chunk);
+                assert_eq!(None, processor.first_error_offset(), "chunk {}", chunk);
+                assert_eq!(Some("añb€c😀d"), processor.as_str());
+
+                let processor = validate(invalid, chunk);
+                assert_eq!(Some(8), processor.first_error_offset(), "chunk {}", chunk);
+                assert_eq!(None, processor.as_str());
+                assert_eq!(invalid.to_vec(), *processor.data());
+            }
+
+        // sequence truncated by end of part
+        for chunk in 1..5
+            {
+                assert_eq!(Some(3), validate(b"abc\xF0\x9F\x98", chunk).first_error_offset(), "chunk {}", chunk);
+            }
+    }
+}
\ No newline at end of file
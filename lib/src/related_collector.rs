@@ -0,0 +1,275 @@
+//! Collect parts of `multipart/related` (RFC 2387) data, ex.: SOAP with attachments (MTOM):
+//! root part (XML) and binary attachments, referenced from root by `Content-ID`.
+//!
+//! ```rust,ignore
+//! let mut multipart_parser = MultipartParser::new_owned(&boundary, RelatedCollector::new());
+//! multipart_parser.write_all(body)?;
+//! let related = multipart_parser.into_target();
+//! let xml = related.root().unwrap();
+//! // <xop:Include href="cid:img1@example.com"/>
+//! let image = related.attachment("cid:img1@example.com").unwrap();
+//! ```
+//!
+//! Root part may be streamed to any processor (ex.: `WriterProcessor` over input of XML parser) instead of buffering:
+//!
+//! ```rust,ignore
+//! let xml = Rc::new(RefCell::new(WriterProcessor::new(ProcessParams::new("root", None), xml_input)));
+//! let mut related = RelatedCollector::new();
+//! related.root_processor(xml.clone()).max_attachment_size(Some(10 * 1024 * 1024));
+//! ```
+
+use std::cell::{RefCell};
+use std::collections::{HashMap};
+use std::io::{Error as IOError};
+use std::rc::{Rc};
+use ::gnitive_multipart::{MultipartParserTarget, MultipartParserTargetGenerated, ProcessContent, ProcessParams, Headers};
+use ::url_decode::{percent_decode};
+
+
+/// `MultipartParserTarget`, which store root part and attachments by `Content-ID`
+pub struct RelatedCollector
+{
+    /// `Content-ID` of root part (`start` parameter of `Content-Type`), `None` - first part is root
+    start: Option<String>,
+
+    /// Data of root part
+    root: Option<Vec<u8>>,
+
+    /// Processor of root part, see `root_processor`
+    root_processor: Option<Rc<RefCell<ProcessContent>>>,
+
+    /// `true` after root part is selected
+    root_received: bool,
+
+    /// Data of other parts by `Content-ID` (without `<>`), parts without `Content-ID` are ignored
+    attachments: HashMap<String, Vec<u8>>,
+
+    /// Maximum size of one attachment, see `max_attachment_size`
+    max_attachment_size: Option<usize>
+}
+
+impl RelatedCollector
+{
+    pub fn new() -> RelatedCollector
+    {
+        RelatedCollector
+            {
+                start: None,
+                root: None,
+                root_processor: None,
+                root_received: false,
+                attachments: HashMap::new(),
+                max_attachment_size: None
+            }
+    }
+
+    /// Set `Content-ID` of root part (`start` parameter of `multipart/related` `Content-Type`), default - first part is root
+    pub fn start(&mut self, content_id: &str) -> &mut Self
+    {
+        self.start = Some(content_id.trim_start_matches('<').trim_end_matches('>').to_string());
+        self
+    }
+
+    /// Stream root part to `processor` (ex.: `WriterProcessor` over input of XML parser), instead of buffering it for `root`
+    pub fn root_processor(&mut self, processor: Rc<RefCell<ProcessContent>>) -> &mut Self
+    {
+        self.root_processor = Some(processor);
+        self
+    }
+
+    /// Maximum size of one attachment, default `None` - unlimited. Greater attachment fires `MultipartParseError::SizeLimit`
+    /// and is not stored.
+    pub fn max_attachment_size(&mut self, max_size: Option<usize>) -> &mut Self
+    {
+        self.max_attachment_size = max_size;
+        self
+    }
+
+    /// Data of root part, `None` if root part is not received or streamed to `root_processor`
+    pub fn root(&self) -> Option<&[u8]>
+    {
+        self.root.as_ref().map(|root| root.as_slice())
+    }
+
+    /// Data of attachment by `Content-ID` (ex.: `img1@example.com`) or `cid:` URL from root (ex.: `cid:img1%40example.com`)
+    pub fn attachment(&self, content_id: &str) -> Option<&[u8]>
+    {
+        let content_id = match content_id.starts_with("cid:")
+            {
+                true => percent_decode(&content_id[4..]),
+                false => content_id.to_string()
+            };
+        self.attachments.get(&content_id).map(|attachment| attachment.as_slice())
+    }
+
+    /// All attachments by `Content-ID`
+    pub fn attachments(&self) -> &HashMap<String, Vec<u8>>
+    {
+        &self.attachments
+    }
+}
+
+impl MultipartParserTarget for RelatedCollector {}
+
+impl MultipartParserTargetGenerated for RelatedCollector
+{
+    fn get_all_required(&self) -> Vec<String> { vec![] }
+
+    fn content_parser_generated(&mut self, self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        let content_id = headers.get_content_id().map(|content_id| content_id.to_string());
+        let is_root = !self.root_received && match self.start
+            {
+                Some(ref start) => content_id.as_ref() == Some(start),
+                None => true
+            };
+        if !is_root && content_id.is_none()
+            {
+                return None;
+            }
+        if is_root
+            {
+                self.root_received = true;
+                if let Some(ref processor) = self.root_processor
+                    {
+                        return Some(processor.clone());
+                    }
+            }
+        let max_size = match is_root
+            {
+                true => None,
+                false => self.max_attachment_size
+            };
+        Some(Rc::new(RefCell::new(RelatedProcessor
+            {
+                params: ProcessParams::new(content_id.clone().unwrap_or_default(), max_size),
+                exceeded: false,
+                is_root,
+                content_id,
+                data: vec![],
+                target: self_.clone()
+            })))
+    }
+}
+
+
+/// Collect data of one part, store it in `RelatedCollector` in `flush`.
+/// Data greater than `max_size` of `params` is dropped (`SizeLimit` is fired by `MultipartParser`).
+struct RelatedProcessor
+{
+    params: ProcessParams,
+    exceeded: bool,
+    is_root: bool,
+    content_id: Option<String>,
+    data: Vec<u8>,
+    target: Rc<RefCell<RelatedCollector>>
+}
+
+impl ProcessContent for RelatedProcessor
+{
+    fn open(&mut self, _headers: &Headers) -> () {}
+
+    fn write(&mut self, _headers: &Headers, data: &[u8]) -> Result<(), IOError>
+    {
+        match self.params.max_size
+            {
+                _ if self.exceeded => (),
+                Some(max_size) if self.data.len() + data.len() > max_size =>
+                    {
+                        self.exceeded = true;
+                        self.data = vec![];
+                    },
+                _ => self.data.extend(data)
+            }
+        Ok(())
+    }
+
+    fn flush(&mut self, _headers: &Headers) -> Result<(), IOError>
+    {
+        if self.exceeded
+            {
+                return Ok(());
+            }
+        let data = self.data.split_off(0);
+        let mut target = self.target.borrow_mut();
+        match (self.is_root, &self.content_id)
+            {
+                (true, _) => target.root = Some(data),
+                (false, &Some(ref content_id)) =>
+                    {
+                        target.attachments.insert(content_id.clone(), data);
+                    },
+                (false, &None) => ()
+            }
+        Ok(())
+    }
+
+    fn get_process_params(&self) -> &ProcessParams
+    {
+        &self.params
+    }
+
+    fn buffered_size(&self) -> usize
+    {
+        self.data.len()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{RelatedCollector};
+    use std::cell::{RefCell};
+    use std::io::{Write};
+    use std::rc::{Rc};
+    use ::gnitive_multipart::{ProcessParams};
+    use ::multipart_parser::{MultipartParser};
+    use ::process_content::{WriterProcessor};
+
+    #[test]
+    fn mtom() -> ()
+    {
+        let body: &[u8] = b"--MIME_boundary\r\nContent-Type: application/xop+xml; charset=UTF-8; type=\"text/xml\"\r\nContent-ID: <root@example.com>\r\n\r\n<soap:Envelope><photo><xop:Include href=\"cid:img1%40example.com\"/></photo><sig><xop:Include href=\"cid:sig1@example.com\"/></sig></soap:Envelope>\r\n--MIME_boundary\r\nContent-Type: image/png\r\nContent-Transfer-Encoding: binary\r\nContent-ID: <img1@example.com>\r\n\r\n\x89PNG\0\xff\r\n--MIME_boundary\r\nContent-Type: application/pkcs7-signature\r\nContent-ID: <sig1@example.com>\r\n\r\n\x30\x82\r\n--MIME_boundary\r\nContent-Type: text/plain\r\n\r\nno Content-ID\r\n--MIME_boundary--\r\n";
+        for &start in &[None, Some("<root@example.com>")]
+            {
+                let mut related = RelatedCollector::new();
+                if let Some(start) = start
+                    {
+                        related.start(start);
+                    }
+                let mut multipart_parser = MultipartParser::new_owned("MIME_boundary", related);
+                multipart_parser.write_all(body).unwrap();
+
+                let related = multipart_parser.into_target();
+                let root = String::from_utf8(related.root().unwrap().to_vec()).unwrap();
+                assert!(root.starts_with("<soap:Envelope>"));
+                assert_eq!(Some(&b"\x89PNG\0\xff"[..]), related.attachment("cid:img1%40example.com"));
+                assert_eq!(Some(&b"\x30\x82"[..]), related.attachment("sig1@example.com"));
+                assert_eq!(None, related.attachment("root@example.com"));
+                assert_eq!(2, related.attachments().len());
+            }
+    }
+
+    #[test]
+    fn root_processor() -> ()
+    {
+        let body: &[u8] = b"--MIME_boundary\r\nContent-Type: text/xml\r\nContent-ID: <root@example.com>\r\n\r\n<soap:Envelope/>\r\n--MIME_boundary\r\nContent-ID: <small@example.com>\r\n\r\n0123\r\n--MIME_boundary\r\nContent-ID: <large@example.com>\r\n\r\n0123456789\r\n--MIME_boundary--\r\n";
+        for chunk in 1..body.len() + 1
+            {
+                let xml = Rc::new(RefCell::new(WriterProcessor::new(ProcessParams::new("root", None), vec![])));
+                let mut related = RelatedCollector::new();
+                related.root_processor(xml.clone()).max_attachment_size(Some(4));
+                let mut multipart_parser = MultipartParser::new_owned("MIME_boundary", related);
+                for data in body.chunks(chunk)
+                    {
+                        multipart_parser.write_all(data).unwrap();
+                    }
+
+                let related = multipart_parser.into_target();
+                assert_eq!(b"<soap:Envelope/>".to_vec(), *xml.borrow().get_ref());
+                assert_eq!(None, related.root());
+                assert_eq!(Some(&b"0123"[..]), related.attachment("small@example.com"));
+                assert_eq!(None, related.attachment("large@example.com"));
+            }
+    }
+}
\ No newline at end of file
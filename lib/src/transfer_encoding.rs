@@ -0,0 +1,444 @@
+//! Streaming codecs for `Content-Transfer-Encoding` (RFC 2045) of part data.
+//!
+//! Data may be split at any byte (network packets, boundary processing), so `Decoder` and `Encoder` keep
+//! incomplete sequences between `update` calls and write the rest in `finish`.
+
+use std::io::{Error as IOError, ErrorKind};
+use ::gnitive_multipart::{Headers};
+
+
+/// Base64 alphabet (RFC 4648)
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Value of base64 symbol by byte, `BASE64_INVALID` for bytes out of alphabet
+const BASE64_VALUES: [u8; 256] = base64_values();
+
+const BASE64_INVALID: u8 = 0xFF;
+
+/// Maximum length of line of base64 data, without `\r\n` (RFC 2045, 6.8)
+const BASE64_LINE_MAX: usize = 76;
+
+/// Maximum length of line of quoted-printable data, without `\r\n`
+const QP_LINE_MAX: usize = 76;
+
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+
+const fn base64_values() -> [u8; 256]
+{
+    let mut result = [BASE64_INVALID; 256];
+    let mut i = 0;
+    while i < BASE64.len()
+        {
+            result[BASE64[i] as usize] = i as u8;
+            i += 1;
+        }
+    result
+}
+
+
+/// `Content-Transfer-Encoding`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Cte
+{
+    /// No encoding: `binary`, `8bit`, `7bit`
+    Binary,
+
+    /// `base64`, encoded by lines of 76 symbols
+    Base64,
+
+    /// `quoted-printable`
+    QuotedPrintable
+}
+
+impl Cte
+{
+    /// Encoding by value of `Content-Transfer-Encoding` header (ignoring case), `None` if unknown
+    pub fn parse(value: &str) -> Option<Cte>
+    {
+        match value.trim().to_lowercase().as_str()
+            {
+                "7bit" | "8bit" | "binary" => Some(Cte::Binary),
+                "base64" => Some(Cte::Base64),
+                "quoted-printable" => Some(Cte::QuotedPrintable),
+                _ => None
+            }
+    }
+
+    /// Encoding of part by `Content-Transfer-Encoding` header, `Binary` if header absent or unknown
+    pub fn from_headers(headers: &Headers) -> Cte
+    {
+        match headers.get_header("Content-Transfer-Encoding")
+            {
+                Some(header) => Cte::parse(&header.value).unwrap_or(Cte::Binary),
+                None => Cte::Binary
+            }
+    }
+}
+
+
+/// Streaming decoder of `Cte`
+pub struct Decoder
+{
+    cte: Cte,
+
+    /// Incomplete sequence: base64 symbols (less than 4) or quoted-printable `=`, `=X`, `=\r`
+    pending: Vec<u8>,
+
+    /// Base64 padding `=` read, only padding and whitespaces allowed after it
+    padding: bool
+}
+
+impl Decoder
+{
+    pub fn new(cte: Cte) -> Decoder
+    {
+        Decoder
+            {
+                cte,
+                pending: vec![],
+                padding: false
+            }
+    }
+
+    /// Decode `data`, append result to `out`.
+    /// Return `Err` with kind `InvalidData` for symbols, not allowed by encoding.
+    pub fn update(&mut self, data: &[u8], out: &mut Vec<u8>) -> Result<(), IOError>
+    {
+        match self.cte
+            {
+                Cte::Binary =>
+                    {
+                        out.extend(data);
+                        Ok(())
+                    },
+                Cte::Base64 => self.update_base64(data, out),
+                Cte::QuotedPrintable => self.update_quoted_printable(data, out)
+            }
+    }
+
+    /// Finish decoding, append rest of data to `out`.
+    /// Return `Err` with kind `InvalidData` if data truncated.
+    pub fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), IOError>
+    {
+        let pending = ::std::mem::replace(&mut self.pending, vec![]);
+        match self.cte
+            {
+                Cte::Base64 if pending.len() == 1 => Err(IOError::new(ErrorKind::InvalidData, "Base64 data truncated")),
+                Cte::Base64 =>
+                    {
+                        Decoder::decode_base64_group(&pending, out);
+                        Ok(())
+                    },
+                // `=` at end of data - soft line break without `\r\n`, incomplete escape (ex.: `=4`) is kept as is
+                Cte::QuotedPrintable if pending.len() > 1 && pending != b"=\r" =>
+                    {
+                        out.extend(pending);
+                        Ok(())
+                    },
+                _ => Ok(())
+            }
+    }
+
+    fn update_base64(&mut self, data: &[u8], out: &mut Vec<u8>) -> Result<(), IOError>
+    {
+        for &c in data
+            {
+                match c
+                    {
+                        b'\r' | b'\n' | b' ' | b'\t' => continue,
+                        b'=' =>
+                            {
+                                self.padding = true;
+                                continue;
+                            },
+                        _ if self.padding => return Err(IOError::new(ErrorKind::InvalidData, "Base64 data after padding")),
+                        _ => ()
+                    }
+
+                let value = BASE64_VALUES[c as usize];
+                if value == BASE64_INVALID
+                    {
+                        return Err(IOError::new(ErrorKind::InvalidData, format!("Invalid base64 symbol 0x{:02X}", c)));
+                    }
+                self.pending.push(value);
+                if self.pending.len() == 4
+                    {
+                        Decoder::decode_base64_group(&self.pending, out);
+                        self.pending.clear();
+                    }
+            }
+        Ok(())
+    }
+
+    /// Decode 2...4 sextets to 1...3 bytes
+    fn decode_base64_group(sextets: &[u8], out: &mut Vec<u8>) -> ()
+    {
+        let mut bits: u32 = 0;
+        for (i, &sextet) in sextets.iter().enumerate()
+            {
+                bits |= (sextet as u32) << (18 - 6 * i);
+            }
+        let count = match sextets.len()
+            {
+                0 | 1 => 0,
+                len => len - 1
+            };
+        for i in 0..count
+            {
+                out.push((bits >> (16 - 8 * i)) as u8);
+            }
+    }
+
+    /// Quoted-printable is decoded robustly (RFC 2045, 6.7): `=`, not followed by two hex digits (lowercase allowed)
+    /// or line break, is kept as is (ex.: `=ZZ`), so no `Err` returned.
+    fn update_quoted_printable(&mut self, data: &[u8], out: &mut Vec<u8>) -> Result<(), IOError>
+    {
+        for &c in data
+            {
+                self.update_quoted_printable_symbol(c, out);
+            }
+        Ok(())
+    }
+
+    fn update_quoted_printable_symbol(&mut self, c: u8, out: &mut Vec<u8>) -> ()
+    {
+        if self.pending.is_empty()
+            {
+                match c
+                    {
+                        b'=' => self.pending.push(c),
+                        _ => out.push(c)
+                    }
+                return;
+            }
+
+        self.pending.push(c);
+        let decoded = match &self.pending[..]
+            {
+                // soft line break
+                b"=\r\n" | b"=\n" => None,
+                b"=\r" => return,
+                &[b'=', high] if (high as char).is_digit(16) => return,
+                &[b'=', high, low] if (high as char).is_digit(16) && (low as char).is_digit(16) =>
+                    {
+                        (high as char).to_digit(16).and_then(|high| (low as char).to_digit(16).map(|low| (high * 16 + low) as u8))
+                    },
+                _ =>
+                    {
+                        // not escape - `=` is kept, rest of symbols (at most 2) processed again
+                        let rest = self.pending.split_off(1);
+                        self.pending.clear();
+                        out.push(b'=');
+                        for c in rest
+                            {
+                                self.update_quoted_printable_symbol(c, out);
+                            }
+                        return;
+                    }
+            };
+        self.pending.clear();
+        out.extend(decoded);
+    }
+}
+
+
+/// Streaming encoder of `Cte`
+pub struct Encoder
+{
+    cte: Cte,
+
+    /// Base64: bytes of incomplete group (less than 3)
+    pending: Vec<u8>,
+
+    /// Length of current line of encoded data
+    line_len: usize
+}
+
+impl Encoder
+{
+    pub fn new(cte: Cte) -> Encoder
+    {
+        Encoder
+            {
+                cte,
+                pending: vec![],
+                line_len: 0
+            }
+    }
+
+    /// Encode `data`, append result to `out`
+    pub fn update(&mut self, data: &[u8], out: &mut Vec<u8>) -> ()
+    {
+        match self.cte
+            {
+                Cte::Binary => out.extend(data),
+                Cte::Base64 =>
+                    {
+                        for &c in data
+                            {
+                                self.pending.push(c);
+                                if self.pending.len() == 3
+                                    {
+                                        self.encode_base64_group(out);
+                                    }
+                            }
+                    },
+                Cte::QuotedPrintable =>
+                    {
+                        for &c in data
+                            {
+                                self.encode_quoted_printable(c, out);
+                            }
+                    }
+            }
+    }
+
+    /// Finish encoding, append rest of data (with padding) to `out`
+    pub fn finish(&mut self, out: &mut Vec<u8>) -> ()
+    {
+        if self.cte == Cte::Base64 && !self.pending.is_empty()
+            {
+                self.encode_base64_group(out);
+            }
+    }
+
+    /// Encode 1...3 pending bytes to 4 symbols, with `=` padding; `\r\n` keeps lines not longer than `BASE64_LINE_MAX`
+    fn encode_base64_group(&mut self, out: &mut Vec<u8>) -> ()
+    {
+        if self.line_len + 4 > BASE64_LINE_MAX
+            {
+                out.extend(b"\r\n");
+                self.line_len = 0;
+            }
+        let mut bits: u32 = 0;
+        for (i, &byte) in self.pending.iter().enumerate()
+            {
+                bits |= (byte as u32) << (16 - 8 * i);
+            }
+        for i in 0..4
+            {
+                match i <= self.pending.len()
+                    {
+                        true => out.push(BASE64[((bits >> (18 - 6 * i)) & 0x3F) as usize]),
+                        false => out.push(b'=')
+                    }
+            }
+        self.pending.clear();
+        self.line_len += 4;
+    }
+
+    /// Encode one byte: printable ASCII (except `=`), space and tab as is, other as `=XX`.
+    /// Line breaks of data are encoded too, soft line breaks `=\r\n` keep lines not longer than `QP_LINE_MAX`.
+    fn encode_quoted_printable(&mut self, c: u8, out: &mut Vec<u8>) -> ()
+    {
+        let literal = (c >= 33 && c <= 126 && c != b'=') || c == b' ' || c == b'\t';
+        let len = if literal { 1 } else { 3 };
+        // place for `=` of soft line break
+        if self.line_len + len > QP_LINE_MAX - 1
+            {
+                out.extend(b"=\r\n");
+                self.line_len = 0;
+            }
+        match literal
+            {
+                true => out.push(c),
+                false => out.extend(&[b'=', HEX[(c >> 4) as usize], HEX[(c & 0x0F) as usize]])
+            }
+        self.line_len += len;
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Cte, Decoder, Encoder};
+    use ::gnitive_multipart::{Headers};
+
+    /// Encode and decode `data`, written by chunks of `chunk` bytes
+    fn encode(cte: Cte, data: &[u8], chunk: usize) -> Vec<u8>
+    {
+        let mut encoder = Encoder::new(cte);
+        let mut result: Vec<u8> = vec![];
+        for data in data.chunks(chunk)
+            {
+                encoder.update(data, &mut result);
+            }
+        encoder.finish(&mut result);
+        result
+    }
+
+    fn decode(cte: Cte, data: &[u8], chunk: usize) -> Result<Vec<u8>, ::std::io::Error>
+    {
+        let mut decoder = Decoder::new(cte);
+        let mut result: Vec<u8> = vec![];
+        for data in data.chunks(chunk)
+            {
+                decoder.update(data, &mut result)?;
+            }
+        decoder.finish(&mut result)?;
+        Ok(result)
+    }
+
+    #[test]
+    fn base64() -> ()
+    {
+        for &(data, encoded) in &[(&b""[..], &b""[..]), (b"f", b"Zg=="), (b"fo", b"Zm8="), (b"foo", b"Zm9v"), (b"foob", b"Zm9vYg=="), (b"\x00\xFF\xFE", b"AP/+")]
+            {
+                for chunk in 1..5
+                    {
+                        assert_eq!(encoded.to_vec(), encode(Cte::Base64, data, chunk));
+                        assert_eq!(data.to_vec(), decode(Cte::Base64, encoded, chunk).unwrap());
+                    }
+            }
+        assert_eq!(b"foob".to_vec(), decode(Cte::Base64, b"Zm9v\r\nYg==\r\n", 3).unwrap());
+
+        // lines of 76 symbols (RFC 2045, 6.8), no line break at end
+        let data: Vec<u8> = (0..=255u8).cycle().take(600).collect();
+        for &chunk in &[1, 7, data.len()]
+            {
+                let encoded = encode(Cte::Base64, &data, chunk);
+                let lines: Vec<&[u8]> = encoded.split(|&c| c == b'\n').collect();
+                assert_eq!(11, lines.len());
+                assert!(lines[..10].iter().all(|line| line.len() == 76 + 1 && line.ends_with(b"\r")));
+                assert_eq!(800 - 10 * 76, lines[10].len());
+                assert_eq!(data, decode(Cte::Base64, &encoded, chunk).unwrap());
+            }
+        assert!(decode(Cte::Base64, b"Zm9v!", 1).is_err());
+        assert!(decode(Cte::Base64, b"Zm9vY", 1).is_err());
+        assert!(decode(Cte::Base64, b"Zg==Zg", 1).is_err());
+    }
+
+    #[test]
+    fn quoted_printable() -> ()
+    {
+        let data: Vec<u8> = (0..=255u8).cycle().take(600).collect();
+        for chunk in 1..5
+            {
+                let encoded = encode(Cte::QuotedPrintable, &data, chunk);
+                assert!(encoded.split(|&c| c == b'\n').all(|line| line.len() <= 76 + 1));
+                assert_eq!(data, decode(Cte::QuotedPrintable, &encoded, chunk).unwrap());
+            }
+        assert_eq!(b"a=b c\r\nd".to_vec(), decode(Cte::QuotedPrintable, b"a=3Db=\r\n c=0D=0A=\nd", 1).unwrap());
+        // invalid and incomplete escapes are kept as is
+        for &chunk in &[1, 2, 64]
+            {
+                assert_eq!(b"=ZZ =4Z ==4a=\rX=4".to_vec(), decode(Cte::QuotedPrintable, b"=ZZ =4Z ===34a=\rX=4", chunk).unwrap());
+                assert_eq!(b"\xab\xcd".to_vec(), decode(Cte::QuotedPrintable, b"=ab=Cd=", chunk).unwrap());
+            }
+    }
+
+    #[test]
+    fn from_headers() -> ()
+    {
+        let cte = |value: &str| Cte::from_headers(&Headers::new(&vec![format!("Content-Transfer-Encoding: {}", value)]));
+        assert_eq!(Cte::Base64, cte("BASE64"));
+        assert_eq!(Cte::QuotedPrintable, cte("quoted-printable"));
+        assert_eq!(Cte::Binary, cte("8bit"));
+        assert_eq!(Cte::Binary, cte("x-uuencode"));
+        assert_eq!(None, Cte::parse("x-uuencode"));
+        assert_eq!(Some(Cte::Binary), Cte::parse(" 7BIT "));
+        assert_eq!(Cte::Binary, Cte::from_headers(&Headers::new(&vec![])));
+    }
+}
\ No newline at end of file
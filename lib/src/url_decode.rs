@@ -0,0 +1,90 @@
+//! Decode percent-encoded (`%XX`) strings, used by `Headers::decode_param_names`, extended header parameters
+//! (`filename*`) and generated code of `gnitive-multipart-derive` (`form_decode` attribute).
+
+/// Decode `%XX` sequences in `s`.
+/// Invalid sequences are left as is, `s` is returned unchanged if result is not valid UTF-8.
+pub fn percent_decode(s: &str) -> String
+{
+    match String::from_utf8(percent_decode_bytes(s))
+        {
+            Ok(decoded) => decoded,
+            Err(_) => s.to_string()
+        }
+}
+
+/// Decode `%XX` sequences in `s` to bytes, invalid sequences are left as is
+fn percent_decode_bytes(s: &str) -> Vec<u8>
+{
+    let bytes = s.as_bytes();
+    let mut result: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut pos: usize = 0;
+    while pos < bytes.len()
+        {
+            if bytes[pos] == b'%' && pos + 2 < bytes.len()
+                {
+                    let high = (bytes[pos + 1] as char).to_digit(16);
+                    let low = (bytes[pos + 2] as char).to_digit(16);
+                    if let (Some(high), Some(low)) = (high, low)
+                        {
+                            result.push((high * 16 + low) as u8);
+                            pos += 3;
+                            continue;
+                        }
+                }
+            result.push(bytes[pos]);
+            pos += 1;
+        }
+    result
+}
+
+/// Decode extended parameter value `<charset>'<language>'<value>` of RFC 5987 (ex.: `UTF-8''%e2%82%ac.txt` of `filename*`).
+/// Charsets `UTF-8` and `ISO-8859-1` are supported (ignoring case). Return `None` for other charsets, value without
+/// two `'` or not valid UTF-8.
+pub fn ext_value_decode(s: &str) -> Option<String>
+{
+    let parts: Vec<&str> = s.splitn(3, '\'').collect();
+    if parts.len() != 3
+        {
+            return None;
+        }
+    let bytes = percent_decode_bytes(parts[2]);
+    match parts[0].to_lowercase().as_str()
+        {
+            "utf-8" => String::from_utf8(bytes).ok(),
+            "iso-8859-1" => Some(bytes.iter().map(|&c| c as char).collect()),
+            _ => None
+        }
+}
+
+/// Decode `application/x-www-form-urlencoded` value: `+` to space, then `%XX` sequences (see `percent_decode`)
+pub fn form_decode(s: &str) -> String
+{
+    percent_decode(&s.replace('+', " "))
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::{percent_decode, form_decode, ext_value_decode};
+
+    #[test]
+    fn decode() -> ()
+    {
+        assert_eq!("a+b c", percent_decode("a+b%20c"));
+        assert_eq!("a b c", form_decode("a+b%20c"));
+        assert_eq!("a+b", form_decode("a%2Bb"));
+        assert_eq!("%zz%4", form_decode("%zz%4"));
+        assert_eq!("%FF+", percent_decode("%FF+"));
+    }
+
+    #[test]
+    fn ext_value() -> ()
+    {
+        assert_eq!(Some("\u{20ac} rates.txt".to_string()), ext_value_decode("UTF-8''%e2%82%ac%20rates.txt"));
+        assert_eq!(Some("\u{a3}.txt".to_string()), ext_value_decode("iso-8859-1'en'%A3.txt"));
+        assert_eq!(None, ext_value_decode("UTF-8''%FF.txt"));
+        assert_eq!(None, ext_value_decode("KOI8-R''%C1.txt"));
+        assert_eq!(None, ext_value_decode("a.txt"));
+    }
+}
\ No newline at end of file
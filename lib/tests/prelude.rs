@@ -0,0 +1,57 @@
+//! Parse with items imported from `gnitive_multipart::prelude` only
+
+#![feature(try_from)]
+extern crate gnitive_multipart;
+
+use std::cell::{RefCell};
+use std::convert::{TryFrom};
+use std::io::{Write, Error};
+use std::rc::{Rc};
+
+use gnitive_multipart::prelude::*;
+
+
+/// Count errors, ignore data
+struct Counter
+{
+    errors: usize
+}
+
+impl MultipartParserTarget for Counter
+{
+    fn error(&mut self, _error: &MultipartParseError) -> Result<OnError, Error>
+    {
+        self.errors += 1;
+        Ok(OnError::ContinueWithoutError)
+    }
+}
+
+impl MultipartParserTargetGenerated for Counter
+{
+    fn get_all_required(&self) -> Vec<String> { vec![] }
+
+    fn content_parser_generated(&mut self, _self_: &Rc<RefCell<Self>>, headers: &Headers) -> Option<Rc<RefCell<ProcessContent>>>
+    {
+        match headers.get_name().map(|name| name.as_str())
+            {
+                Some("count") => Some(Rc::new(RefCell::new(DefaultProcessor::new(ProcessParams::new("count", Some(16)))))),
+                _ => Some(Rc::new(RefCell::new(NullProcessor::new())))
+            }
+    }
+}
+
+#[test]
+fn prelude() -> ()
+{
+    let mut multipart_parser = MultipartParser::new_owned("boundary", Counter { errors: 0 });
+    multipart_parser.write_all(b"--boundary\r\nContent-Disposition: form-data; name=\"count\"\r\n\r\n42\r\n--boundary\r\nContent-Disposition: form-data; name=\"other\"\r\n\r\nx\r\n--boundary--\r\n").unwrap();
+    assert_eq!(0, multipart_parser.into_target().errors);
+
+    let processor = DefaultProcessor::with_raw_data(ProcessParams::new("count", None), b"4x".to_vec());
+    let error = i32::try_from(&processor).unwrap_err();
+    match error.to_multipart_parse_error("count".to_string(), processor.raw_data())
+        {
+            MultipartParseError::ParseIntError(ref name, _, _) => assert_eq!("count", name),
+            _ => panic!("ParseIntError expected")
+        }
+}
\ No newline at end of file